@@ -0,0 +1,71 @@
+use std::fmt;
+
+use crate::json_result;
+
+/// An evaluated expression together with its result.
+///
+/// Callers that show, record or serialize results use this type, so every
+/// place formats a calculation the same way.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::Calculation;
+///
+/// let calculation = Calculation::new(" 5 + 5 ", 10.0);
+/// assert_eq!(calculation.input, "5 + 5");
+/// assert_eq!(calculation.to_string(), "5 + 5 = 10");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Calculation {
+    /// The expression as entered, without surrounding whitespace
+    pub input: String,
+    /// The value the expression evaluated to
+    pub result: f64,
+}
+
+impl Calculation {
+    /// Creates a calculation, trimming whitespace around `input`.
+    pub fn new(input: &str, result: f64) -> Calculation {
+        Calculation {
+            input: input.trim().to_string(),
+            result,
+        }
+    }
+
+    /// Formats the calculation as a JSON object (see [`json_result`]).
+    pub fn to_json(&self) -> String {
+        json_result(&self.input, &Ok(self.result))
+    }
+}
+
+impl fmt::Display for Calculation {
+    /// Writes the calculation as `input = result`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {}", self.input, self.result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_trims_input() {
+        let calculation = Calculation::new("  2 * 3\n", 6.0);
+        assert_eq!(calculation.input, "2 * 3");
+        assert_eq!(calculation.result, 6.0);
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Calculation::new("1 / 4", 0.25).to_string(), "1 / 4 = 0.25");
+    }
+
+    #[test]
+    fn test_to_json() {
+        assert_eq!(
+            Calculation::new("5 + 5", 10.0).to_json(),
+            r#"{"expression":"5 + 5","result":10.0}"#
+        );
+    }
+}
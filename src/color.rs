@@ -0,0 +1,93 @@
+use std::ffi::OsStr;
+
+/// ANSI sequence that resets the text color.
+const RESET: &str = "\x1b[0m";
+
+/// Colors used for calculator output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    /// Used for results
+    Green,
+    /// Used for errors
+    Red,
+}
+
+impl Color {
+    /// The ANSI escape sequence that switches to this color.
+    fn code(self) -> &'static str {
+        match self {
+            Color::Green => "\x1b[32m",
+            Color::Red => "\x1b[31m",
+        }
+    }
+}
+
+/// Wraps `text` in the ANSI escape codes for `color`.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::{colorize, Color};
+///
+/// assert_eq!(colorize("10", Color::Green), "\x1b[32m10\x1b[0m");
+/// ```
+pub fn colorize(text: &str, color: Color) -> String {
+    format!("{}{}{}", color.code(), text, RESET)
+}
+
+/// Decides whether output should be colored.
+///
+/// Colors are used only on a terminal, and never if they were turned off
+/// with `--no-color` or by setting the `NO_COLOR` environment variable to a
+/// non-empty value (see <https://no-color.org>).
+///
+/// # Arguments
+/// * `no_color_flag` - Whether `--no-color` was given
+/// * `no_color_env` - The value of `NO_COLOR`, if it is set
+/// * `is_terminal` - Whether the output goes to a terminal
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::color_enabled;
+///
+/// assert!(color_enabled(false, None, true));
+/// assert!(!color_enabled(false, None, false));
+/// ```
+pub fn color_enabled(no_color_flag: bool, no_color_env: Option<&OsStr>, is_terminal: bool) -> bool {
+    let no_color_env = no_color_env.is_some_and(|value| !value.is_empty());
+    is_terminal && !no_color_flag && !no_color_env
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_colorize() {
+        assert_eq!(colorize("10", Color::Green), "\x1b[32m10\x1b[0m");
+        assert_eq!(
+            colorize("Error: oops", Color::Red),
+            "\x1b[31mError: oops\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_color_enabled_on_terminal() {
+        assert!(color_enabled(false, None, true));
+        assert!(color_enabled(false, Some(OsStr::new("")), true));
+    }
+
+    #[test]
+    fn test_color_disabled_without_terminal() {
+        assert!(!color_enabled(false, None, false));
+    }
+
+    #[test]
+    fn test_color_disabled_by_flag() {
+        assert!(!color_enabled(true, None, true));
+    }
+
+    #[test]
+    fn test_color_disabled_by_no_color() {
+        assert!(!color_enabled(false, Some(OsStr::new("1")), true));
+    }
+}
@@ -0,0 +1,237 @@
+use std::fmt;
+
+use crate::exact::{Arithmetic, Exact, ExactError};
+use crate::CalcError;
+
+/// A complex number `re + im·i`.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::Complex;
+///
+/// assert_eq!(Complex::new(5.0, 2.0).to_string(), "5 + 2i");
+/// assert_eq!(Complex::new(1.0, -1.0).to_string(), "1 - i");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Complex {
+    /// Real part
+    pub re: f64,
+    /// Imaginary part
+    pub im: f64,
+}
+
+impl Complex {
+    /// Creates the complex number `re + im·i`.
+    pub fn new(re: f64, im: f64) -> Complex {
+        Complex { re, im }
+    }
+
+    /// Checks that both parts of a result are finite numbers.
+    fn finite(self) -> Result<Self, ExactError> {
+        if self.re.is_nan() || self.im.is_nan() {
+            Err(CalcError::NotANumber.into())
+        } else if self.re.is_infinite() || self.im.is_infinite() {
+            Err(CalcError::Overflow.into())
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Fails unless both operands are real, for operations that are only
+    /// defined on real numbers.
+    fn require_real(self, rhs: Self, operator: &str) -> Result<(f64, f64), ExactError> {
+        if self.im == 0.0 && rhs.im == 0.0 {
+            Ok((self.re, rhs.re))
+        } else {
+            Err(
+                CalcError::Domain(format!("{} is not defined for complex numbers", operator))
+                    .into(),
+            )
+        }
+    }
+
+    /// Applies a real operator through [`crate::calculate`], so real-only
+    /// operations behave exactly as they do outside complex mode.
+    fn real(self, rhs: Self, operator: &str) -> Result<Self, ExactError> {
+        let (lhs, rhs) = self.require_real(rhs, operator)?;
+        Ok(Complex::new(crate::calculate(lhs, rhs, operator)?, 0.0))
+    }
+}
+
+impl Arithmetic for Complex {
+    fn from_f64(value: f64) -> Result<Self, ExactError> {
+        Ok(Complex::new(value, 0.0))
+    }
+
+    /// Complex numbers are made of `f64`s, so the integer is rounded.
+    fn from_integer(value: i128) -> Result<Self, ExactError> {
+        Ok(Complex::new(value as f64, 0.0))
+    }
+
+    fn from_exact(exact: &Exact) -> Result<Self, ExactError> {
+        match exact {
+            Exact::Complex(complex) => Ok(*complex),
+            _ => Self::from_f64(exact.approximate().ok_or(ExactError::Unsupported)?),
+        }
+    }
+
+    fn imaginary_unit() -> Result<Self, ExactError> {
+        Ok(Complex::new(0.0, 1.0))
+    }
+
+    fn add(self, rhs: Self) -> Result<Self, ExactError> {
+        Complex::new(self.re + rhs.re, self.im + rhs.im).finite()
+    }
+
+    fn sub(self, rhs: Self) -> Result<Self, ExactError> {
+        Complex::new(self.re - rhs.re, self.im - rhs.im).finite()
+    }
+
+    fn mul(self, rhs: Self) -> Result<Self, ExactError> {
+        Complex::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+        .finite()
+    }
+
+    fn div(self, rhs: Self) -> Result<Self, ExactError> {
+        let norm = rhs.re * rhs.re + rhs.im * rhs.im;
+        if norm == 0.0 {
+            return Err(CalcError::DivisionByZero.into());
+        }
+        Complex::new(
+            (self.re * rhs.re + self.im * rhs.im) / norm,
+            (self.im * rhs.re - self.re * rhs.im) / norm,
+        )
+        .finite()
+    }
+
+    fn floor_div(self, rhs: Self) -> Result<Self, ExactError> {
+        self.real(rhs, "//")
+    }
+
+    fn rem(self, rhs: Self) -> Result<Self, ExactError> {
+        self.real(rhs, "%")
+    }
+
+    /// Raises to a whole-number power by repeated multiplication; between
+    /// real numbers any power is allowed, as with `^`.
+    fn pow(self, rhs: Self) -> Result<Self, ExactError> {
+        if self.im == 0.0 && rhs.im == 0.0 && rhs.re.fract() != 0.0 {
+            return self.real(rhs, "^");
+        }
+        if rhs.im != 0.0 || rhs.re.fract() != 0.0 || rhs.re.abs() > u32::MAX as f64 {
+            return Err(CalcError::Domain(
+                "Complex numbers can only be raised to whole-number powers".to_string(),
+            )
+            .into());
+        }
+
+        let mut exponent = rhs.re.abs() as u32;
+        let mut base = self;
+        let mut power = Complex::new(1.0, 0.0);
+        while exponent > 0 {
+            if exponent % 2 == 1 {
+                power = power.mul(base)?;
+            }
+            base = base.mul(base)?;
+            exponent /= 2;
+        }
+        if rhs.re < 0.0 {
+            Complex::new(1.0, 0.0).div(power)
+        } else {
+            Ok(power)
+        }
+    }
+
+    fn neg(self) -> Result<Self, ExactError> {
+        Ok(Complex::new(-self.re, -self.im))
+    }
+}
+
+impl fmt::Display for Complex {
+    /// Writes the number as `re + im` followed by `i`, leaving out a part
+    /// that is zero and a coefficient of one.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let imaginary = |im: f64| {
+            if im == 1.0 {
+                "i".to_string()
+            } else {
+                format!("{}i", im)
+            }
+        };
+
+        if self.im == 0.0 {
+            write!(f, "{}", self.re)
+        } else if self.re == 0.0 && self.im == -1.0 {
+            write!(f, "-i")
+        } else if self.re == 0.0 {
+            write!(f, "{}", imaginary(self.im))
+        } else if self.im < 0.0 {
+            write!(f, "{} - {}", self.re, imaginary(-self.im))
+        } else {
+            write!(f, "{} + {}", self.re, imaginary(self.im))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add() {
+        let sum = Complex::new(1.0, 2.0).add(Complex::new(3.0, 4.0));
+        assert_eq!(sum, Ok(Complex::new(4.0, 6.0)));
+    }
+
+    #[test]
+    fn test_mul() {
+        let product = Complex::new(1.0, 1.0).mul(Complex::new(1.0, -1.0));
+        assert_eq!(product, Ok(Complex::new(2.0, 0.0)));
+        let square = Complex::new(0.0, 1.0).mul(Complex::new(0.0, 1.0));
+        assert_eq!(square, Ok(Complex::new(-1.0, 0.0)));
+    }
+
+    #[test]
+    fn test_div() {
+        let quotient = Complex::new(2.0, 0.0).div(Complex::new(1.0, 1.0));
+        assert_eq!(quotient, Ok(Complex::new(1.0, -1.0)));
+        assert_eq!(
+            Complex::new(1.0, 0.0).div(Complex::new(0.0, 0.0)),
+            Err(ExactError::Calc(CalcError::DivisionByZero))
+        );
+    }
+
+    #[test]
+    fn test_pow() {
+        let i = Complex::new(0.0, 1.0);
+        assert_eq!(i.pow(Complex::new(2.0, 0.0)), Ok(Complex::new(-1.0, 0.0)));
+        assert_eq!(i.pow(Complex::new(4.0, 0.0)), Ok(Complex::new(1.0, 0.0)));
+        assert!(matches!(
+            i.pow(Complex::new(0.5, 0.0)),
+            Err(ExactError::Calc(CalcError::Domain(_)))
+        ));
+    }
+
+    #[test]
+    fn test_real_only_operators() {
+        let remainder = Complex::new(7.0, 0.0).rem(Complex::new(2.0, 0.0));
+        assert_eq!(remainder, Ok(Complex::new(1.0, 0.0)));
+        assert!(matches!(
+            Complex::new(7.0, 1.0).floor_div(Complex::new(2.0, 0.0)),
+            Err(ExactError::Calc(CalcError::Domain(_)))
+        ));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Complex::new(5.0, 2.0).to_string(), "5 + 2i");
+        assert_eq!(Complex::new(5.0, -2.5).to_string(), "5 - 2.5i");
+        assert_eq!(Complex::new(0.0, 3.0).to_string(), "3i");
+        assert_eq!(Complex::new(0.0, 1.0).to_string(), "i");
+        assert_eq!(Complex::new(0.0, -1.0).to_string(), "-i");
+        assert_eq!(Complex::new(2.0, 0.0).to_string(), "2");
+    }
+}
@@ -0,0 +1,217 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::session::{parse_currency, parse_precision, parse_prompt, parse_switch};
+use crate::{
+    AngleMode, CalcError, Locale, Settings, DEFAULT_MAX_DEPTH, DEFAULT_MAX_INPUT_LEN,
+    DEFAULT_PROMPT,
+};
+
+/// Defaults read from a configuration file by [`load_config`].
+///
+/// Command-line options take precedence over these values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// How results are displayed
+    pub settings: Settings,
+    /// The unit for trigonometric functions
+    pub angle_mode: AngleMode,
+    /// Which decimal separator is accepted in input
+    pub locale: Locale,
+    /// Whether `x`, `×` and `÷` may be used as operators
+    pub operator_aliases: bool,
+    /// Whether output may be colored; `color = off` works like `--no-color`
+    pub color: bool,
+    /// The prompt of the interactive calculator
+    pub prompt: String,
+    /// Most characters accepted in an input; `--max-input-len` overrides it
+    pub max_input_len: usize,
+    /// How deeply expressions may be nested; `--max-depth` overrides it
+    pub max_depth: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            settings: Settings::default(),
+            angle_mode: AngleMode::default(),
+            locale: Locale::default(),
+            operator_aliases: true,
+            color: true,
+            prompt: DEFAULT_PROMPT.to_string(),
+            max_input_len: DEFAULT_MAX_INPUT_LEN,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+}
+
+impl Config {
+    /// Sets `key` to `value`, as given on one line of the configuration file.
+    fn set(&mut self, key: &str, value: &str) -> Result<(), CalcError> {
+        match key {
+            "precision" => self.settings.precision = parse_precision(value)?,
+            "round_mode" => self.settings.round_mode = value.parse()?,
+            "sep" => self.settings.separators = parse_switch(value)?,
+            "sci" => self.settings.scientific = parse_switch(value)?,
+            "eng" => self.settings.engineering = parse_switch(value)?,
+            "base" => self.settings.base = value.parse()?,
+            "currency" => self.settings.currency = parse_currency(value),
+            "angle_mode" => self.angle_mode = value.parse()?,
+            "locale" => self.locale = value.parse()?,
+            "aliases" => self.operator_aliases = parse_switch(value)?,
+            "color" => self.color = parse_switch(value)?,
+            "prompt" => self.prompt = parse_prompt(value),
+            "max_input_len" => self.max_input_len = parse_limit(value)?,
+            "max_depth" => self.max_depth = parse_limit(value)?,
+            _ => {
+                return Err(CalcError::InvalidCommand(format!(
+                    "Unknown setting '{}'",
+                    key
+                )))
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses the value of a limit such as `max_input_len`, which must be a
+/// positive integer.
+fn parse_limit(value: &str) -> Result<usize, CalcError> {
+    value
+        .parse()
+        .ok()
+        .filter(|limit| *limit > 0)
+        .ok_or_else(|| {
+            CalcError::InvalidCommand(format!("Invalid limit '{}'. Use a positive integer", value))
+        })
+}
+
+/// Reads the configuration file at `path`, such as `~/.calculatorrc`.
+///
+/// Each line is a setting written as `key = value`; blank lines and lines
+/// starting with `#` are skipped. The keys are `precision`, `round_mode`,
+/// `sep`, `sci`, `eng`, `base`, `currency`, `angle_mode`, `locale`, `aliases`,
+/// `color`, `prompt`, `max_input_len` and `max_depth`, and they take the same
+/// values as the session commands or options of the same name, e.g. `precision = 2`,
+/// `angle_mode = deg` or `prompt = "calc> "`.
+///
+/// A missing file yields the defaults. Unknown keys, invalid values and a
+/// file that can't be read are reported as warnings on stderr and otherwise
+/// ignored, so a broken configuration never stops the calculator from
+/// starting.
+pub fn load_config(path: &Path) -> Config {
+    match fs::read_to_string(path) {
+        Ok(contents) => {
+            let (config, warnings) = parse_config(&contents);
+            for warning in warnings {
+                eprintln!("Warning: {}: {}", path.display(), warning);
+            }
+            config
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Config::default(),
+        Err(e) => {
+            eprintln!(
+                "Warning: Could not read configuration from {}: {}",
+                path.display(),
+                e
+            );
+            Config::default()
+        }
+    }
+}
+
+/// Parses the contents of a configuration file, returning the configuration
+/// and a warning for each line that couldn't be applied.
+fn parse_config(contents: &str) -> (Config, Vec<String>) {
+    let mut config = Config::default();
+    let mut warnings = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let result = match line.split_once('=') {
+            Some((key, value)) => config.set(key.trim(), value.trim()),
+            None => Err(CalcError::InvalidCommand(
+                "Expected a setting as key = value".to_string(),
+            )),
+        };
+        if let Err(e) = result {
+            warnings.push(format!("line {}: {}", index + 1, e));
+        }
+    }
+    (config, warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OutputBase, RoundMode};
+
+    #[test]
+    fn test_parse_config() {
+        let contents = "\
+# defaults for the calculator
+precision = 2
+round_mode = up
+
+sep = on
+base = hex
+currency = €
+angle_mode = deg
+locale = eu
+aliases = off
+color = off
+prompt = \"calc> \"
+max_input_len = 500
+max_depth = 64
+";
+        let (config, warnings) = parse_config(contents);
+        assert!(warnings.is_empty());
+        assert_eq!(
+            config,
+            Config {
+                settings: Settings {
+                    precision: Some(2),
+                    separators: true,
+                    base: OutputBase::Hexadecimal,
+                    scientific: false,
+                    engineering: false,
+                    round_mode: RoundMode::Up,
+                    currency: Some("€".to_string()),
+                },
+                angle_mode: AngleMode::Degrees,
+                locale: Locale::Eu,
+                operator_aliases: false,
+                color: false,
+                prompt: "calc> ".to_string(),
+                max_input_len: 500,
+                max_depth: 64,
+            }
+        );
+        assert_eq!(config.settings.format(10.0 / 3.0), "€3.34");
+    }
+
+    #[test]
+    fn test_parse_config_warnings() {
+        let (config, warnings) = parse_config("colour = off\nprecision = lots\nsci\nsci = on");
+        assert_eq!(
+            warnings,
+            [
+                "line 1: Unknown setting 'colour'",
+                "line 2: Invalid precision 'lots'. Use a number of decimal places or 'full'",
+                "line 3: Expected a setting as key = value",
+            ]
+        );
+        assert_eq!(config.settings.precision, None);
+        assert!(config.settings.scientific);
+        assert!(config.color);
+    }
+
+    #[test]
+    fn test_load_missing_config() {
+        let path = std::env::temp_dir().join("rust_calculator_missing_config");
+        assert_eq!(load_config(&path), Config::default());
+    }
+}
@@ -0,0 +1,363 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::parser::UserFunction;
+use crate::{CalcError, Exact, Rng};
+
+/// Unit in which trigonometric functions interpret their argument.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AngleMode {
+    /// Angles are in radians (the default)
+    #[default]
+    Radians,
+    /// Angles are in degrees
+    Degrees,
+}
+
+impl AngleMode {
+    /// Converts an angle in this unit to radians.
+    pub fn to_radians(self, angle: f64) -> f64 {
+        match self {
+            AngleMode::Radians => angle,
+            AngleMode::Degrees => angle.to_radians(),
+        }
+    }
+
+    /// Converts an angle in degrees to this unit.
+    pub fn from_degrees(self, degrees: f64) -> f64 {
+        match self {
+            AngleMode::Radians => degrees.to_radians(),
+            AngleMode::Degrees => degrees,
+        }
+    }
+}
+
+impl FromStr for AngleMode {
+    type Err = CalcError;
+
+    /// Parses the argument of the `mode` command: `deg` or `rad`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rad" => Ok(AngleMode::Radians),
+            "deg" => Ok(AngleMode::Degrees),
+            _ => Err(CalcError::UnknownIdentifier(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for AngleMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AngleMode::Radians => write!(f, "radians"),
+            AngleMode::Degrees => write!(f, "degrees"),
+        }
+    }
+}
+
+/// Number type that expressions are evaluated on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NumberMode {
+    /// Floating-point numbers (the default)
+    #[default]
+    Real,
+    /// Exact integers, falling back to floating point where that isn't
+    /// possible
+    Integer,
+    /// Exact fractions such as `1/3`, falling back to floating point where
+    /// that isn't possible
+    Fraction,
+    /// Complex numbers written with the imaginary unit `i`, as in `2 + 3i`
+    Complex,
+}
+
+impl FromStr for NumberMode {
+    type Err = CalcError;
+
+    /// Parses the argument of the `mode` command: `real`, `int`, `frac` or
+    /// `complex`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "real" => Ok(NumberMode::Real),
+            "int" => Ok(NumberMode::Integer),
+            "frac" => Ok(NumberMode::Fraction),
+            "complex" => Ok(NumberMode::Complex),
+            _ => Err(CalcError::UnknownIdentifier(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for NumberMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NumberMode::Real => write!(f, "real"),
+            NumberMode::Integer => write!(f, "integer"),
+            NumberMode::Fraction => write!(f, "fraction"),
+            NumberMode::Complex => write!(f, "complex"),
+        }
+    }
+}
+
+impl NumberMode {
+    /// Explains that an expression was evaluated with floating point because
+    /// this mode can't evaluate it exactly, or `None` in [`NumberMode::Real`].
+    ///
+    /// # Examples
+    /// ```
+    /// use rust_calculator_cli::NumberMode;
+    ///
+    /// assert_eq!(
+    ///     NumberMode::Integer.fallback_warning().unwrap(),
+    ///     "not exact in integer mode, evaluated with floating point"
+    /// );
+    /// assert_eq!(NumberMode::Real.fallback_warning(), None);
+    /// ```
+    pub fn fallback_warning(self) -> Option<String> {
+        (self != NumberMode::Real)
+            .then(|| format!("not exact in {} mode, evaluated with floating point", self))
+    }
+}
+
+/// Convention for writing decimal numbers in expressions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    /// `.` is the decimal point (the default)
+    #[default]
+    Us,
+    /// `,` is also accepted as the decimal point, as in `5,5`
+    Eu,
+}
+
+impl FromStr for Locale {
+    type Err = CalcError;
+
+    /// Parses the argument of the `locale` command: `us` or `eu`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "us" => Ok(Locale::Us),
+            "eu" => Ok(Locale::Eu),
+            _ => Err(CalcError::InvalidCommand(format!(
+                "Unknown locale '{}'. Use us or eu",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Locale::Us => write!(f, "US"),
+            Locale::Eu => write!(f, "EU"),
+        }
+    }
+}
+
+/// What integer-mode arithmetic does with a result outside the integer width
+/// set with `intwidth`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Report [`CalcError::Overflow`] (the default)
+    #[default]
+    Error,
+    /// Wrap around as unsigned integers of that width do, so `255 + 1` is `0`
+    /// at 8 bits
+    Wrap,
+    /// Stop at the smallest or largest value of that width, so `255 + 1` is
+    /// `255` at 8 bits
+    Saturate,
+}
+
+impl FromStr for Overflow {
+    type Err = CalcError;
+
+    /// Parses the argument of the `overflow` command: `wrap`, `saturate` or
+    /// `error`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(Overflow::Error),
+            "wrap" => Ok(Overflow::Wrap),
+            "saturate" => Ok(Overflow::Saturate),
+            _ => Err(CalcError::InvalidCommand(format!(
+                "Unknown overflow behavior '{}'. Use wrap, saturate or error",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Overflow::Error => write!(f, "error"),
+            Overflow::Wrap => write!(f, "wrap"),
+            Overflow::Saturate => write!(f, "saturate"),
+        }
+    }
+}
+
+/// Number of characters accepted in a line of input by default, see
+/// [`Context::max_input_len`].
+pub const DEFAULT_MAX_INPUT_LEN: usize = 10_000;
+
+/// How deeply expressions may be nested by default, see
+/// [`Context::max_depth`].
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+/// Integer widths accepted by `intwidth`, in bits.
+pub const INT_WIDTHS: [u32; 4] = [8, 16, 32, 64];
+
+/// State that influences how an expression is evaluated.
+///
+/// A `Context` is created once by the caller and passed to every evaluation,
+/// so settings made by one command (e.g. `mode deg`) and assigned variables
+/// apply to the next.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::{evaluate_with, AngleMode, Context};
+///
+/// let context = Context {
+///     angle_mode: AngleMode::Degrees,
+///     ..Context::default()
+/// };
+/// assert_eq!(evaluate_with("sin(90)", &context).unwrap(), 1.0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Context {
+    /// Unit used by `sin`, `cos` and `tan`
+    pub angle_mode: AngleMode,
+    /// Whether a comma may be used as the decimal point
+    pub locale: Locale,
+    /// Number type used for plain expressions
+    pub number_mode: NumberMode,
+    /// Width in bits of the unsigned integers that integer-mode arithmetic
+    /// emulates, one of [`INT_WIDTHS`], as set with `intwidth`. Without one,
+    /// integers are only limited by `i128`.
+    pub int_width: Option<u32>,
+    /// What happens to integer-mode results outside `int_width`
+    pub overflow: Overflow,
+    /// Result of the previous calculation, available as `ans`.
+    /// It is `0` until a calculation has succeeded.
+    pub ans: f64,
+    /// The previous result if it was evaluated exactly, which `ans` stands
+    /// for in the exact number modes instead of its rounded [`Context::ans`].
+    /// [`Context::set_ans`] clears it.
+    pub exact_ans: Option<Exact>,
+    /// The memory register changed by `M+`, `M-` and `MC` and available as
+    /// `mr`. It is `0` while empty.
+    pub memory: f64,
+    /// Variables assigned with `name = expression`
+    pub variables: HashMap<String, f64>,
+    /// Functions defined with `define name(x) = expression`
+    pub functions: HashMap<String, UserFunction>,
+    /// Whether `inf` may be used as a value, as enabled by `--allow-inf` or
+    /// `mode sci`
+    pub allow_infinity: bool,
+    /// Generator used by `rand`, seeded with `--seed` for reproducible results
+    pub rng: Rng,
+    /// Whether `x` between two operands, `×` and `÷` are read as `*` and `/`,
+    /// as switched by `aliases on|off`. It is on by default; turn it off so
+    /// that `x` is always a variable.
+    pub operator_aliases: bool,
+    /// Most characters accepted in an input, as set with `--max-input-len`.
+    /// Longer input is rejected with [`CalcError::InputTooLong`] before it is
+    /// tokenized, so pathological input can't exhaust resources.
+    pub max_input_len: usize,
+    /// How deeply parentheses, function calls, operators such as `-` and `^`
+    /// and chains such as `1 + 2 + 3` may be nested, as set with
+    /// `--max-depth`. Deeper expressions are rejected with
+    /// [`CalcError::TooDeeplyNested`] instead of overflowing the stack.
+    pub max_depth: usize,
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Context {
+            angle_mode: AngleMode::default(),
+            locale: Locale::default(),
+            number_mode: NumberMode::default(),
+            int_width: None,
+            overflow: Overflow::default(),
+            ans: 0.0,
+            exact_ans: None,
+            memory: 0.0,
+            variables: HashMap::new(),
+            functions: HashMap::new(),
+            allow_infinity: false,
+            rng: Rng::default(),
+            operator_aliases: true,
+            max_input_len: DEFAULT_MAX_INPUT_LEN,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+}
+
+impl Context {
+    /// Makes `value` the new `ans`, forgetting any exact previous result.
+    pub fn set_ans(&mut self, value: f64) {
+        self.ans = value;
+        self.exact_ans = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_angle_mode_defaults_to_radians() {
+        assert_eq!(Context::default().angle_mode, AngleMode::Radians);
+    }
+
+    #[test]
+    fn test_ans_defaults_to_zero() {
+        assert_eq!(Context::default().ans, 0.0);
+    }
+
+    #[test]
+    fn test_angle_mode_from_str() {
+        assert_eq!("deg".parse(), Ok(AngleMode::Degrees));
+        assert_eq!("rad".parse(), Ok(AngleMode::Radians));
+        assert!("grad".parse::<AngleMode>().is_err());
+    }
+
+    #[test]
+    fn test_overflow_from_str() {
+        assert_eq!("wrap".parse(), Ok(Overflow::Wrap));
+        assert_eq!("saturate".parse(), Ok(Overflow::Saturate));
+        assert_eq!("error".parse(), Ok(Overflow::Error));
+        assert!("clamp".parse::<Overflow>().is_err());
+        assert_eq!(Context::default().overflow, Overflow::Error);
+    }
+
+    #[test]
+    fn test_number_mode_from_str() {
+        assert_eq!("int".parse(), Ok(NumberMode::Integer));
+        assert_eq!("real".parse(), Ok(NumberMode::Real));
+        assert_eq!("frac".parse(), Ok(NumberMode::Fraction));
+        assert_eq!("complex".parse(), Ok(NumberMode::Complex));
+        assert!("deg".parse::<NumberMode>().is_err());
+        assert_eq!(Context::default().number_mode, NumberMode::Real);
+    }
+
+    #[test]
+    fn test_locale_from_str() {
+        assert_eq!("us".parse(), Ok(Locale::Us));
+        assert_eq!("eu".parse(), Ok(Locale::Eu));
+        assert!("de".parse::<Locale>().is_err());
+        assert_eq!(Context::default().locale, Locale::Us);
+    }
+
+    #[test]
+    fn test_angle_mode_to_radians() {
+        assert_eq!(AngleMode::Radians.to_radians(1.5), 1.5);
+        assert_eq!(AngleMode::Degrees.to_radians(180.0), std::f64::consts::PI);
+    }
+
+    #[test]
+    fn test_angle_mode_from_degrees() {
+        assert_eq!(AngleMode::Radians.from_degrees(180.0), std::f64::consts::PI);
+        assert_eq!(AngleMode::Degrees.from_degrees(90.0), 90.0);
+    }
+}
@@ -0,0 +1,43 @@
+/// Joins `fields` into one line of CSV.
+///
+/// A field that contains a comma, a double quote or a line break is put in
+/// double quotes, with any double quotes inside it doubled, so
+/// `max(1, 2)` becomes `"max(1, 2)"`.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::csv_row;
+///
+/// assert_eq!(csv_row(&["5 + 5", "10"]), "5 + 5,10");
+/// assert_eq!(csv_row(&["max(1, 2)", "2"]), r#""max(1, 2)",2"#);
+/// ```
+pub fn csv_row(fields: &[&str]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_row_plain_fields() {
+        assert_eq!(csv_row(&["expression", "result"]), "expression,result");
+        assert_eq!(csv_row(&["2 ^ 10", "1024"]), "2 ^ 10,1024");
+    }
+
+    #[test]
+    fn test_csv_row_quotes_fields() {
+        assert_eq!(csv_row(&["log(8, 2)", "3"]), "\"log(8, 2)\",3");
+        assert_eq!(csv_row(&["say \"hi\"", "1"]), "\"say \"\"hi\"\"\",1");
+    }
+}
@@ -0,0 +1,228 @@
+use std::fmt;
+
+use crate::OPERATORS;
+
+/// Errors that can occur while parsing or evaluating a calculation.
+///
+/// Each variant describes a distinct failure mode, so callers can match on
+/// the kind of error instead of inspecting its message.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::{calculate, CalcError};
+///
+/// match calculate(5.0, 0.0, "/") {
+///     Err(CalcError::DivisionByZero) => println!("Nice try"),
+///     other => panic!("unexpected result: {:?}", other),
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalcError {
+    /// The input is empty or doesn't have the expected shape
+    InvalidInput,
+    /// The input has more characters than the limit, which is given, see
+    /// [`Context::max_input_len`](crate::Context::max_input_len)
+    InputTooLong(usize),
+    /// Parentheses, function calls, operators such as `-` and `^` or chains
+    /// such as `1 + 2 + 3` are nested deeper than the limit, which is given, see
+    /// [`Context::max_depth`](crate::Context::max_depth)
+    TooDeeplyNested(usize),
+    /// The operator is not supported
+    InvalidOperator {
+        operator: String,
+        /// Byte offset of the operator in the input, if known
+        position: Option<usize>,
+    },
+    /// A token that should be a number can't be parsed as one
+    ParseNumber {
+        token: String,
+        /// Byte offset of the token in the input, if known
+        position: Option<usize>,
+    },
+    /// Division (or remainder) by zero was attempted
+    DivisionByZero,
+    /// A calculation on finite numbers produced a result too large for an
+    /// `f64`, or an integer outside the width set with `intwidth`
+    Overflow,
+    /// A number or result is NaN, which the calculator can't work with
+    NotANumber,
+    /// A `(` has no matching `)` or vice versa
+    UnmatchedParenthesis,
+    /// A pair of parentheses contains nothing, as in `()`
+    EmptyParentheses,
+    /// A token appears where it isn't allowed, e.g. the second `+` in `2 + + 3`
+    UnexpectedToken {
+        token: String,
+        /// Byte offset of the token in the input, if known
+        position: Option<usize>,
+    },
+    /// The input ended where more was expected, e.g. `2 +`
+    UnexpectedEnd,
+    /// A name that isn't a known function, constant or variable
+    UnknownIdentifier(String),
+    /// A name that can't be assigned to, e.g. `1x` or `pi`
+    InvalidVariableName(String),
+    /// A function was called with a number of arguments it doesn't accept
+    ArgumentCount { function: String, found: usize },
+    /// A REPL command is malformed, e.g. an unknown mode
+    InvalidCommand(String),
+    /// The operation is mathematically undefined for its operands
+    Domain(String),
+    /// An operator in RPN input found fewer than two values on the stack
+    InsufficientOperands(String),
+    /// RPN input left more than one value on the stack
+    TooManyOperands(usize),
+}
+
+impl CalcError {
+    /// The byte offset in the input where the error was found, if known.
+    ///
+    /// # Examples
+    /// ```
+    /// use rust_calculator_cli::parse_input;
+    ///
+    /// let error = parse_input("5 $ 3").unwrap_err();
+    /// assert_eq!(error.position(), Some(2));
+    /// assert!(error.to_string().starts_with("Invalid operator '$' at position 2"));
+    /// ```
+    pub fn position(&self) -> Option<usize> {
+        match self {
+            CalcError::InvalidOperator { position, .. }
+            | CalcError::ParseNumber { position, .. }
+            | CalcError::UnexpectedToken { position, .. } => *position,
+            _ => None,
+        }
+    }
+
+    /// Records that the error was found at byte offset `at`, unless it
+    /// already has a position or is an error without one.
+    pub(crate) fn at(mut self, at: usize) -> CalcError {
+        if let CalcError::InvalidOperator { position, .. }
+        | CalcError::ParseNumber { position, .. }
+        | CalcError::UnexpectedToken { position, .. } = &mut self
+        {
+            position.get_or_insert(at);
+        }
+        self
+    }
+
+    /// Moves the position by `offset` bytes, for errors in a part of a longer
+    /// input, such as the right-hand side of an assignment.
+    pub(crate) fn shifted(mut self, offset: usize) -> CalcError {
+        if let CalcError::InvalidOperator { position, .. }
+        | CalcError::ParseNumber { position, .. }
+        | CalcError::UnexpectedToken { position, .. } = &mut self
+        {
+            if let Some(position) = position {
+                *position += offset;
+            }
+        }
+        self
+    }
+
+    /// Moves the position back by `offset` bytes, for errors in an input that
+    /// was extended at the front before parsing.
+    pub(crate) fn shifted_back(mut self, offset: usize) -> CalcError {
+        if let CalcError::InvalidOperator { position, .. }
+        | CalcError::ParseNumber { position, .. }
+        | CalcError::UnexpectedToken { position, .. } = &mut self
+        {
+            if let Some(position) = position {
+                *position = position.saturating_sub(offset);
+            }
+        }
+        self
+    }
+}
+
+/// Formats the ` at position N` suffix of an error message.
+fn at_position(position: &Option<usize>) -> String {
+    match position {
+        Some(position) => format!(" at position {}", position),
+        None => String::new(),
+    }
+}
+
+impl fmt::Display for CalcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalcError::InvalidInput => write!(f, "Invalid input"),
+            CalcError::InputTooLong(max) => {
+                write!(f, "Input too long: the limit is {} characters", max)
+            }
+            CalcError::TooDeeplyNested(max) => {
+                write!(
+                    f,
+                    "Expression too deeply nested: the limit is {} levels",
+                    max
+                )
+            }
+            CalcError::InvalidOperator { operator, position } => {
+                write!(
+                    f,
+                    "Invalid operator '{}'{}. Use {}",
+                    operator,
+                    at_position(position),
+                    OPERATORS.join(", ")
+                )
+            }
+            CalcError::ParseNumber { token, position } => {
+                write!(f, "Invalid number '{}'{}", token, at_position(position))
+            }
+            CalcError::DivisionByZero => write!(f, "Cannot divide by zero"),
+            CalcError::Overflow => write!(f, "Result overflowed"),
+            CalcError::NotANumber => write!(f, "NaN is not a valid number"),
+            CalcError::UnmatchedParenthesis => write!(f, "Unmatched parenthesis"),
+            CalcError::EmptyParentheses => write!(f, "Empty parentheses"),
+            CalcError::UnexpectedToken { token, position } => {
+                write!(f, "Unexpected {}{}", token, at_position(position))
+            }
+            CalcError::UnexpectedEnd => write!(f, "Unexpected end of input"),
+            CalcError::UnknownIdentifier(name) => write!(f, "Unknown identifier: {}", name),
+            CalcError::InvalidVariableName(name) => {
+                write!(f, "Invalid variable name: '{}'", name)
+            }
+            CalcError::ArgumentCount { function, found } => {
+                write!(f, "Wrong number of arguments for {}: {}", function, found)
+            }
+            CalcError::InvalidCommand(message) => write!(f, "{}", message),
+            CalcError::Domain(message) => write!(f, "{}", message),
+            CalcError::InsufficientOperands(operator) => {
+                write!(f, "Insufficient operands for '{}'", operator)
+            }
+            CalcError::TooManyOperands(count) => {
+                write!(f, "Too many operands: {} values are left over", count)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CalcError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_without_position() {
+        let error = CalcError::ParseNumber {
+            token: "1e".to_string(),
+            position: None,
+        };
+        assert_eq!(error.to_string(), "Invalid number '1e'");
+    }
+
+    #[test]
+    fn test_display_with_position() {
+        let error = CalcError::ParseNumber {
+            token: "1e".to_string(),
+            position: None,
+        }
+        .at(4);
+        assert_eq!(error.to_string(), "Invalid number '1e' at position 4");
+        assert_eq!(error.clone().at(9).position(), Some(4));
+        assert_eq!(error.clone().shifted(3).position(), Some(7));
+        assert_eq!(error.shifted_back(3).position(), Some(1));
+        assert_eq!(CalcError::DivisionByZero.at(1).position(), None);
+    }
+}
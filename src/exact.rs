@@ -0,0 +1,540 @@
+//! Evaluation in the integer, fraction and complex number modes.
+//!
+//! Instead of `f64`, the [`Expr`] tree from the [`parser`](crate::parser) is
+//! evaluated on a type implementing [`Arithmetic`]. Only the arithmetic core
+//! is supported here: numbers, names, parentheses, negation and the operators
+//! `+`, `-`, `*`, `/`, `//`, `%` and `^`, including implicit multiplication
+//! such as `2(3 + 4)`.
+//!
+//! Anything beyond that, such as function calls, or a value the type can't
+//! represent makes the expression [`Unsupported`](ExactError::Unsupported),
+//! and the caller falls back to evaluating it with `f64`. Malformed input is
+//! reported as unsupported too, so the `f64` parser produces the error.
+//!
+//! Integer literals beyond 2^53 are read from their digits rather than as an
+//! `f64`, and `ans` is the previous exact result if there is one, so neither
+//! is rounded on the way.
+//!
+//! In complex mode the identifier `i` is the imaginary unit, and a number
+//! directly followed by `i`, as in `3i`, is an imaginary number.
+//!
+//! In integer mode with an `intwidth`, every value and intermediate result is
+//! brought into the range of an unsigned integer of that width as chosen by
+//! [`Context::overflow`]: wrapped around, saturated, or reported as an error.
+
+use std::fmt;
+
+use crate::parser::{parse_ast, Expr, UnaryOperator};
+use crate::{
+    check_input_length, constant, CalcError, Complex, Context, Fraction, NumberMode, Overflow,
+};
+
+/// Result of evaluating an expression in a number mode other than
+/// [`NumberMode::Real`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Exact {
+    /// An integer, from [`NumberMode::Integer`]
+    Integer(i128),
+    /// A fraction, from [`NumberMode::Fraction`]
+    Fraction(Fraction),
+    /// A complex number, from [`NumberMode::Complex`]
+    Complex(Complex),
+}
+
+impl Exact {
+    /// The value as an `f64`, which may lose precision, or `None` for a
+    /// complex number with an imaginary part.
+    pub fn approximate(&self) -> Option<f64> {
+        match self {
+            Exact::Integer(integer) => Some(*integer as f64),
+            Exact::Fraction(fraction) => Some(fraction.to_f64()),
+            Exact::Complex(complex) => (complex.im == 0.0).then_some(complex.re),
+        }
+    }
+}
+
+impl fmt::Display for Exact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Exact::Integer(integer) => write!(f, "{}", integer),
+            Exact::Fraction(fraction) => write!(f, "{}", fraction),
+            Exact::Complex(complex) => write!(f, "{}", complex),
+        }
+    }
+}
+
+/// Why an expression couldn't be evaluated exactly.
+#[derive(Debug, PartialEq)]
+pub(crate) enum ExactError {
+    /// The expression needs something the number type can't do, so it should
+    /// be evaluated with `f64` instead
+    Unsupported,
+    /// The expression is invalid in this number mode as well as with `f64`
+    Calc(CalcError),
+}
+
+impl From<CalcError> for ExactError {
+    fn from(error: CalcError) -> Self {
+        ExactError::Calc(error)
+    }
+}
+
+/// A number type that expressions can be evaluated on exactly.
+pub(crate) trait Arithmetic: Sized {
+    /// Converts a number literal, variable or constant.
+    fn from_f64(value: f64) -> Result<Self, ExactError>;
+    /// Converts an integer literal beyond 2^53, see [`Expr::Integer`].
+    fn from_integer(value: i128) -> Result<Self, ExactError>;
+    /// Converts a previous exact result, used for `ans`. By default it goes
+    /// through its `f64` approximation.
+    fn from_exact(exact: &Exact) -> Result<Self, ExactError> {
+        Self::from_f64(exact.approximate().ok_or(ExactError::Unsupported)?)
+    }
+    /// The imaginary unit `i`, for types that have one.
+    fn imaginary_unit() -> Result<Self, ExactError> {
+        Err(ExactError::Unsupported)
+    }
+    fn add(self, rhs: Self) -> Result<Self, ExactError>;
+    fn sub(self, rhs: Self) -> Result<Self, ExactError>;
+    fn mul(self, rhs: Self) -> Result<Self, ExactError>;
+    fn div(self, rhs: Self) -> Result<Self, ExactError>;
+    fn floor_div(self, rhs: Self) -> Result<Self, ExactError>;
+    fn rem(self, rhs: Self) -> Result<Self, ExactError>;
+    fn pow(self, rhs: Self) -> Result<Self, ExactError>;
+    fn neg(self) -> Result<Self, ExactError>;
+    /// Brings a value into the range allowed by `context`, for types with a
+    /// limited range such as integers after `intwidth`.
+    fn fit(self, _context: &Context) -> Result<Self, ExactError> {
+        Ok(self)
+    }
+    /// Raises to a power like [`Arithmetic::pow`], fitting the result into
+    /// the range allowed by `context`.
+    fn fitted_pow(self, rhs: Self, context: &Context) -> Result<Self, ExactError> {
+        self.pow(rhs)?.fit(context)
+    }
+}
+
+impl Arithmetic for i128 {
+    /// Accepts whole numbers up to 2^53 only, since larger values may
+    /// already have been rounded.
+    fn from_f64(value: f64) -> Result<Self, ExactError> {
+        if value.fract() == 0.0 && value.abs() <= 2f64.powi(53) {
+            Ok(value as i128)
+        } else {
+            Err(ExactError::Unsupported)
+        }
+    }
+
+    fn from_integer(value: i128) -> Result<Self, ExactError> {
+        Ok(value)
+    }
+
+    fn from_exact(exact: &Exact) -> Result<Self, ExactError> {
+        match exact {
+            Exact::Integer(integer) => Ok(*integer),
+            _ => Self::from_f64(exact.approximate().ok_or(ExactError::Unsupported)?),
+        }
+    }
+
+    fn add(self, rhs: Self) -> Result<Self, ExactError> {
+        self.checked_add(rhs).ok_or(ExactError::Unsupported)
+    }
+
+    fn sub(self, rhs: Self) -> Result<Self, ExactError> {
+        self.checked_sub(rhs).ok_or(ExactError::Unsupported)
+    }
+
+    fn mul(self, rhs: Self) -> Result<Self, ExactError> {
+        self.checked_mul(rhs).ok_or(ExactError::Unsupported)
+    }
+
+    /// Divides exactly, failing if there is a remainder.
+    fn div(self, rhs: Self) -> Result<Self, ExactError> {
+        let remainder = self.rem(rhs)?;
+        if remainder != 0 {
+            return Err(CalcError::Domain(format!(
+                "{} / {} is not a whole number (remainder {}); use // or %",
+                self, rhs, remainder
+            ))
+            .into());
+        }
+        self.checked_div(rhs).ok_or(ExactError::Unsupported)
+    }
+
+    fn floor_div(self, rhs: Self) -> Result<Self, ExactError> {
+        let remainder = self.rem(rhs)?;
+        let quotient = self.checked_div(rhs).ok_or(ExactError::Unsupported)?;
+        // Integer division rounds toward zero; round toward negative infinity
+        // instead when the exact quotient is negative.
+        if remainder != 0 && (remainder < 0) != (rhs < 0) {
+            Ok(quotient - 1)
+        } else {
+            Ok(quotient)
+        }
+    }
+
+    /// The remainder takes the sign of `self`, as with `f64`.
+    fn rem(self, rhs: Self) -> Result<Self, ExactError> {
+        if rhs == 0 {
+            return Err(CalcError::DivisionByZero.into());
+        }
+        self.checked_rem(rhs).ok_or(ExactError::Unsupported)
+    }
+
+    fn pow(self, rhs: Self) -> Result<Self, ExactError> {
+        let exponent = u32::try_from(rhs).map_err(|_| ExactError::Unsupported)?;
+        self.checked_pow(exponent).ok_or(ExactError::Unsupported)
+    }
+
+    fn neg(self) -> Result<Self, ExactError> {
+        self.checked_neg().ok_or(ExactError::Unsupported)
+    }
+
+    /// Applies [`Context::overflow`] to values outside the unsigned range of
+    /// [`Context::int_width`] bits.
+    fn fit(self, context: &Context) -> Result<Self, ExactError> {
+        let Some(width) = context.int_width else {
+            return Ok(self);
+        };
+        let max = (1i128 << width) - 1;
+        if (0..=max).contains(&self) {
+            return Ok(self);
+        }
+        match context.overflow {
+            Overflow::Wrap => Ok(self.rem_euclid(max + 1)),
+            Overflow::Saturate => Ok(self.clamp(0, max)),
+            Overflow::Error => Err(CalcError::Overflow.into()),
+        }
+    }
+
+    /// With an integer width, squares and multiplies step by step, fitting
+    /// each step, so that large powers wrap or saturate instead of exceeding
+    /// `i128`. For a base of at least 2 a step only overflows if the whole
+    /// power does.
+    fn fitted_pow(self, rhs: Self, context: &Context) -> Result<Self, ExactError> {
+        if context.int_width.is_none() {
+            return Arithmetic::pow(self, rhs);
+        }
+        let mut exponent = u32::try_from(rhs).map_err(|_| ExactError::Unsupported)?;
+        let mut base = self;
+        let mut result = 1i128.fit(context)?;
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.mul(base)?.fit(context)?;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.mul(base)?.fit(context)?;
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// Evaluates `input` exactly in the number mode of `context`.
+///
+/// Returns `Ok(None)` if the context uses [`NumberMode::Real`] or the
+/// expression has to be evaluated with `f64`.
+pub(crate) fn evaluate_exact(input: &str, context: &Context) -> Result<Option<Exact>, CalcError> {
+    let result = match context.number_mode {
+        NumberMode::Real => return Ok(None),
+        NumberMode::Integer => evaluate::<i128>(input, context).map(Exact::Integer),
+        NumberMode::Fraction => evaluate::<Fraction>(input, context).map(Exact::Fraction),
+        NumberMode::Complex => evaluate::<Complex>(input, context).map(Exact::Complex),
+    };
+
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(ExactError::Unsupported) => Ok(None),
+        Err(ExactError::Calc(error)) => Err(error),
+    }
+}
+
+/// Evaluates `input` on the number type `T`.
+fn evaluate<T: Arithmetic>(input: &str, context: &Context) -> Result<T, ExactError> {
+    check_input_length(input, context)?;
+    let expr = parse_ast(input, context).map_err(|_| ExactError::Unsupported)?;
+    Evaluator { context }.value(&expr)
+}
+
+/// Walks an [`Expr`], evaluating it on a number type.
+struct Evaluator<'a> {
+    context: &'a Context,
+}
+
+impl Evaluator<'_> {
+    fn value<T: Arithmetic>(&self, expr: &Expr) -> Result<T, ExactError> {
+        let value = match expr {
+            Expr::Num(number) => T::from_f64(*number)?,
+            Expr::Integer(integer) => T::from_integer(*integer)?,
+            Expr::Ident(name) => self.identifier(name)?,
+            Expr::Group(inner) => return self.value(inner),
+            Expr::UnaryOp {
+                op: UnaryOperator::Neg,
+                operand,
+            } => self.value::<T>(operand)?.neg()?,
+            Expr::BinaryOp { op, lhs, rhs } => {
+                let lhs = self.value::<T>(lhs)?;
+                let rhs = self.value::<T>(rhs)?;
+                match *op {
+                    "+" => lhs.add(rhs)?,
+                    "-" => lhs.sub(rhs)?,
+                    "*" => lhs.mul(rhs)?,
+                    "/" => lhs.div(rhs)?,
+                    "//" => lhs.floor_div(rhs)?,
+                    "%" => lhs.rem(rhs)?,
+                    "^" => return lhs.fitted_pow(rhs, self.context),
+                    _ => return Err(ExactError::Unsupported),
+                }
+            }
+            Expr::UnaryOp { .. } | Expr::FuncCall { .. } => return Err(ExactError::Unsupported),
+        };
+        value.fit(self.context)
+    }
+
+    fn identifier<T: Arithmetic>(&self, name: &str) -> Result<T, ExactError> {
+        if name == "i" {
+            if let Ok(unit) = T::imaginary_unit() {
+                return Ok(unit);
+            }
+        }
+        if name == "ans" {
+            if let Some(exact) = &self.context.exact_ans {
+                return T::from_exact(exact);
+            }
+        }
+        let value = match name {
+            "ans" => Some(self.context.ans),
+            _ => constant(name).or_else(|| self.context.variables.get(name).copied()),
+        };
+        T::from_f64(value.ok_or(ExactError::Unsupported)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn integer(input: &str) -> Result<Option<Exact>, CalcError> {
+        let context = Context {
+            number_mode: NumberMode::Integer,
+            ..Context::default()
+        };
+        evaluate_exact(input, &context)
+    }
+
+    #[test]
+    fn test_integer_exact_large_product() {
+        assert_eq!(
+            integer("99999999999 * 99999999999"),
+            Ok(Some(Exact::Integer(9999999999800000000001)))
+        );
+        // The same product in f64 is rounded.
+        assert_ne!(
+            (99999999999f64 * 99999999999f64) as i128,
+            9999999999800000000001
+        );
+    }
+
+    #[test]
+    fn test_integer_exact_power() {
+        assert_eq!(
+            integer("2 ^ 100"),
+            Ok(Some(Exact::Integer(1267650600228229401496703205376)))
+        );
+        assert_eq!(integer("-2 ^ 3"), Ok(Some(Exact::Integer(-8))));
+        assert_eq!(integer("2 ^ 3 ^ 2"), Ok(Some(Exact::Integer(512))));
+    }
+
+    #[test]
+    fn test_integer_implicit_multiplication() {
+        assert_eq!(
+            integer("99999999999(99999999999)"),
+            Ok(Some(Exact::Integer(9999999999800000000001)))
+        );
+        assert_eq!(integer("2(3 + 4)"), Ok(Some(Exact::Integer(14))));
+    }
+
+    #[test]
+    fn test_integer_large_literals_are_exact() {
+        assert_eq!(
+            integer("9007199254740993 * 1"),
+            Ok(Some(Exact::Integer(9007199254740993)))
+        );
+        let context = Context {
+            number_mode: NumberMode::Integer,
+            int_width: Some(64),
+            ..Context::default()
+        };
+        assert_eq!(
+            evaluate_exact("0xffff_ffff_ffff_ffff - 1", &context),
+            Ok(Some(Exact::Integer(u64::MAX as i128 - 1)))
+        );
+    }
+
+    #[test]
+    fn test_exact_ans() {
+        let mut context = Context {
+            number_mode: NumberMode::Integer,
+            exact_ans: Some(Exact::Integer(9999999999800000000001)),
+            ..Context::default()
+        };
+        context.ans = 9999999999800000000001.0;
+        assert_eq!(
+            evaluate_exact("ans + 1", &context),
+            Ok(Some(Exact::Integer(9999999999800000000002)))
+        );
+
+        context.number_mode = NumberMode::Fraction;
+        context.exact_ans = Some(Exact::Fraction(Fraction::new(1, 3).unwrap()));
+        assert_eq!(
+            evaluate_exact("ans * 3", &context).map(|exact| exact.map(|value| value.to_string())),
+            Ok(Some("1".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_integer_precedence() {
+        assert_eq!(integer("2 + 3 * (4 - 1)"), Ok(Some(Exact::Integer(11))));
+        assert_eq!(integer("7 % 3 - 7 // 2"), Ok(Some(Exact::Integer(-2))));
+        assert_eq!(integer("-7 // 2"), Ok(Some(Exact::Integer(-4))));
+        assert_eq!(integer("7 // -2"), Ok(Some(Exact::Integer(-4))));
+    }
+
+    #[test]
+    fn test_integer_division() {
+        assert_eq!(integer("10 / 2"), Ok(Some(Exact::Integer(5))));
+        let error = integer("7 / 2").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "7 / 2 is not a whole number (remainder 1); use // or %"
+        );
+        assert_eq!(integer("1 / 0"), Err(CalcError::DivisionByZero));
+    }
+
+    fn integer_with_width(input: &str, overflow: Overflow) -> Result<Option<Exact>, CalcError> {
+        let context = Context {
+            number_mode: NumberMode::Integer,
+            int_width: Some(8),
+            overflow,
+            ..Context::default()
+        };
+        evaluate_exact(input, &context)
+    }
+
+    #[test]
+    fn test_integer_width_wraps() {
+        let wrap = |input| integer_with_width(input, Overflow::Wrap);
+        assert_eq!(wrap("255 + 1"), Ok(Some(Exact::Integer(0))));
+        assert_eq!(wrap("0 - 1"), Ok(Some(Exact::Integer(255))));
+        assert_eq!(wrap("16 * 17"), Ok(Some(Exact::Integer(16))));
+        assert_eq!(wrap("300"), Ok(Some(Exact::Integer(44))));
+        assert_eq!(wrap("3 ^ 200"), Ok(Some(Exact::Integer(161))));
+    }
+
+    #[test]
+    fn test_integer_width_saturates() {
+        let saturate = |input| integer_with_width(input, Overflow::Saturate);
+        assert_eq!(saturate("255 + 1"), Ok(Some(Exact::Integer(255))));
+        assert_eq!(saturate("0 - 1"), Ok(Some(Exact::Integer(0))));
+        assert_eq!(saturate("(255 + 1) - 10"), Ok(Some(Exact::Integer(245))));
+        assert_eq!(saturate("2 ^ 200"), Ok(Some(Exact::Integer(255))));
+    }
+
+    #[test]
+    fn test_integer_width_errors() {
+        let error = |input| integer_with_width(input, Overflow::Error);
+        assert_eq!(error("255 + 1"), Err(CalcError::Overflow));
+        assert_eq!(error("0 - 1"), Err(CalcError::Overflow));
+        assert_eq!(error("16 ^ 2"), Err(CalcError::Overflow));
+        assert_eq!(error("16 ^ 1"), Ok(Some(Exact::Integer(16))));
+        assert_eq!(error("200 + 55"), Ok(Some(Exact::Integer(255))));
+        assert_eq!(error("255 + 1 - 1"), Err(CalcError::Overflow));
+    }
+
+    #[test]
+    fn test_integer_falls_back_to_real() {
+        assert_eq!(integer("2.5 * 2"), Ok(None));
+        assert_eq!(integer("sqrt(16)"), Ok(None));
+        assert_eq!(integer("2 ^ -1"), Ok(None));
+        assert_eq!(integer("2 ^ 200"), Ok(None));
+        assert_eq!(integer("2 +"), Ok(None));
+        assert_eq!(integer("5!"), Ok(None));
+        assert_eq!(integer("1 < 2"), Ok(None));
+    }
+
+    fn fraction(input: &str) -> Result<Option<String>, CalcError> {
+        let context = Context {
+            number_mode: NumberMode::Fraction,
+            ..Context::default()
+        };
+        evaluate_exact(input, &context).map(|exact| exact.map(|value| value.to_string()))
+    }
+
+    #[test]
+    fn test_fraction_one_third() {
+        assert_eq!(fraction("1/3"), Ok(Some("1/3".to_string())));
+        assert_eq!(fraction("1/3 + 1/6"), Ok(Some("1/2".to_string())));
+    }
+
+    #[test]
+    fn test_fraction_reduces() {
+        assert_eq!(fraction("2/4"), Ok(Some("1/2".to_string())));
+        assert_eq!(fraction("0.5 + 0.25"), Ok(Some("3/4".to_string())));
+    }
+
+    #[test]
+    fn test_fraction_whole_and_improper_results() {
+        assert_eq!(fraction("4/2"), Ok(Some("2".to_string())));
+        assert_eq!(fraction("7/2"), Ok(Some("7/2".to_string())));
+        assert_eq!(fraction("-(1/3) * 3"), Ok(Some("-1".to_string())));
+        assert_eq!(fraction("(2/3) ^ -2"), Ok(Some("9/4".to_string())));
+    }
+
+    #[test]
+    fn test_fraction_falls_back_to_real() {
+        assert_eq!(fraction("pi / 2"), Ok(None));
+        assert_eq!(fraction("2 ^ (1/2)"), Ok(None));
+        assert_eq!(fraction("1 / 0"), Err(CalcError::DivisionByZero));
+    }
+
+    fn complex(input: &str) -> Result<Option<String>, CalcError> {
+        let context = Context {
+            number_mode: NumberMode::Complex,
+            ..Context::default()
+        };
+        evaluate_exact(input, &context).map(|exact| exact.map(|value| value.to_string()))
+    }
+
+    #[test]
+    fn test_complex_addition() {
+        assert_eq!(
+            complex("(1 + 2i) + (3 + 4i)"),
+            Ok(Some("4 + 6i".to_string()))
+        );
+        assert_eq!(complex("2 + 3i"), Ok(Some("2 + 3i".to_string())));
+    }
+
+    #[test]
+    fn test_complex_multiplication() {
+        assert_eq!(complex("(1 + i) * (1 - i)"), Ok(Some("2".to_string())));
+        assert_eq!(complex("i * i"), Ok(Some("-1".to_string())));
+        assert_eq!(complex("2 * 3i - 1"), Ok(Some("-1 + 6i".to_string())));
+    }
+
+    #[test]
+    fn test_complex_falls_back_to_real() {
+        assert_eq!(complex("sqrt(4)"), Ok(None));
+        assert_eq!(complex("5 + 2"), Ok(Some("7".to_string())));
+    }
+
+    #[test]
+    fn test_imaginary_unit_only_in_complex_mode() {
+        assert_eq!(integer("3i"), Ok(None));
+    }
+
+    #[test]
+    fn test_real_mode_is_not_exact() {
+        assert_eq!(evaluate_exact("1 + 1", &Context::default()), Ok(None));
+    }
+}
@@ -0,0 +1,265 @@
+use std::fmt;
+
+use crate::exact::{Arithmetic, Exact, ExactError};
+use crate::CalcError;
+
+/// A rational number, always stored in lowest terms with a positive
+/// denominator.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::Fraction;
+///
+/// let half = Fraction::new(2, 4).unwrap();
+/// assert_eq!(half.to_string(), "1/2");
+/// assert_eq!(Fraction::new(4, 2).unwrap().to_string(), "2");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fraction {
+    numerator: i128,
+    denominator: i128,
+}
+
+/// Largest number of decimal places a number may have to be converted to a
+/// fraction. Anything longer, like the digits of `pi`, is treated as
+/// irrational.
+const MAX_DECIMAL_PLACES: usize = 9;
+
+impl Fraction {
+    /// Creates the fraction `numerator / denominator`, reduced to lowest terms.
+    ///
+    /// # Errors
+    /// * [`CalcError::DivisionByZero`] if `denominator` is zero
+    pub fn new(numerator: i128, denominator: i128) -> Result<Fraction, CalcError> {
+        if denominator == 0 {
+            return Err(CalcError::DivisionByZero);
+        }
+        let divisor = gcd(numerator, denominator);
+        let sign = if denominator < 0 { -1 } else { 1 };
+        Ok(Fraction {
+            numerator: sign * numerator / divisor,
+            denominator: sign * denominator / divisor,
+        })
+    }
+
+    /// The numerator, which carries the sign.
+    pub fn numerator(&self) -> i128 {
+        self.numerator
+    }
+
+    /// The denominator, which is always positive.
+    pub fn denominator(&self) -> i128 {
+        self.denominator
+    }
+
+    /// The value as an `f64`, which may lose precision.
+    pub fn to_f64(&self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+
+    /// Builds a fraction from possibly overflowing parts.
+    fn checked(numerator: Option<i128>, denominator: Option<i128>) -> Result<Self, ExactError> {
+        match (numerator, denominator) {
+            (Some(numerator), Some(denominator)) => Ok(Fraction::new(numerator, denominator)?),
+            _ => Err(ExactError::Unsupported),
+        }
+    }
+
+    /// Rounds toward negative infinity.
+    fn floor(self) -> i128 {
+        self.numerator.div_euclid(self.denominator)
+    }
+
+    /// Rounds toward zero.
+    fn trunc(self) -> i128 {
+        self.numerator / self.denominator
+    }
+}
+
+impl Arithmetic for Fraction {
+    /// Converts numbers with up to [`MAX_DECIMAL_PLACES`] decimal places, so
+    /// `0.25` becomes `1/4`.
+    fn from_f64(value: f64) -> Result<Self, ExactError> {
+        // The shortest decimal representation is what the user typed for a
+        // literal, e.g. `1.1` rather than the binary value just above it.
+        let decimal = value.to_string();
+        let (integer, fraction) = decimal.split_once('.').unwrap_or((&decimal, ""));
+        if fraction.len() > MAX_DECIMAL_PLACES {
+            return Err(ExactError::Unsupported);
+        }
+        let numerator = format!("{}{}", integer, fraction)
+            .parse()
+            .map_err(|_| ExactError::Unsupported)?;
+        Ok(Fraction::new(numerator, 10i128.pow(fraction.len() as u32))?)
+    }
+
+    fn from_integer(value: i128) -> Result<Self, ExactError> {
+        Ok(Fraction::new(value, 1)?)
+    }
+
+    fn from_exact(exact: &Exact) -> Result<Self, ExactError> {
+        match exact {
+            Exact::Integer(integer) => Self::from_integer(*integer),
+            Exact::Fraction(fraction) => Ok(*fraction),
+            Exact::Complex(_) => {
+                Self::from_f64(exact.approximate().ok_or(ExactError::Unsupported)?)
+            }
+        }
+    }
+
+    fn add(self, rhs: Self) -> Result<Self, ExactError> {
+        let numerator = self
+            .numerator
+            .checked_mul(rhs.denominator)
+            .zip(rhs.numerator.checked_mul(self.denominator))
+            .and_then(|(a, b)| a.checked_add(b));
+        Fraction::checked(numerator, self.denominator.checked_mul(rhs.denominator))
+    }
+
+    fn sub(self, rhs: Self) -> Result<Self, ExactError> {
+        self.add(rhs.neg()?)
+    }
+
+    fn mul(self, rhs: Self) -> Result<Self, ExactError> {
+        Fraction::checked(
+            self.numerator.checked_mul(rhs.numerator),
+            self.denominator.checked_mul(rhs.denominator),
+        )
+    }
+
+    fn div(self, rhs: Self) -> Result<Self, ExactError> {
+        if rhs.numerator == 0 {
+            return Err(CalcError::DivisionByZero.into());
+        }
+        Fraction::checked(
+            self.numerator.checked_mul(rhs.denominator),
+            self.denominator.checked_mul(rhs.numerator),
+        )
+    }
+
+    fn floor_div(self, rhs: Self) -> Result<Self, ExactError> {
+        Ok(Fraction::new(self.div(rhs)?.floor(), 1)?)
+    }
+
+    /// The remainder takes the sign of `self`, as with `f64`.
+    fn rem(self, rhs: Self) -> Result<Self, ExactError> {
+        let quotient = Fraction::new(self.div(rhs)?.trunc(), 1)?;
+        self.sub(rhs.mul(quotient)?)
+    }
+
+    /// Raises to a whole-number power; other exponents aren't rational in
+    /// general.
+    fn pow(self, rhs: Self) -> Result<Self, ExactError> {
+        if rhs.denominator != 1 {
+            return Err(ExactError::Unsupported);
+        }
+        let exponent =
+            u32::try_from(rhs.numerator.unsigned_abs()).map_err(|_| ExactError::Unsupported)?;
+        let power = Fraction::checked(
+            self.numerator.checked_pow(exponent),
+            self.denominator.checked_pow(exponent),
+        )?;
+        if rhs.numerator < 0 {
+            Fraction::new(1, 1)?.div(power)
+        } else {
+            Ok(power)
+        }
+    }
+
+    fn neg(self) -> Result<Self, ExactError> {
+        Fraction::checked(self.numerator.checked_neg(), Some(self.denominator))
+    }
+}
+
+impl fmt::Display for Fraction {
+    /// Writes the fraction as `numerator/denominator`, or as an integer if
+    /// the denominator is 1.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.denominator == 1 {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
+
+/// Greatest common divisor by the Euclidean algorithm, never zero for a
+/// non-zero `b`.
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a as i128
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fraction(numerator: i128, denominator: i128) -> Fraction {
+        Fraction::new(numerator, denominator).unwrap()
+    }
+
+    #[test]
+    fn test_new_reduces() {
+        assert_eq!(fraction(2, 4), fraction(1, 2));
+        assert_eq!(fraction(3, -6).numerator(), -1);
+        assert_eq!(fraction(3, -6).denominator(), 2);
+        assert_eq!(Fraction::new(1, 0), Err(CalcError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(fraction(1, 3).to_string(), "1/3");
+        assert_eq!(fraction(7, 2).to_string(), "7/2");
+        assert_eq!(fraction(-4, 2).to_string(), "-2");
+        assert_eq!(fraction(0, 5).to_string(), "0");
+    }
+
+    #[test]
+    fn test_from_f64() {
+        assert_eq!(Fraction::from_f64(0.25), Ok(fraction(1, 4)));
+        assert_eq!(Fraction::from_f64(3.0), Ok(fraction(3, 1)));
+        assert_eq!(Fraction::from_f64(-1.1), Ok(fraction(-11, 10)));
+        assert_eq!(
+            Fraction::from_f64(std::f64::consts::PI),
+            Err(ExactError::Unsupported)
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_is_exact() {
+        let third = fraction(1, 3);
+        let sixth = fraction(1, 6);
+        assert_eq!(third.add(sixth), Ok(fraction(1, 2)));
+        assert_eq!(third.sub(sixth), Ok(fraction(1, 6)));
+        assert_eq!(third.mul(sixth), Ok(fraction(1, 18)));
+        assert_eq!(third.div(sixth), Ok(fraction(2, 1)));
+        assert_eq!(
+            third.div(fraction(0, 1)),
+            Err(ExactError::Calc(CalcError::DivisionByZero))
+        );
+    }
+
+    #[test]
+    fn test_floor_div_and_rem() {
+        let seven_halves = fraction(7, 2);
+        assert_eq!(seven_halves.floor_div(fraction(1, 1)), Ok(fraction(3, 1)));
+        assert_eq!(seven_halves.rem(fraction(1, 1)), Ok(fraction(1, 2)));
+        assert_eq!(
+            seven_halves.neg().unwrap().floor_div(fraction(1, 1)),
+            Ok(fraction(-4, 1))
+        );
+    }
+
+    #[test]
+    fn test_pow() {
+        assert_eq!(fraction(2, 3).pow(fraction(2, 1)), Ok(fraction(4, 9)));
+        assert_eq!(fraction(2, 3).pow(fraction(-1, 1)), Ok(fraction(3, 2)));
+        assert_eq!(
+            fraction(2, 1).pow(fraction(1, 2)),
+            Err(ExactError::Unsupported)
+        );
+    }
+}
@@ -0,0 +1,671 @@
+use std::f64::consts;
+
+use crate::{calculate, CalcError, Context};
+
+/// Names accepted by [`call_function`].
+pub const FUNCTIONS: [&str; 19] = [
+    "sqrt",
+    "sin",
+    "cos",
+    "tan",
+    "ln",
+    "log",
+    "min",
+    "max",
+    "abs",
+    "floor",
+    "ceil",
+    "round",
+    "gcd",
+    "lcm",
+    "pow",
+    "pct_change",
+    "pct_of",
+    "rand",
+    "mathmod",
+];
+
+/// Returns `true` if `name` is a function that [`call_function`] can apply.
+pub fn is_function(name: &str) -> bool {
+    FUNCTIONS.contains(&name)
+}
+
+/// Looks up the value of a named mathematical constant.
+///
+/// # Supported Constants
+/// * `pi` - π, the ratio of a circle's circumference to its diameter
+/// * `e` - Euler's number, the base of the natural logarithm
+/// * `tau` - τ = 2π
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::constant;
+///
+/// assert_eq!(constant("pi"), Some(std::f64::consts::PI));
+/// assert_eq!(constant("pie"), None);
+/// ```
+pub fn constant(name: &str) -> Option<f64> {
+    match name {
+        "pi" => Some(consts::PI),
+        "e" => Some(consts::E),
+        "tau" => Some(consts::TAU),
+        _ => None,
+    }
+}
+
+/// Applies a named function to its arguments.
+///
+/// # Arguments
+/// * `name` - Name of the function, e.g. `"sqrt"`
+/// * `arguments` - The values the function is applied to
+/// * `context` - Evaluation settings, e.g. the angle mode for trigonometry
+///
+/// # Returns
+/// * `Result<f64, CalcError>` - The result of the function
+///
+/// # Supported Functions
+/// * `sqrt(x)` - Square root
+/// * `sin(x)`, `cos(x)`, `tan(x)` - Trigonometric functions of an angle, in
+///   radians or degrees depending on [`Context::angle_mode`]
+/// * `ln(x)` - Natural logarithm
+/// * `log(x)` - Base-10 logarithm
+/// * `log(x, base)` - Logarithm to an arbitrary base
+/// * `min(a, b)`, `max(a, b)` - The smaller or larger of two values
+/// * `abs(x)` - Absolute value
+/// * `floor(x)`, `ceil(x)` - Round down or up to the nearest integer
+/// * `round(x)` - Round to the nearest integer; halfway cases are rounded away
+///   from zero, so `round(2.5)` is `3` and `round(-2.5)` is `-3`
+/// * `pow(base, exp)` - Exponentiation, the same as `base ^ exp`
+/// * `gcd(a, b)`, `lcm(a, b)` - Greatest common divisor and least common
+///   multiple of two non-negative integers; `gcd(0, 0)` and `lcm(0, n)` are `0`
+/// * `pct_change(old, new)` - The change from `old` to `new` as a percentage
+///   of `old`, `(new - old) / |old| * 100`: `pct_change(100, 150)` is `50` and
+///   `pct_change(100, 80)` is `-20`. Dividing by the magnitude keeps an
+///   increase positive even if `old` is negative.
+/// * `pct_of(part, whole)` - What percentage `part` is of `whole`,
+///   `part / whole * 100`: `pct_of(25, 200)` is `12.5`
+/// * `mathmod(a, b)` - Mathematical modulo, `((a % b) + b) % b`, whose result
+///   has the sign of `b`: `mathmod(-7, 3)` is `2`, where `-7 % 3` is `-1`
+///   because the `%` operator keeps the sign of `a`
+///
+/// Trigonometric results are subject to floating-point rounding, so exact
+/// values are not guaranteed: `sin(pi)` is a tiny number close to, but not
+/// exactly, zero. `tan` near π/2 returns a very large value rather than failing.
+///
+/// # Errors
+/// * [`CalcError::Domain`] if an argument is outside the function's domain
+///   (e.g. the square root of a negative number, the logarithm of zero or the
+///   `gcd` of a fraction, or `rand(a, b)` with `a >= b`)
+/// * [`CalcError::DivisionByZero`] if the base of `pct_change` or `pct_of`
+///   (`old` or `whole`) or the divisor of `mathmod` is zero
+/// * [`CalcError::NotANumber`] if `min` or `max` is given a NaN
+/// * [`CalcError::ArgumentCount`] if the function doesn't accept that many
+///   arguments
+/// * [`CalcError::UnknownIdentifier`] if no function has the given name
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::{call_function, Context};
+///
+/// let context = Context::default();
+/// assert_eq!(call_function("sqrt", &[16.0], &context).unwrap(), 4.0);
+/// assert_eq!(call_function("log", &[8.0, 2.0], &context).unwrap(), 3.0);
+/// assert!(call_function("sqrt", &[-1.0], &context).is_err());
+/// ```
+pub fn call_function(name: &str, arguments: &[f64], context: &Context) -> Result<f64, CalcError> {
+    match (name, arguments) {
+        ("sqrt", &[x]) => {
+            if x < 0.0 {
+                Err(CalcError::Domain("sqrt of negative number".to_string()))
+            } else {
+                Ok(x.sqrt())
+            }
+        }
+        ("sin", &[x]) => Ok(context.angle_mode.to_radians(x).sin()),
+        ("cos", &[x]) => Ok(context.angle_mode.to_radians(x).cos()),
+        ("tan", &[x]) => Ok(context.angle_mode.to_radians(x).tan()),
+        ("ln", &[x]) => Ok(positive_for_log(name, x)?.ln()),
+        ("log", &[x]) => Ok(positive_for_log(name, x)?.log10()),
+        ("log", &[x, base]) => {
+            let base = positive_for_log(name, base)?;
+            if base == 1.0 {
+                return Err(CalcError::Domain("log base cannot be 1".to_string()));
+            }
+            Ok(positive_for_log(name, x)?.log(base))
+        }
+        ("abs", &[x]) => Ok(x.abs()),
+        ("floor", &[x]) => Ok(x.floor()),
+        ("ceil", &[x]) => Ok(x.ceil()),
+        ("round", &[x]) => Ok(x.round()),
+        ("pow", &[base, exponent]) => calculate(base, exponent, "^"),
+        ("gcd", &[a, b]) => Ok(gcd(to_natural(name, a)?, to_natural(name, b)?) as f64),
+        ("lcm", &[a, b]) => {
+            let (a, b) = (to_natural(name, a)?, to_natural(name, b)?);
+            if a == 0 || b == 0 {
+                return Ok(0.0);
+            }
+            // Computed in floating point, as the product can exceed `u64`.
+            Ok((a / gcd(a, b)) as f64 * b as f64)
+        }
+        ("pct_change", &[old, new]) => {
+            if old == 0.0 {
+                return Err(CalcError::DivisionByZero);
+            }
+            Ok((new - old) / old.abs() * 100.0)
+        }
+        ("pct_of", &[part, whole]) => {
+            if whole == 0.0 {
+                return Err(CalcError::DivisionByZero);
+            }
+            Ok(part / whole * 100.0)
+        }
+        ("mathmod", &[a, b]) => {
+            let remainder = calculate(a, b, "%")?;
+            Ok((remainder + b) % b)
+        }
+        ("rand", &[]) => Ok(context.rng.next_f64()),
+        ("rand", &[low, high]) => {
+            if low >= high {
+                return Err(CalcError::Domain("rand(a, b) needs a < b".to_string()));
+            }
+            Ok(low + (high - low) * context.rng.next_f64())
+        }
+        ("min" | "max", &[a, b]) if a.is_nan() || b.is_nan() => Err(CalcError::NotANumber),
+        ("min", &[a, b]) => Ok(a.min(b)),
+        ("max", &[a, b]) => Ok(a.max(b)),
+        _ if is_function(name) => Err(CalcError::ArgumentCount {
+            function: name.to_string(),
+            found: arguments.len(),
+        }),
+        _ => Err(CalcError::UnknownIdentifier(name.to_string())),
+    }
+}
+
+/// Computes the factorial `n!` of a non-negative integer.
+///
+/// The result is an `f64`, so it becomes inexact for large `n` and overflows
+/// to infinity from `171!` onwards (`170!` ≈ 7.26e306 is the largest finite
+/// factorial).
+///
+/// # Errors
+/// * [`CalcError::Domain`] if `n` is negative or not an integer
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::factorial;
+///
+/// assert_eq!(factorial(5.0).unwrap(), 120.0);
+/// assert!(factorial(2.5).is_err());
+/// ```
+pub fn factorial(n: f64) -> Result<f64, CalcError> {
+    if n < 0.0 {
+        return Err(CalcError::Domain(
+            "Factorial of a negative number is undefined".to_string(),
+        ));
+    }
+    if n.fract() != 0.0 {
+        return Err(CalcError::Domain(
+            "Factorial is only defined for integers".to_string(),
+        ));
+    }
+
+    let mut result: f64 = 1.0;
+    let mut i = 2.0;
+    while i <= n && result.is_finite() {
+        result *= i;
+        i += 1.0;
+    }
+    Ok(result)
+}
+
+/// Greatest common divisor by the Euclidean algorithm, with `gcd(0, 0) = 0`.
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Checks that an argument of `gcd` or `lcm` is a non-negative integer.
+fn to_natural(name: &str, x: f64) -> Result<u64, CalcError> {
+    // `u64::MAX as f64` rounds up to 2^64, which is already out of range.
+    if x.fract() != 0.0 || x < 0.0 || x >= u64::MAX as f64 {
+        return Err(CalcError::Domain(format!(
+            "{} requires non-negative integers, got {}",
+            name, x
+        )));
+    }
+    Ok(x as u64)
+}
+
+/// Checks that a logarithm argument is positive, where it is defined.
+fn positive_for_log(name: &str, x: f64) -> Result<f64, CalcError> {
+    if x > 0.0 {
+        Ok(x)
+    } else {
+        Err(CalcError::Domain(format!(
+            "{} of non-positive number",
+            name
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{approx_eq, Rng};
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+    const EPSILON: f64 = 1e-10;
+
+    fn call(name: &str, argument: f64) -> Result<f64, CalcError> {
+        call_function(name, &[argument], &Context::default())
+    }
+
+    #[test]
+    fn test_sqrt_perfect_square() {
+        let result = call("sqrt", 16.0);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 4.0);
+    }
+
+    #[test]
+    fn test_sqrt_non_perfect_square() {
+        let result = call("sqrt", 2.0);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), std::f64::consts::SQRT_2);
+    }
+
+    #[test]
+    fn test_sqrt_negative_argument() {
+        let result = call("sqrt", -4.0);
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().to_string(), "sqrt of negative number");
+    }
+
+    #[test]
+    fn test_sin() {
+        assert_eq!(call("sin", 0.0).unwrap(), 0.0);
+        assert!(approx_eq(call("sin", FRAC_PI_2).unwrap(), 1.0, EPSILON));
+        assert!(approx_eq(call("sin", PI).unwrap(), 0.0, EPSILON));
+    }
+
+    #[test]
+    fn test_cos() {
+        assert_eq!(call("cos", 0.0).unwrap(), 1.0);
+        assert!(approx_eq(call("cos", PI).unwrap(), -1.0, EPSILON));
+    }
+
+    #[test]
+    fn test_tan() {
+        assert_eq!(call("tan", 0.0).unwrap(), 0.0);
+        assert!(approx_eq(call("tan", FRAC_PI_4).unwrap(), 1.0, EPSILON));
+    }
+
+    #[test]
+    fn test_tan_near_half_pi() {
+        let result = call("tan", FRAC_PI_2);
+        assert!(result.is_ok());
+        assert!(result.unwrap() > 1e15);
+    }
+
+    #[test]
+    fn test_trig_in_degrees() {
+        let context = Context {
+            angle_mode: crate::AngleMode::Degrees,
+            ..Context::default()
+        };
+        assert!(approx_eq(
+            call_function("sin", &[90.0], &context).unwrap(),
+            1.0,
+            EPSILON
+        ));
+        assert!(approx_eq(
+            call_function("cos", &[180.0], &context).unwrap(),
+            -1.0,
+            EPSILON
+        ));
+        assert!(approx_eq(
+            call_function("tan", &[45.0], &context).unwrap(),
+            1.0,
+            EPSILON
+        ));
+    }
+
+    #[test]
+    #[allow(clippy::approx_constant)]
+    fn test_constants() {
+        assert!(approx_eq(constant("pi").unwrap(), 3.14159, 1e-5));
+        assert!(approx_eq(constant("e").unwrap(), 2.71828, 1e-5));
+        assert!(approx_eq(constant("tau").unwrap(), 6.28318, 1e-5));
+    }
+
+    #[test]
+    fn test_unknown_constant() {
+        assert_eq!(constant("pie"), None);
+        assert_eq!(constant("sqrt"), None);
+    }
+
+    #[test]
+    fn test_is_function() {
+        assert!(is_function("sqrt"));
+        assert!(!is_function("pi"));
+    }
+
+    #[test]
+    fn test_ln() {
+        assert!(approx_eq(
+            call("ln", std::f64::consts::E).unwrap(),
+            1.0,
+            EPSILON
+        ));
+        assert_eq!(call("ln", 1.0).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_ln_of_zero() {
+        let result = call("ln", 0.0);
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().to_string(), "ln of non-positive number");
+    }
+
+    #[test]
+    fn test_log_base_ten() {
+        assert!(approx_eq(call("log", 1000.0).unwrap(), 3.0, EPSILON));
+        assert!(call("log", -10.0).is_err());
+    }
+
+    #[test]
+    fn test_log_with_base() {
+        let context = Context::default();
+        assert!(approx_eq(
+            call_function("log", &[8.0, 2.0], &context).unwrap(),
+            3.0,
+            EPSILON
+        ));
+        assert!(call_function("log", &[8.0, 1.0], &context).is_err());
+        assert!(call_function("log", &[8.0, 0.0], &context).is_err());
+    }
+
+    #[test]
+    fn test_abs() {
+        assert_eq!(call("abs", -3.0), Ok(3.0));
+        assert_eq!(call("abs", 3.0), Ok(3.0));
+        assert_eq!(call("abs", 0.0), Ok(0.0));
+    }
+
+    #[test]
+    fn test_floor() {
+        assert_eq!(call("floor", 2.7), Ok(2.0));
+        assert_eq!(call("floor", -2.1), Ok(-3.0));
+    }
+
+    #[test]
+    fn test_ceil() {
+        assert_eq!(call("ceil", 2.1), Ok(3.0));
+        assert_eq!(call("ceil", -2.7), Ok(-2.0));
+    }
+
+    #[test]
+    fn test_round() {
+        assert_eq!(call("round", 2.4), Ok(2.0));
+        assert_eq!(call("round", 2.6), Ok(3.0));
+    }
+
+    #[test]
+    fn test_round_half_away_from_zero() {
+        assert_eq!(call("round", 2.5), Ok(3.0));
+        assert_eq!(call("round", -2.5), Ok(-3.0));
+        assert_eq!(call("round", 0.5), Ok(1.0));
+    }
+
+    #[test]
+    fn test_pow() {
+        let context = Context::default();
+        assert_eq!(call_function("pow", &[2.0, 10.0], &context), Ok(1024.0));
+        assert_eq!(call_function("pow", &[2.0, -1.0], &context), Ok(0.5));
+    }
+
+    #[test]
+    fn test_pow_matches_operator() {
+        let context = Context::default();
+        for (base, exponent) in [
+            (2.0, 10.0),
+            (9.0, 0.5),
+            (-2.0, 3.0),
+            (-8.0, 0.5),
+            (0.0, 0.0),
+        ] {
+            assert_eq!(
+                call_function("pow", &[base, exponent], &context),
+                calculate(base, exponent, "^")
+            );
+        }
+    }
+
+    #[test]
+    fn test_gcd() {
+        let context = Context::default();
+        assert_eq!(call_function("gcd", &[12.0, 18.0], &context), Ok(6.0));
+        assert_eq!(call_function("gcd", &[17.0, 5.0], &context), Ok(1.0));
+        assert_eq!(call_function("gcd", &[0.0, 9.0], &context), Ok(9.0));
+    }
+
+    #[test]
+    fn test_lcm() {
+        let context = Context::default();
+        assert_eq!(call_function("lcm", &[4.0, 6.0], &context), Ok(12.0));
+        assert_eq!(call_function("lcm", &[7.0, 7.0], &context), Ok(7.0));
+        assert_eq!(call_function("lcm", &[0.0, 5.0], &context), Ok(0.0));
+    }
+
+    #[test]
+    fn test_pct_change() {
+        let context = Context::default();
+        assert_eq!(
+            call_function("pct_change", &[100.0, 150.0], &context),
+            Ok(50.0)
+        );
+        assert_eq!(
+            call_function("pct_change", &[100.0, 80.0], &context),
+            Ok(-20.0)
+        );
+        assert_eq!(
+            call_function("pct_change", &[-50.0, -25.0], &context),
+            Ok(50.0)
+        );
+        assert_eq!(call_function("pct_change", &[7.0, 7.0], &context), Ok(0.0));
+    }
+
+    #[test]
+    fn test_pct_of() {
+        let context = Context::default();
+        assert_eq!(call_function("pct_of", &[25.0, 200.0], &context), Ok(12.5));
+        assert_eq!(
+            call_function("pct_of", &[300.0, 200.0], &context),
+            Ok(150.0)
+        );
+        assert_eq!(call_function("pct_of", &[-10.0, 40.0], &context), Ok(-25.0));
+    }
+
+    #[test]
+    fn test_mathmod_follows_divisor_sign() {
+        let context = Context::default();
+        let mathmod = |a, b| call_function("mathmod", &[a, b], &context);
+        assert_eq!(mathmod(-7.0, 3.0), Ok(2.0));
+        assert_eq!(calculate(-7.0, 3.0, "%"), Ok(-1.0));
+        assert_eq!(mathmod(7.0, -3.0), Ok(-2.0));
+        assert_eq!(calculate(7.0, -3.0, "%"), Ok(1.0));
+        assert_eq!(mathmod(-7.0, -3.0), Ok(-1.0));
+        assert_eq!(calculate(-7.0, -3.0, "%"), Ok(-1.0));
+    }
+
+    #[test]
+    fn test_mathmod_matches_remainder_for_positive_operands() {
+        let context = Context::default();
+        assert_eq!(call_function("mathmod", &[7.0, 3.0], &context), Ok(1.0));
+        assert_eq!(call_function("mathmod", &[6.0, 3.0], &context), Ok(0.0));
+        assert!(approx_eq(
+            call_function("mathmod", &[-0.5, 2.0], &context).unwrap(),
+            1.5,
+            1e-12
+        ));
+        assert_eq!(
+            call_function("mathmod", &[5.0, 0.0], &context),
+            Err(CalcError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn test_rand_respects_range() {
+        let context = Context {
+            rng: Rng::with_seed(3),
+            ..Context::default()
+        };
+        for _ in 0..1000 {
+            let unit = call_function("rand", &[], &context).unwrap();
+            assert!((0.0..1.0).contains(&unit), "{} is out of range", unit);
+            let ranged = call_function("rand", &[-5.0, 5.0], &context).unwrap();
+            assert!((-5.0..5.0).contains(&ranged), "{} is out of range", ranged);
+        }
+    }
+
+    #[test]
+    fn test_rand_with_seed_is_reproducible() {
+        let sequence = |seed| {
+            let context = Context {
+                rng: Rng::with_seed(seed),
+                ..Context::default()
+            };
+            (0..10)
+                .map(|_| call_function("rand", &[1.0, 100.0], &context).unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sequence(42), sequence(42));
+        assert_ne!(sequence(42), sequence(43));
+    }
+
+    #[test]
+    fn test_rand_rejects_empty_range() {
+        let context = Context::default();
+        assert!(matches!(
+            call_function("rand", &[2.0, 2.0], &context),
+            Err(CalcError::Domain(_))
+        ));
+        assert!(matches!(
+            call_function("rand", &[1.0], &context),
+            Err(CalcError::ArgumentCount { found: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_percentages_reject_zero_base() {
+        let context = Context::default();
+        assert_eq!(
+            call_function("pct_change", &[0.0, 5.0], &context),
+            Err(CalcError::DivisionByZero)
+        );
+        assert_eq!(
+            call_function("pct_of", &[5.0, 0.0], &context),
+            Err(CalcError::DivisionByZero)
+        );
+        assert_eq!(call_function("pct_of", &[0.0, 5.0], &context), Ok(0.0));
+    }
+
+    #[test]
+    fn test_gcd_of_zeros() {
+        let context = Context::default();
+        assert_eq!(call_function("gcd", &[0.0, 0.0], &context), Ok(0.0));
+        assert_eq!(call_function("lcm", &[0.0, 0.0], &context), Ok(0.0));
+    }
+
+    #[test]
+    fn test_gcd_lcm_reject_invalid_operands() {
+        let context = Context::default();
+        let result = call_function("gcd", &[2.5, 5.0], &context);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "gcd requires non-negative integers, got 2.5"
+        );
+        assert!(matches!(
+            call_function("lcm", &[-4.0, 6.0], &context),
+            Err(CalcError::Domain(_))
+        ));
+    }
+
+    #[test]
+    fn test_min() {
+        let context = Context::default();
+        assert_eq!(call_function("min", &[3.0, 7.0], &context), Ok(3.0));
+        assert_eq!(call_function("min", &[-1.0, -5.0], &context), Ok(-5.0));
+        assert_eq!(call_function("min", &[2.0, 2.0], &context), Ok(2.0));
+    }
+
+    #[test]
+    fn test_max() {
+        let context = Context::default();
+        assert_eq!(call_function("max", &[3.0, 7.0], &context), Ok(7.0));
+        assert_eq!(call_function("max", &[-1.0, -5.0], &context), Ok(-1.0));
+        assert_eq!(call_function("max", &[2.0, 2.0], &context), Ok(2.0));
+    }
+
+    #[test]
+    fn test_min_max_reject_nan() {
+        let context = Context::default();
+        assert_eq!(
+            call_function("min", &[f64::NAN, 1.0], &context),
+            Err(CalcError::NotANumber)
+        );
+        assert_eq!(
+            call_function("max", &[1.0, f64::NAN], &context),
+            Err(CalcError::NotANumber)
+        );
+        assert!(matches!(
+            call_function("max", &[1.0], &context),
+            Err(CalcError::ArgumentCount { .. })
+        ));
+    }
+
+    #[test]
+    fn test_factorial() {
+        assert_eq!(factorial(0.0), Ok(1.0));
+        assert_eq!(factorial(1.0), Ok(1.0));
+        assert_eq!(factorial(5.0), Ok(120.0));
+    }
+
+    #[test]
+    fn test_factorial_overflow() {
+        assert!(factorial(170.0).unwrap().is_finite());
+        assert_eq!(factorial(171.0), Ok(f64::INFINITY));
+    }
+
+    #[test]
+    fn test_factorial_rejects_non_integer() {
+        assert!(matches!(factorial(2.5), Err(CalcError::Domain(_))));
+    }
+
+    #[test]
+    fn test_factorial_rejects_negative() {
+        assert!(matches!(factorial(-1.0), Err(CalcError::Domain(_))));
+    }
+
+    #[test]
+    fn test_wrong_argument_count() {
+        let result = call_function("sqrt", &[4.0, 9.0], &Context::default());
+        assert_eq!(
+            result,
+            Err(CalcError::ArgumentCount {
+                function: "sqrt".to_string(),
+                found: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn test_unknown_function() {
+        assert_eq!(
+            call("foo", 1.0),
+            Err(CalcError::UnknownIdentifier("foo".to_string()))
+        );
+    }
+}
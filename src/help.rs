@@ -0,0 +1,392 @@
+use crate::tokenizer::OPERATOR_ALIASES;
+
+/// An entry of [`HELP_SECTIONS`] as `(name, description, explanation)`.
+pub type HelpEntry = (&'static str, &'static str, &'static str);
+
+/// Everything listed by the `help` command, as `(section, entries)`.
+///
+/// This is the only place the help text is written down, so a new operator,
+/// function or command only needs an entry here to show up in `help`. The
+/// explanation is the longer text shown by `explain` (see [`explain`]); it is
+/// empty for commands.
+pub const HELP_SECTIONS: &[(&str, &[HelpEntry])] = &[
+    (
+        "Operators",
+        &[
+            ("+", "addition", "Addition: adds the right number to the left"),
+            (
+                "-",
+                "subtraction, or negation in front of a value",
+                "Subtraction: subtracts the right number from the left; in front of a value it negates it",
+            ),
+            (
+                "*",
+                "multiplication, also x or × as in 5 x 5",
+                "Multiplication: multiplies the left number by the right",
+            ),
+            (
+                "/",
+                "division, also ÷",
+                "Division: divides the left number by the right; errors on divide-by-zero",
+            ),
+            (
+                "//",
+                "floor division",
+                "Floor division: divides the left number by the right and rounds down, so 7 // 2 is 3; errors on divide-by-zero",
+            ),
+            (
+                "%",
+                "remainder, or a percentage after a value as in 200 + 10%",
+                "Remainder or percentage: between two numbers it is the remainder, what is left of the left number after dividing by the right, with the sign of the left, so 7 % 3 is 1 and -7 % 3 is -1 (see mathmod); after a value it is a percentage, so 50% is 0.5 and 200 + 10% is 220",
+            ),
+            (
+                "^",
+                "exponentiation",
+                "Exponentiation: raises the left number to the power of the right; it groups from the right, so 2 ^ 3 ^ 2 is 512",
+            ),
+            (
+                "!",
+                "factorial, after a value as in 5!",
+                "Factorial: multiplies all integers from 1 up to the value, so 5! is 120; only for non-negative integers",
+            ),
+            ("&", "bitwise AND", "Bitwise AND: keeps the bits set in both integers, so 6 & 3 is 2"),
+            ("|", "bitwise OR", "Bitwise OR: keeps the bits set in either integer, so 6 | 3 is 7"),
+            (
+                "xor",
+                "bitwise exclusive OR",
+                "Bitwise exclusive OR: keeps the bits set in exactly one of the integers, so 6 xor 3 is 5",
+            ),
+            (
+                "<<",
+                "left shift",
+                "Left shift: moves the bits of the left integer up by the right number of places, so 1 << 4 is 16",
+            ),
+            (
+                ">>",
+                "right shift",
+                "Right shift: moves the bits of the left integer down by the right number of places, so 16 >> 2 is 4",
+            ),
+            (
+                "< > <= >= == !=",
+                "comparisons, giving true or false as in 5 > 3",
+                "Comparisons: less than, greater than, at most, at least, equal and not equal, giving true or false; == allows for floating-point rounding",
+            ),
+        ],
+    ),
+    (
+        "Functions",
+        &[
+            (
+                "sqrt(x)",
+                "square root",
+                "Square root: the non-negative number whose square is x; errors on negative numbers",
+            ),
+            ("sin(x)", "sine", "Sine: of an angle in radians, or in degrees after mode deg"),
+            ("cos(x)", "cosine", "Cosine: of an angle in radians, or in degrees after mode deg"),
+            (
+                "tan(x)",
+                "tangent",
+                "Tangent: of an angle in radians, or in degrees after mode deg; very large near a right angle",
+            ),
+            (
+                "ln(x)",
+                "natural logarithm",
+                "Natural logarithm: the power e must be raised to to give x; errors unless x is positive",
+            ),
+            (
+                "log(x)",
+                "base-10 logarithm, or log(x, base) for another base",
+                "Logarithm: the power 10, or the given base, must be raised to to give x; errors unless x and the base are positive and the base isn't 1",
+            ),
+            (
+                "pow(base, exp)",
+                "exponentiation, the same as base ^ exp",
+                "Power: base raised to the power exp, the same as base ^ exp",
+            ),
+            ("min(a, b)", "the smaller of two values", "Minimum: the smaller of a and b"),
+            ("max(a, b)", "the larger of two values", "Maximum: the larger of a and b"),
+            ("abs(x)", "absolute value", "Absolute value: x without its sign, so abs(-3) is 3"),
+            (
+                "floor(x)",
+                "round down to an integer",
+                "Floor: the largest integer not above x, so floor(-2.5) is -3",
+            ),
+            (
+                "ceil(x)",
+                "round up to an integer",
+                "Ceiling: the smallest integer not below x, so ceil(2.1) is 3",
+            ),
+            (
+                "round(x)",
+                "round to the nearest integer, halves away from zero",
+                "Rounding: the integer nearest to x, with halves rounded away from zero, so round(2.5) is 3",
+            ),
+            (
+                "gcd(a, b)",
+                "greatest common divisor of two integers",
+                "Greatest common divisor: the largest integer that divides both a and b; only for non-negative integers",
+            ),
+            (
+                "lcm(a, b)",
+                "least common multiple of two integers",
+                "Least common multiple: the smallest integer that both a and b divide; only for non-negative integers",
+            ),
+            (
+                "pct_change(old, new)",
+                "change from old to new in percent of old",
+                "Percentage change: how much new differs from old, in percent of old; errors if old is 0",
+            ),
+            (
+                "pct_of(part, whole)",
+                "part as a percentage of whole",
+                "Percentage of: part as a percentage of whole, so pct_of(25, 200) is 12.5; errors if whole is 0",
+            ),
+            (
+                "mathmod(a, b)",
+                "modulo with the sign of b, so mathmod(-7, 3) is 2",
+                "Mathematical modulo: the remainder of a divided by b, with the sign of b, so mathmod(-7, 3) is 2 where -7 % 3 is -1; errors if b is 0",
+            ),
+            (
+                "rand(), rand(a, b)",
+                "a random number from 0 up to 1, or from a up to b",
+                "Random number: from 0 up to but not including 1, or from a up to b; --seed makes the numbers repeatable",
+            ),
+        ],
+    ),
+    (
+        "Constants",
+        &[
+            (
+                "pi",
+                "the ratio of a circle's circumference to its diameter",
+                "Pi: the ratio of a circle's circumference to its diameter, about 3.14159",
+            ),
+            (
+                "e",
+                "Euler's number",
+                "Euler's number: the base of the natural logarithm, about 2.71828",
+            ),
+            ("tau", "2 * pi", "Tau: 2 * pi, a full turn in radians, about 6.28319"),
+            (
+                "ans",
+                "the previous result, also used by a leading operator as in + 5",
+                "Previous result: the result of the last calculation, 0 before the first",
+            ),
+            ("mr", "the value in memory", "Memory: the value stored with M+ and M-, 0 while empty"),
+        ],
+    ),
+    (
+        "Commands",
+        &[
+            ("name = expression", "assign a variable", ""),
+            (
+                "define f(x) = expression",
+                "define a function, e.g. define double(x) = x * 2",
+                "",
+            ),
+            ("mode deg|rad", "measure angles in degrees or radians", ""),
+            (
+                "mode int|frac|complex|real",
+                "use exact integers, fractions or complex numbers, or floats",
+                "",
+            ),
+            (
+                "intwidth 8|16|32|64|off",
+                "emulate unsigned integers of that width in int mode, and show negative hex/bin results in two's complement",
+                "",
+            ),
+            ("overflow wrap|saturate|error", "what int mode does with results too wide", ""),
+            ("mode tape|rpn|std", "keep a running total, read RPN input, or read expressions", ""),
+            ("mode sci|finite", "accept or reject inf as a value", ""),
+            ("total, reset", "show or zero the running total in tape mode", ""),
+            ("history", "list previous calculations", ""),
+            ("search TERM", "list previous calculations containing TERM", ""),
+            ("last_error", "show the most recent error again", ""),
+            ("M+, M-, MR, MC", "add ans to memory, subtract it, show or clear memory", ""),
+            ("!N", "evaluate history entry N again", ""),
+            ("undo", "restore ans and the variables before the last change", ""),
+            ("precision N|full", "show N decimal places or full precision", ""),
+            ("round_mode nearest|up|down|truncate", "how results are rounded to the precision", ""),
+            ("sep on|off", "group results into thousands", ""),
+            ("sci on|off", "show results in scientific notation", ""),
+            ("eng on|off", "show results in engineering notation", ""),
+            ("base dec|hex|bin", "show integer results in that base", ""),
+            ("currency on|off|SYMBOL", "show results as money, e.g. $1,234.50", ""),
+            ("locale us|eu", "accept a decimal comma in eu mode", ""),
+            ("aliases on|off", "read x, × and ÷ as operators or not", ""),
+            ("prompt \"TEXT\"", "change the prompt, e.g. prompt \"calc> \"", ""),
+            ("convert N FROM TO", "convert between units, e.g. convert 100 km mi", ""),
+            ("clear", "clear the screen, or clear all to also reset state", ""),
+            ("time EXPRESSION", "evaluate and show how long it took", ""),
+            ("repeat N EXPRESSION", "evaluate N times, e.g. repeat 3 2 + 2", ""),
+            ("sum N...", "add up a list of numbers, e.g. sum 1 2 3", ""),
+            ("mean N...", "the average of a list of numbers", ""),
+            ("median N...", "the middle value of a list, or the mean of the two middle values", ""),
+            ("stddev N...", "the population standard deviation of a list", ""),
+            ("explain NAME", "describe an operator, function or constant", ""),
+            ("tokens EXPRESSION", "show how an expression is split into tokens", ""),
+            ("ast EXPRESSION", "show the tree an expression is parsed into", ""),
+            ("help", "show this list", ""),
+            ("q, quit, exit, :q", "quit the calculator", ""),
+        ],
+    ),
+];
+
+/// Builds the text shown by the `help` command from [`HELP_SECTIONS`].
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::help_text;
+///
+/// assert!(help_text().contains("sqrt(x)"));
+/// ```
+pub fn help_text() -> String {
+    let width = HELP_SECTIONS
+        .iter()
+        .flat_map(|(_, entries)| entries.iter())
+        .map(|(name, _, _)| name.len())
+        .max()
+        .unwrap_or(0);
+
+    HELP_SECTIONS
+        .iter()
+        .map(|(section, entries)| {
+            let lines: Vec<String> = entries
+                .iter()
+                .map(|(name, description, _)| format!("  {:width$}  {}", name, description))
+                .collect();
+            format!("{}:\n{}", section, lines.join("\n"))
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Returns the explanation of an operator, function or constant, as shown by
+/// `explain`, or `None` if `name` has none.
+///
+/// A function is looked up by its name alone, such as `sqrt`, and each of
+/// the comparison operators by its own symbol. The operator aliases `x` and
+/// `×` are explained as `*`, and `÷` as `/`.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::explain;
+///
+/// assert_eq!(
+///     explain("/"),
+///     Some("Division: divides the left number by the right; errors on divide-by-zero")
+/// );
+/// assert_eq!(explain("history"), None);
+/// ```
+pub fn explain(name: &str) -> Option<&'static str> {
+    let name = match name {
+        "x" | "X" => "*",
+        _ => OPERATOR_ALIASES
+            .iter()
+            .find(|(alias, _)| name.chars().eq([*alias]))
+            .map_or(name, |(_, operator)| operator),
+    };
+    HELP_SECTIONS
+        .iter()
+        .flat_map(|(_, entries)| entries.iter())
+        .filter(|(_, _, explanation)| !explanation.is_empty())
+        .find(|(entry, _, _)| match entry.split_once('(') {
+            Some((function, _)) => function == name,
+            None => entry.split_whitespace().any(|symbol| symbol == name),
+        })
+        .map(|(_, _, explanation)| *explanation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::FUNCTIONS;
+    use crate::OPERATORS;
+
+    fn names() -> Vec<&'static str> {
+        HELP_SECTIONS
+            .iter()
+            .flat_map(|(_, entries)| entries.iter().map(|(name, _, _)| *name))
+            .collect()
+    }
+
+    #[test]
+    fn test_help_lists_every_operator() {
+        let names = names();
+        for operator in OPERATORS {
+            assert!(names.contains(&operator), "{} is missing", operator);
+        }
+    }
+
+    #[test]
+    fn test_help_lists_every_function() {
+        let names = names();
+        for function in FUNCTIONS {
+            let call = format!("{}(", function);
+            assert!(
+                names.iter().any(|name| name.starts_with(&call)),
+                "{} is missing",
+                function
+            );
+        }
+    }
+
+    #[test]
+    fn test_explain_operator() {
+        assert_eq!(
+            explain("+"),
+            Some("Addition: adds the right number to the left")
+        );
+        assert_eq!(
+            explain("/"),
+            Some("Division: divides the left number by the right; errors on divide-by-zero")
+        );
+        assert!(explain("<=").unwrap().starts_with("Comparisons:"));
+    }
+
+    #[test]
+    fn test_explain_operator_aliases() {
+        assert_eq!(explain("x"), explain("*"));
+        assert_eq!(explain("×"), explain("*"));
+        assert_eq!(explain("÷"), explain("/"));
+        assert!(explain("x").is_some());
+    }
+
+    #[test]
+    fn test_explain_percent() {
+        let explanation = explain("%").unwrap();
+        assert!(explanation.contains("remainder"));
+        assert!(explanation.contains("200 + 10% is 220"));
+    }
+
+    #[test]
+    fn test_explain_function() {
+        assert_eq!(
+            explain("sqrt"),
+            Some("Square root: the non-negative number whose square is x; errors on negative numbers")
+        );
+        assert!(explain("rand").unwrap().starts_with("Random number:"));
+        assert_eq!(explain("sqrt(x)"), None);
+    }
+
+    #[test]
+    fn test_explain_unknown() {
+        assert_eq!(explain("foo"), None);
+        assert_eq!(explain("history"), None);
+    }
+
+    #[test]
+    fn test_every_operator_and_function_is_explained() {
+        for name in OPERATORS.iter().chain(FUNCTIONS.iter()) {
+            assert!(explain(name).is_some(), "{} is not explained", name);
+        }
+    }
+
+    #[test]
+    fn test_help_text_layout() {
+        let text = help_text();
+        assert!(text.starts_with("Operators:\n"));
+        assert!(text.contains("\n\nCommands:\n"));
+        assert!(text.contains("sqrt(x)"));
+    }
+}
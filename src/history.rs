@@ -0,0 +1,255 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::{split_assignment, Calculation};
+
+/// Number of entries kept by [`History::default`].
+pub const DEFAULT_MAX_ENTRIES: usize = 100;
+
+/// In-memory record of successful calculations, oldest first.
+///
+/// Each entry has the form `expression = result`, or for an assignment
+/// `name = expression → result`, so `x = 3` doesn't read like the chained
+/// equality `x = 3 = 3`. Once the history holds `max_entries` entries,
+/// recording a new one drops the oldest.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::{Calculation, History};
+///
+/// let mut history = History::new(2);
+/// history.record(&Calculation::new("1 + 1", 2.0));
+/// history.record(&Calculation::new("2 + 2", 4.0));
+/// history.record(&Calculation::new("3 + 3", 6.0));
+/// assert_eq!(history.entries(), ["2 + 2 = 4", "3 + 3 = 6"]);
+/// assert_eq!(history.expression(1), Some("2 + 2"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct History {
+    entries: Vec<String>,
+    max_entries: usize,
+}
+
+impl History {
+    /// Creates an empty history that keeps at most `max_entries` entries.
+    pub fn new(max_entries: usize) -> Self {
+        History {
+            entries: Vec::new(),
+            max_entries,
+        }
+    }
+
+    /// Records a successful calculation as `expression = result`, or an
+    /// assignment such as `x = 2 + 1` as `x = 2 + 1 → 3`.
+    pub fn record(&mut self, calculation: &Calculation) {
+        let entry = match split_assignment(&calculation.input) {
+            Some(_) => format!("{} → {}", calculation.input, calculation.result),
+            None => calculation.to_string(),
+        };
+        self.push(entry);
+    }
+
+    /// Appends a raw entry, dropping the oldest entries beyond the limit.
+    pub fn push(&mut self, entry: String) {
+        self.entries.push(entry);
+        if self.entries.len() > self.max_entries {
+            let excess = self.entries.len() - self.max_entries;
+            self.entries.drain(..excess);
+        }
+    }
+
+    /// Removes all entries.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Returns all entries, oldest first.
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Returns the expression of entry `number` (counting from 1), without
+    /// its `= result` or `→ result` suffix.
+    pub fn expression(&self, number: usize) -> Option<&str> {
+        let entry = self.entries.get(number.checked_sub(1)?)?;
+        Some(
+            entry
+                .rsplit_once(" → ")
+                .or_else(|| entry.rsplit_once(" = "))
+                .map_or(entry.as_str(), |(expression, _)| expression),
+        )
+    }
+
+    /// Returns the entries containing `term`, ignoring case, with their
+    /// numbers (counting from 1), oldest first.
+    ///
+    /// # Examples
+    /// ```
+    /// use rust_calculator_cli::{Calculation, History};
+    ///
+    /// let mut history = History::default();
+    /// history.record(&Calculation::new("sqrt(16)", 4.0));
+    /// history.record(&Calculation::new("2 + 2", 4.0));
+    /// assert_eq!(history.search("SQRT"), [(1, "sqrt(16) = 4")]);
+    /// ```
+    pub fn search(&self, term: &str) -> Vec<(usize, &str)> {
+        let term = term.to_lowercase();
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.to_lowercase().contains(&term))
+            .map(|(i, entry)| (i + 1, entry.as_str()))
+            .collect()
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History::new(DEFAULT_MAX_ENTRIES)
+    }
+}
+
+impl std::fmt::Display for History {
+    /// Formats the history as a numbered list, one entry per line.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, entry) in self.entries.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}: {}", i + 1, entry)?;
+        }
+        Ok(())
+    }
+}
+
+/// Loads history entries saved by [`save_history`], one per line.
+///
+/// A missing file yields an empty history. Any other read error is reported
+/// as a warning on stderr and also yields an empty history, so a broken
+/// history file never stops the calculator from starting.
+pub fn load_history(path: &Path) -> Vec<String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(str::to_string)
+            .collect(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => {
+            eprintln!(
+                "Warning: Could not read history from {}: {}",
+                path.display(),
+                e
+            );
+            Vec::new()
+        }
+    }
+}
+
+/// Saves the history entries to `path`, one per line, replacing the file.
+///
+/// # Errors
+/// Returns an error if the file cannot be written.
+pub fn save_history(path: &Path, history: &History) -> io::Result<()> {
+    let mut contents = history.entries().join("\n");
+    contents.push('\n');
+    fs::write(path, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!(
+            "rust_calculator_history_{}_{}",
+            name,
+            std::process::id()
+        ))
+    }
+
+    #[test]
+    fn test_record_formats_entry() {
+        let mut history = History::default();
+        history.record(&Calculation::new("5 + 5 ", 10.0));
+        assert_eq!(history.entries(), ["5 + 5 = 10"]);
+    }
+
+    #[test]
+    fn test_history_is_capped() {
+        let mut history = History::new(3);
+        for i in 1..=5 {
+            history.record(&Calculation::new(&i.to_string(), i as f64));
+        }
+        assert_eq!(history.entries(), ["3 = 3", "4 = 4", "5 = 5"]);
+    }
+
+    #[test]
+    fn test_expression_by_number() {
+        let mut history = History::default();
+        history.record(&Calculation::new("1 + 2", 3.0));
+        history.record(&Calculation::new("x = 4", 4.0));
+        assert_eq!(history.entries()[1], "x = 4 → 4");
+        assert_eq!(history.expression(1), Some("1 + 2"));
+        assert_eq!(history.expression(2), Some("x = 4"));
+        assert_eq!(history.expression(0), None);
+        assert_eq!(history.expression(3), None);
+    }
+
+    #[test]
+    fn test_search_ignores_case() {
+        let mut history = History::default();
+        history.record(&Calculation::new("sqrt(16)", 4.0));
+        history.record(&Calculation::new("2 + 2", 4.0));
+        history.record(&Calculation::new("SQRT(9) * 2", 6.0));
+        assert_eq!(
+            history.search("sqrt"),
+            [(1, "sqrt(16) = 4"), (3, "SQRT(9) * 2 = 6")]
+        );
+        assert_eq!(
+            history.search("= 4"),
+            [(1, "sqrt(16) = 4"), (2, "2 + 2 = 4")]
+        );
+        assert!(history.search("log").is_empty());
+    }
+
+    #[test]
+    fn test_display_numbers_entries() {
+        let mut history = History::default();
+        history.record(&Calculation::new("1 + 1", 2.0));
+        history.record(&Calculation::new("2 * 3", 6.0));
+        assert_eq!(history.to_string(), "1: 1 + 1 = 2\n2: 2 * 3 = 6");
+    }
+
+    #[test]
+    fn test_save_and_load_history() {
+        let path = temp_path("round_trip");
+        let mut history = History::default();
+        history.record(&Calculation::new("1 + 1", 2.0));
+        history.record(&Calculation::new("x = 3", 3.0));
+
+        save_history(&path, &history).unwrap();
+        let entries = load_history(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(entries, ["1 + 1 = 2", "x = 3 → 3"]);
+    }
+
+    #[test]
+    fn test_load_missing_history() {
+        let path = temp_path("missing");
+        assert!(load_history(&path).is_empty());
+    }
+
+    #[test]
+    fn test_load_unreadable_history() {
+        // A directory can't be read as a file.
+        let path = temp_path("directory");
+        fs::create_dir_all(&path).unwrap();
+        let entries = load_history(&path);
+        fs::remove_dir(&path).unwrap();
+
+        assert!(entries.is_empty());
+    }
+}
@@ -0,0 +1,118 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by the signal handler when Ctrl-C is pressed, and cleared by
+/// [`take_interrupt`].
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// What the calculator does about a Ctrl-C, as decided by
+/// [`Interrupts::interrupt`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterruptAction {
+    /// Tell the user how to quit and keep reading input
+    Warn,
+    /// Quit, as on the end of input
+    Exit,
+}
+
+/// Keeps track of Ctrl-C presses in the interactive calculator.
+///
+/// The first Ctrl-C only warns; pressing it again before entering another
+/// line quits.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::{InterruptAction, Interrupts};
+///
+/// let mut interrupts = Interrupts::default();
+/// assert_eq!(interrupts.interrupt(), InterruptAction::Warn);
+/// assert_eq!(interrupts.interrupt(), InterruptAction::Exit);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Interrupts {
+    /// Whether Ctrl-C was pressed since the last line of input
+    pending: bool,
+}
+
+impl Interrupts {
+    /// Records a Ctrl-C and returns what to do about it.
+    pub fn interrupt(&mut self) -> InterruptAction {
+        if self.pending {
+            InterruptAction::Exit
+        } else {
+            self.pending = true;
+            InterruptAction::Warn
+        }
+    }
+
+    /// Records that a line was entered, so the next Ctrl-C only warns again.
+    pub fn reset(&mut self) {
+        self.pending = false;
+    }
+}
+
+/// Catches Ctrl-C (`SIGINT`) so that it no longer ends the process, and
+/// instead is reported by [`take_interrupt`].
+///
+/// This is only supported on Unix; elsewhere Ctrl-C still ends the process.
+pub fn install_interrupt_handler() {
+    #[cfg(unix)]
+    {
+        use std::os::raw::c_int;
+
+        /// The signal number of `SIGINT`, which is the same on all Unix systems.
+        const SIGINT: c_int = 2;
+
+        extern "C" {
+            fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> usize;
+        }
+
+        extern "C" fn on_interrupt(_signum: c_int) {
+            // Storing to an atomic is all that is safe to do in a signal handler.
+            INTERRUPTED.store(true, Ordering::SeqCst);
+        }
+
+        // SAFETY: the handler only stores to an atomic, which is
+        // async-signal-safe.
+        unsafe {
+            signal(SIGINT, on_interrupt);
+        }
+    }
+}
+
+/// Returns `true` if Ctrl-C was pressed since the last call, once the handler
+/// is installed with [`install_interrupt_handler`].
+pub fn take_interrupt() -> bool {
+    INTERRUPTED.swap(false, Ordering::SeqCst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_interrupt_warns() {
+        let mut interrupts = Interrupts::default();
+        assert_eq!(interrupts.interrupt(), InterruptAction::Warn);
+    }
+
+    #[test]
+    fn test_second_interrupt_exits() {
+        let mut interrupts = Interrupts::default();
+        interrupts.interrupt();
+        assert_eq!(interrupts.interrupt(), InterruptAction::Exit);
+        assert_eq!(interrupts.interrupt(), InterruptAction::Exit);
+    }
+
+    #[test]
+    fn test_input_resets_interrupts() {
+        let mut interrupts = Interrupts::default();
+        interrupts.interrupt();
+        interrupts.reset();
+        assert_eq!(interrupts.interrupt(), InterruptAction::Warn);
+    }
+
+    #[test]
+    fn test_no_interrupt_without_signal() {
+        assert!(!take_interrupt());
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{CalcError, Value};
+
+/// Formats the outcome of evaluating `expression` as a JSON object.
+///
+/// A success becomes `{"expression":"...","result":...}` and a failure
+/// `{"expression":"...","error":"..."}`, with the error's message. Results
+/// are written as JSON numbers, so integers keep a `.0` such as `10.0`.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::{evaluate, json_result};
+///
+/// assert_eq!(
+///     json_result("5 + 5", &evaluate("5 + 5")),
+///     r#"{"expression":"5 + 5","result":10.0}"#
+/// );
+/// ```
+pub fn json_result(expression: &str, result: &Result<f64, CalcError>) -> String {
+    match result {
+        Ok(value) => format!(
+            "{{\"expression\":{},\"result\":{}}}",
+            json_string(expression),
+            json_number(*value)
+        ),
+        Err(e) => format!(
+            "{{\"expression\":{},\"error\":{}}}",
+            json_string(expression),
+            json_string(&e.to_string())
+        ),
+    }
+}
+
+/// Formats a [`Value`] that `expression` evaluated to as a JSON object.
+///
+/// Numbers are written like [`json_result`] does, and the result of a
+/// comparison as a JSON boolean.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::{json_value, Value};
+///
+/// assert_eq!(
+///     json_value("5 > 3", Value::Bool(true)),
+///     r#"{"expression":"5 > 3","result":true}"#
+/// );
+/// ```
+pub fn json_value(expression: &str, value: Value) -> String {
+    match value {
+        Value::Number(number) => json_result(expression, &Ok(number)),
+        Value::Bool(result) => format!(
+            "{{\"expression\":{},\"result\":{}}}",
+            json_string(expression),
+            result
+        ),
+    }
+}
+
+/// Writes a number as a JSON number. JSON has no infinity or NaN, so those
+/// become `null`.
+fn json_number(value: f64) -> String {
+    if value.is_finite() {
+        format!("{:?}", value)
+    } else {
+        "null".to_string()
+    }
+}
+
+/// Writes a string as a quoted JSON string, escaping where needed.
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_result_success() {
+        assert_eq!(
+            json_result("5 + 5", &Ok(10.0)),
+            r#"{"expression":"5 + 5","result":10.0}"#
+        );
+        assert_eq!(
+            json_result("1 / 4", &Ok(0.25)),
+            r#"{"expression":"1 / 4","result":0.25}"#
+        );
+    }
+
+    #[test]
+    fn test_json_result_error() {
+        assert_eq!(
+            json_result("5 / 0", &Err(CalcError::DivisionByZero)),
+            r#"{"expression":"5 / 0","error":"Cannot divide by zero"}"#
+        );
+    }
+
+    #[test]
+    fn test_json_value() {
+        assert_eq!(
+            json_value("1 == 2", Value::Bool(false)),
+            r#"{"expression":"1 == 2","result":false}"#
+        );
+        assert_eq!(
+            json_value("5 + 5", Value::Number(10.0)),
+            r#"{"expression":"5 + 5","result":10.0}"#
+        );
+    }
+
+    #[test]
+    fn test_json_string_escapes() {
+        assert_eq!(json_string(r#"a "b" \ c"#), r#""a \"b\" \\ c""#);
+        assert_eq!(json_string("tab\there"), r#""tab\there""#);
+        assert_eq!(json_string("\u{1}"), r#""\u0001""#);
+    }
+
+    #[test]
+    fn test_json_number_non_finite() {
+        assert_eq!(json_number(f64::INFINITY), "null");
+        assert_eq!(json_number(-2.5), "-2.5");
+    }
+}
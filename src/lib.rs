@@ -0,0 +1,1814 @@
+//! Math logic behind the `rust-calculator-cli` binary.
+//!
+//! The same functions the interactive calculator uses are available to other
+//! programs: [`parse_input`] and [`calculate`] for simple `number operator number`
+//! input, [`evaluate`] for full expressions with precedence and parentheses, and
+//! [`call_function`] and [`constant`] for named functions such as `sqrt` and
+//! constants such as `pi`.
+//!
+//! State that carries over between calculations, such as the angle mode, `ans`
+//! and variables, lives in a [`Context`] passed to [`evaluate_with`] and
+//! [`execute`]. A [`Session`] adds the interactive commands and [`History`] on
+//! top.
+//!
+//! # Examples
+//! ```
+//! use rust_calculator_cli::{calculate, evaluate, parse_input};
+//!
+//! let (num1, num2, operator) = parse_input("6 * 7").unwrap();
+//! assert_eq!(calculate(num1, num2, operator).unwrap(), 42.0);
+//!
+//! assert_eq!(evaluate("(2 + 3) * 4").unwrap(), 20.0);
+//! ```
+
+mod calculation;
+mod color;
+mod complex;
+mod config;
+mod context;
+mod csv;
+mod error;
+mod exact;
+mod fraction;
+mod functions;
+mod help;
+mod history;
+mod interrupt;
+mod json;
+pub mod parser;
+mod random;
+mod rpn;
+mod session;
+mod settings;
+mod statistics;
+pub mod tokenizer;
+mod units;
+mod value;
+
+pub use calculation::Calculation;
+pub use color::{color_enabled, colorize, Color};
+pub use complex::Complex;
+pub use config::{load_config, Config};
+pub use context::{
+    AngleMode, Context, Locale, NumberMode, Overflow, DEFAULT_MAX_DEPTH, DEFAULT_MAX_INPUT_LEN,
+    INT_WIDTHS,
+};
+pub use csv::csv_row;
+pub use error::CalcError;
+pub use exact::Exact;
+pub use fraction::Fraction;
+pub use functions::{call_function, constant, factorial, is_function};
+pub use help::{explain, help_text};
+pub use history::{load_history, save_history, History, DEFAULT_MAX_ENTRIES};
+pub use interrupt::{install_interrupt_handler, take_interrupt, InterruptAction, Interrupts};
+pub use json::{json_result, json_value};
+pub use random::Rng;
+pub use rpn::evaluate_rpn;
+pub use session::{is_quit_command, InputMode, Response, Session, DEFAULT_PROMPT};
+pub use settings::{
+    format_currency, format_engineering, format_with_separators, OutputBase, RoundMode, Settings,
+    DEFAULT_CURRENCY_DECIMALS, DEFAULT_CURRENCY_SYMBOL,
+};
+pub use statistics::{aggregate, LIST_COMMANDS};
+pub use units::{convert, Dimension};
+pub use value::Value;
+
+/// Binary operators accepted by [`parse_input`] and [`calculate`].
+pub const OPERATORS: [&str; 12] = [
+    "+", "-", "*", "/", "//", "%", "^", "&", "|", "xor", "<<", ">>",
+];
+
+/// Comparison operators accepted by [`compare`] and in expressions, where
+/// they produce a [`Value::Bool`].
+pub const COMPARISON_OPERATORS: [&str; 6] = ["<", ">", "<=", ">=", "==", "!="];
+
+/// Relative tolerance within which [`compare`] treats two numbers as equal.
+pub const COMPARISON_EPSILON: f64 = 1e-9;
+
+/// Largest integer up to which every integer is exactly representable as an
+/// `f64` (2^53).
+const MAX_EXACT_INTEGER: u128 = 1 << 53;
+
+/// Parses user input into two numbers and an operator.
+///
+/// Spaces around the operator are optional, so `5+5`, `5 +5` and `5+ 5` all
+/// parse like `5 + 5`. A `-` or `+` directly in front of a number, at the
+/// start of the input or right after the operator, is that number's sign:
+/// `-5-3` is `-5 - 3` and `5--3` is `5 - -3`. A sign separated from its
+/// number by a space isn't accepted. Word operators such as `xor` need
+/// spaces around them, since `5xor3` reads as a single number.
+///
+/// Integers beyond 2^53 in magnitude can't be stored exactly and are rounded,
+/// which is reported as a warning on stderr (see [`precision_warning`]).
+///
+/// # Arguments
+/// * `input` - A string slice containing the input to be parsed
+///
+/// # Returns
+/// * `Result<(f64, f64, &str), CalcError>` - A tuple containing:
+///   - first number (f64)
+///   - second number (f64)
+///   - operator (&str)
+///
+/// # Errors
+/// * [`CalcError::InvalidInput`] if the input doesn't consist of exactly two
+///   numbers with an operator between them; use [`evaluate`] for longer
+///   chains such as `5 + 3 - 2`
+/// * [`CalcError::ParseNumber`] if the numbers cannot be converted to f64
+///   (decimal numbers as well as `0x` hexadecimal and `0b` binary integers
+///   are accepted)
+/// * [`CalcError::NotANumber`] if a number is written as `nan`
+/// * [`CalcError::InvalidOperator`] if the operator is not one of the allowed
+///   [`OPERATORS`]
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::parse_input;
+///
+/// let input = "5.5 + 3.2";
+/// let result = parse_input(input);
+/// assert!(result.is_ok());
+/// let (num1, num2, op) = result.unwrap();
+/// assert_eq!(num1, 5.5);
+/// assert_eq!(num2, 3.2);
+/// assert_eq!(op, "+");
+///
+/// assert_eq!(parse_input("5-3"), parse_input("5 - 3"));
+/// assert_eq!(parse_input("5--3"), Ok((5.0, -3.0, "-")));
+/// ```
+pub fn parse_input(input: &str) -> Result<(f64, f64, &str), CalcError> {
+    // Every slice below ends where `input` ends, so its position is the
+    // difference in length.
+    let position = |rest: &str| input.len() - rest.len();
+
+    let start = input.trim_start();
+    let (first, rest) = split_number(start);
+    let rest = rest.trim_start();
+    if first.is_empty() || rest.trim_end().is_empty() {
+        return Err(CalcError::InvalidInput);
+    }
+
+    // Try longer operators first, so `//` isn't read as `/`.
+    let operator = OPERATORS
+        .iter()
+        .filter(|operator| rest.starts_with(*operator))
+        .max_by_key(|operator| operator.len())
+        .ok_or_else(|| {
+            let token = rest.split_whitespace().next().unwrap_or(rest);
+            CalcError::InvalidOperator {
+                operator: token.to_string(),
+                position: Some(position(rest)),
+            }
+        })?;
+
+    let operand = rest[operator.len()..].trim_start();
+    let (second, rest) = split_number(operand);
+    if second.is_empty() {
+        return Err(CalcError::InvalidInput);
+    }
+
+    let num1: f64 = parse_number(first).map_err(|e| e.at(position(start)))?;
+    let num2: f64 = parse_number(second).map_err(|e| e.at(position(operand)))?;
+    let num1 = finite_literal(num1, false)?;
+    let num2 = finite_literal(num2, false)?;
+    if !rest.trim().is_empty() {
+        return Err(CalcError::InvalidInput);
+    }
+
+    for warning in [first, second].into_iter().filter_map(precision_warning) {
+        eprintln!("Warning: {}", warning);
+    }
+
+    Ok((num1, num2, operator))
+}
+
+/// Splits a number token off the start of `input`, returning it and the
+/// rest.
+///
+/// The token is an optional sign followed by letters, digits and `.`, which
+/// also covers `0x` and `0b` prefixes and words such as `abc` so they're
+/// reported as invalid numbers. A sign after the `e` of a decimal exponent,
+/// as in `1e-5`, is part of the token.
+fn split_number(input: &str) -> (&str, &str) {
+    let bytes = input.as_bytes();
+    let hexadecimal = input
+        .get(..2)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case("0x"));
+    let mut end = usize::from(matches!(bytes.first(), Some(b'-' | b'+')));
+    while let Some(&byte) = bytes.get(end) {
+        let exponent_sign = matches!(byte, b'-' | b'+')
+            && !hexadecimal
+            && matches!(bytes[end - 1], b'e' | b'E')
+            && bytes[..end - 1].iter().any(u8::is_ascii_digit);
+        if byte.is_ascii_alphanumeric() || byte == b'.' || exponent_sign {
+            end += 1;
+        } else {
+            break;
+        }
+    }
+    input.split_at(end)
+}
+
+/// Explains that an integer literal gets rounded, if its magnitude exceeds
+/// 2^53 and it therefore can't be represented exactly as an `f64`.
+///
+/// Decimal, `0x` hexadecimal and `0b` binary integers are checked; other
+/// tokens, including decimal fractions, never produce a warning.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::precision_warning;
+///
+/// assert!(precision_warning("9007199254740993").is_some());
+/// assert_eq!(precision_warning("1000000"), None);
+/// ```
+pub fn precision_warning(token: &str) -> Option<String> {
+    let magnitude = integer_magnitude(token.strip_prefix(['-', '+']).unwrap_or(token))?;
+    if magnitude <= MAX_EXACT_INTEGER {
+        return None;
+    }
+    let rounded = parse_number(token).ok()?;
+    Some(format!(
+        "{} can't be represented exactly and is rounded to {}",
+        token, rounded
+    ))
+}
+
+/// The value of a decimal, `0x` hexadecimal or `0b` binary integer literal
+/// without a sign, or `None` for any other token. Literals too large for a
+/// `u128` give `u128::MAX`.
+fn integer_magnitude(token: &str) -> Option<u128> {
+    let digits = token.replace('_', "");
+    match digits.get(..2) {
+        Some("0x" | "0X") => u128::from_str_radix(&digits[2..], 16).ok(),
+        Some("0b" | "0B") => u128::from_str_radix(&digits[2..], 2).ok(),
+        _ if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) => {
+            // Too many digits for a u128 is certainly too many for an f64.
+            Some(digits.parse().unwrap_or(u128::MAX))
+        }
+        _ => None,
+    }
+}
+
+/// The exact value of an integer literal that an `f64` can't hold exactly,
+/// as its magnitude exceeds 2^53, or `None` for other tokens and integers
+/// beyond `i128`.
+pub(crate) fn large_integer(token: &str) -> Option<i128> {
+    integer_magnitude(token)
+        .filter(|&magnitude| magnitude > MAX_EXACT_INTEGER)
+        .and_then(|magnitude| i128::try_from(magnitude).ok())
+}
+
+/// Warnings from [`precision_warning`] for every integer literal in `input`.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::precision_warnings;
+///
+/// assert_eq!(precision_warnings("9007199254740993 + 1").len(), 1);
+/// assert!(precision_warnings("2^53 + 1").is_empty());
+/// ```
+pub fn precision_warnings(input: &str) -> Vec<String> {
+    input
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '_'))
+        .filter_map(precision_warning)
+        .collect()
+}
+
+/// Parses a single token as a number.
+///
+/// Besides decimal numbers, integers with a `0x` (hexadecimal) or `0b`
+/// (binary) prefix are accepted. Infinity is only available as the name
+/// `inf`, so a token such as `inf` or `infinity` is rejected here. A literal
+/// beyond the range of an `f64`, such as `1e400`, still reads as infinity;
+/// see [`finite_literal`].
+///
+/// Digits may be grouped with underscores as in `1_000_000`. Every
+/// underscore must be between two digits, so `1__0` and `5_` are rejected.
+pub(crate) fn parse_number(token: &str) -> Result<f64, CalcError> {
+    let error = || CalcError::ParseNumber {
+        token: token.to_string(),
+        position: None,
+    };
+    let radix = match token.get(..2) {
+        Some("0x" | "0X") => Some(16),
+        Some("0b" | "0B") => Some(2),
+        _ => None,
+    };
+
+    match radix {
+        Some(radix) => {
+            let digits =
+                without_digit_separators(&token[2..], u8::is_ascii_hexdigit).ok_or_else(error)?;
+            if digits.starts_with('+') {
+                return Err(error());
+            }
+            u64::from_str_radix(&digits, radix)
+                .map(|number| number as f64)
+                .map_err(|_| error())
+        }
+        None => match without_digit_separators(token, u8::is_ascii_digit)
+            .ok_or_else(error)?
+            .parse::<f64>()
+        {
+            Ok(number) if number.is_nan() => Err(CalcError::NotANumber),
+            Ok(number) if number.is_infinite() && token.contains(['i', 'I']) => Err(error()),
+            Ok(number) => Ok(number),
+            Err(_) => Err(error()),
+        },
+    }
+}
+
+/// Rejects a number literal too large for an `f64`, such as `1e400`, which
+/// reads as infinity, unless `allow_infinity` is set.
+///
+/// # Errors
+/// * [`CalcError::Overflow`] if `value` is infinite and infinity isn't allowed
+pub(crate) fn finite_literal(value: f64, allow_infinity: bool) -> Result<f64, CalcError> {
+    if value.is_infinite() && !allow_infinity {
+        Err(CalcError::Overflow)
+    } else {
+        Ok(value)
+    }
+}
+
+/// Removes the underscores that group the digits of a number, or returns
+/// `None` if one of them isn't between two bytes accepted by `is_digit`.
+fn without_digit_separators(token: &str, is_digit: fn(&u8) -> bool) -> Option<String> {
+    let bytes = token.as_bytes();
+    let well_placed = bytes.iter().enumerate().all(|(i, &byte)| {
+        byte != b'_' || (i > 0 && is_digit(&bytes[i - 1]) && bytes.get(i + 1).is_some_and(is_digit))
+    });
+    well_placed.then(|| token.replace('_', ""))
+}
+
+/// Performs a mathematical calculation with two numbers and an operator.
+///
+/// # Arguments
+/// * `num1` - First number (f64)
+/// * `num2` - Second number (f64)
+/// * `operator` - Mathematical operator as string slice
+///
+/// # Returns
+/// * `Result<f64, CalcError>` - The result of the calculation
+///
+/// # Supported Operators
+/// * `+` - Addition
+/// * `-` - Subtraction
+/// * `*` - Multiplication
+/// * `/` - Division
+/// * `//` - Floor division (rounds toward negative infinity)
+/// * `%` - Remainder (takes the sign of `num1`)
+/// * `^` - Exponentiation
+/// * `&` - Bitwise AND
+/// * `|` - Bitwise OR
+/// * `xor` - Bitwise exclusive OR (`^` is already exponentiation)
+/// * `<<` - Left shift
+/// * `>>` - Right shift (keeps the sign of `num1`)
+///
+/// The bitwise and shift operators work on whole numbers only; both operands
+/// are converted to `i64`, combined and converted back.
+///
+/// A finite calculation whose result is too large for an `f64` is reported as
+/// an overflow instead of returning infinity. An operand that is already
+/// infinite is passed through, so `inf + 1` is still `inf`, while an
+/// undefined combination such as `inf - inf` is a domain error.
+///
+/// # Errors
+/// * [`CalcError::DivisionByZero`] if division or remainder by zero is attempted
+/// * [`CalcError::Overflow`] if finite operands produce an infinite result
+/// * [`CalcError::NotANumber`] if the result is NaN because of a NaN operand
+/// * [`CalcError::Domain`] if infinite operands have no defined result, as in
+///   `inf - inf` or `inf * 0`
+/// * [`CalcError::Domain`] if a negative number is raised to a fractional power,
+///   a bitwise operator is given a fractional operand, or a shift amount is
+///   negative or 64 or more
+/// * [`CalcError::InvalidOperator`] if an unsupported operator is used
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::calculate;
+///
+/// let result = calculate(10.0, 5.0, "+");
+/// assert_eq!(result.unwrap(), 15.0);
+///
+/// let divide_by_zero = calculate(5.0, 0.0, "/");
+/// assert!(divide_by_zero.is_err());
+/// ```
+pub fn calculate(num1: f64, num2: f64, operator: &str) -> Result<f64, CalcError> {
+    let result = apply_operator(num1, num2, operator)?;
+    if result.is_nan() && (num1.is_infinite() || num2.is_infinite()) {
+        return Err(CalcError::Domain(format!(
+            "{} {} {} is undefined",
+            num1, operator, num2
+        )));
+    }
+    if result.is_nan() {
+        return Err(CalcError::NotANumber);
+    }
+    if result.is_infinite() && num1.is_finite() && num2.is_finite() {
+        return Err(CalcError::Overflow);
+    }
+    Ok(result)
+}
+
+/// Returns `true` if `a` and `b` differ by at most `epsilon`.
+///
+/// This is meant for checking floating-point results, which are rarely exact:
+/// `0.1 + 0.2` is `0.30000000000000004`, not `0.3`. Equal infinities count as
+/// equal, while NaN is never equal to anything, not even itself.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::approx_eq;
+///
+/// assert_ne!(0.1 + 0.2, 0.3);
+/// assert!(approx_eq(0.1 + 0.2, 0.3, 1e-10));
+/// assert!(!approx_eq(1.0, 1.1, 1e-10));
+/// assert!(approx_eq(f64::INFINITY, f64::INFINITY, 1e-10));
+/// assert!(!approx_eq(f64::NAN, f64::NAN, 1e-10));
+/// ```
+pub fn approx_eq(a: f64, b: f64, epsilon: f64) -> bool {
+    a == b || (a - b).abs() <= epsilon
+}
+
+/// Compares two numbers with a comparison operator.
+///
+/// Floating-point results are rarely exact, so `==` accepts numbers whose
+/// difference is at most [`COMPARISON_EPSILON`] times the larger magnitude
+/// (or times 1 for numbers smaller than 1). `0.1 + 0.2 == 0.3` is therefore
+/// `true`. The other operators agree with this: `<` and `>` are `false` for
+/// numbers that count as equal, and `<=` and `>=` are `true`. An infinite
+/// operand is compared exactly, so `1 < inf` and `-inf != inf`.
+///
+/// # Arguments
+/// * `num1` - Left operand
+/// * `num2` - Right operand
+/// * `operator` - One of `<`, `>`, `<=`, `>=`, `==` and `!=`
+///
+/// # Errors
+/// * [`CalcError::NotANumber`] if an operand is NaN
+/// * [`CalcError::InvalidOperator`] if `operator` isn't a comparison
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::compare;
+///
+/// assert_eq!(compare(5.0, 3.0, ">"), Ok(true));
+/// assert_eq!(compare(0.1 + 0.2, 0.3, "=="), Ok(true));
+/// ```
+pub fn compare(num1: f64, num2: f64, operator: &str) -> Result<bool, CalcError> {
+    if num1.is_nan() || num2.is_nan() {
+        return Err(CalcError::NotANumber);
+    }
+    let equal = if num1.is_finite() && num2.is_finite() {
+        let tolerance = COMPARISON_EPSILON * num1.abs().max(num2.abs()).max(1.0);
+        approx_eq(num1, num2, tolerance)
+    } else {
+        num1 == num2
+    };
+    match operator {
+        "==" => Ok(equal),
+        "!=" => Ok(!equal),
+        "<" => Ok(!equal && num1 < num2),
+        ">" => Ok(!equal && num1 > num2),
+        "<=" => Ok(equal || num1 < num2),
+        ">=" => Ok(equal || num1 > num2),
+        _ => Err(CalcError::InvalidOperator {
+            operator: operator.to_string(),
+            position: None,
+        }),
+    }
+}
+
+/// Applies `operator` to its operands without checking the result for overflow.
+fn apply_operator(num1: f64, num2: f64, operator: &str) -> Result<f64, CalcError> {
+    match operator {
+        "+" => Ok(num1 + num2),
+        "-" => Ok(num1 - num2),
+        "*" => Ok(num1 * num2),
+        "/" => {
+            if num2 == 0.0 {
+                Err(CalcError::DivisionByZero)
+            } else {
+                Ok(num1 / num2)
+            }
+        }
+        "//" => {
+            if num2 == 0.0 {
+                Err(CalcError::DivisionByZero)
+            } else {
+                Ok((num1 / num2).floor())
+            }
+        }
+        "%" => {
+            if num2 == 0.0 {
+                Err(CalcError::DivisionByZero)
+            } else {
+                Ok(num1 % num2)
+            }
+        }
+        "^" => {
+            let result = num1.powf(num2);
+            if result.is_nan() {
+                Err(CalcError::Domain(
+                    "Cannot raise a negative number to a fractional power".to_string(),
+                ))
+            } else {
+                Ok(result)
+            }
+        }
+        "&" => Ok((to_integer(num1)? & to_integer(num2)?) as f64),
+        "|" => Ok((to_integer(num1)? | to_integer(num2)?) as f64),
+        "xor" => Ok((to_integer(num1)? ^ to_integer(num2)?) as f64),
+        "<<" => Ok((to_integer(num1)? << shift_amount(num2)?) as f64),
+        ">>" => Ok((to_integer(num1)? >> shift_amount(num2)?) as f64),
+        _ => Err(CalcError::InvalidOperator {
+            operator: operator.to_string(),
+            position: None,
+        }),
+    }
+}
+
+/// Converts an operand of a bitwise operator to an integer.
+fn to_integer(number: f64) -> Result<i64, CalcError> {
+    // `i64::MAX as f64` rounds up to 2^63, which is already out of range.
+    if number.fract() != 0.0 || number < i64::MIN as f64 || number >= i64::MAX as f64 {
+        return Err(CalcError::Domain(format!(
+            "Bitwise operators require integer operands, got {}",
+            number
+        )));
+    }
+    Ok(number as i64)
+}
+
+/// Converts the right operand of a shift to a shift amount in `0..64`.
+fn shift_amount(number: f64) -> Result<u32, CalcError> {
+    match to_integer(number)? {
+        amount @ 0..=63 => Ok(amount as u32),
+        amount if amount < 0 => Err(CalcError::Domain(format!(
+            "Cannot shift by a negative amount, got {}",
+            amount
+        ))),
+        amount => Err(CalcError::Domain(format!(
+            "Cannot shift by 64 or more bits, got {}",
+            amount
+        ))),
+    }
+}
+
+/// Evaluates a full expression such as `(2 + 3) * 4`, respecting operator precedence.
+///
+/// Multiplication, division and remainder bind tighter than addition and
+/// subtraction, exponentiation binds tightest of all (and groups to the right),
+/// and parentheses can be used for grouping. Parsing is delegated to
+/// [`parser::parse_expression`].
+///
+/// # Arguments
+/// * `input` - A string slice containing the expression to evaluate
+///
+/// # Returns
+/// * `Result<f64, CalcError>` - The value of the expression
+///
+/// # Errors
+/// Returns an error if the expression is malformed (e.g. `2 + + 3` or
+/// unbalanced parentheses) or any single calculation fails (see [`calculate`]).
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::evaluate;
+///
+/// let result = evaluate("2 + 3 * 4");
+/// assert_eq!(result.unwrap(), 14.0);
+/// ```
+pub fn evaluate(input: &str) -> Result<f64, CalcError> {
+    evaluate_with(input, &Context::default())
+}
+
+/// Evaluates a full expression using the settings in `context`.
+///
+/// This behaves like [`evaluate`], except that settings such as the angle
+/// mode are taken from `context` instead of the defaults.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::{evaluate_with, AngleMode, Context};
+///
+/// let mut context = Context::default();
+/// context.angle_mode = AngleMode::Degrees;
+/// assert_eq!(evaluate_with("cos(0) + sin(90)", &context).unwrap(), 2.0);
+/// ```
+pub fn evaluate_with(input: &str, context: &Context) -> Result<f64, CalcError> {
+    parser::parse_expression(input, context)
+}
+
+/// Evaluates an expression that may be a comparison, such as `5 > 3`.
+///
+/// This behaves like [`evaluate_with`], but also accepts a single comparison
+/// (see [`compare`]) between two expressions, which evaluates to a
+/// [`Value::Bool`]. Comparisons can't be chained, so `1 < 2 < 3` is an error.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::{evaluate_value, Context, Value};
+///
+/// let context = Context::default();
+/// assert_eq!(evaluate_value("2 * 3 >= 6", &context), Ok(Value::Bool(true)));
+/// assert_eq!(evaluate_value("2 * 3", &context), Ok(Value::Number(6.0)));
+/// ```
+pub fn evaluate_value(input: &str, context: &Context) -> Result<Value, CalcError> {
+    parser::parse_value(input, context)
+}
+
+/// Rejects `input` if it has more than [`Context::max_input_len`] characters.
+pub(crate) fn check_input_length(input: &str, context: &Context) -> Result<(), CalcError> {
+    // The byte length is an upper bound, so most input is never counted.
+    if input.len() > context.max_input_len && input.chars().count() > context.max_input_len {
+        return Err(CalcError::InputTooLong(context.max_input_len));
+    }
+    Ok(())
+}
+
+/// Checks that `input` is a syntactically valid expression, without caring
+/// about its value.
+///
+/// Errors that depend on the values, such as division by zero, are ignored;
+/// see [`parser::check_expression`].
+///
+/// # Errors
+/// Any syntax error from [`evaluate`], such as [`CalcError::UnexpectedEnd`]
+/// or [`CalcError::UnmatchedParenthesis`], and
+/// [`CalcError::UnknownIdentifier`] for unknown names.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::{check_syntax, CalcError};
+///
+/// assert_eq!(check_syntax("5 / 0"), Ok(()));
+/// assert_eq!(check_syntax("5 + "), Err(CalcError::UnexpectedEnd));
+/// ```
+pub fn check_syntax(input: &str) -> Result<(), CalcError> {
+    parser::check_expression(input, &Context::default())
+}
+
+/// Evaluates a full expression like [`evaluate`] and pairs it with its result.
+///
+/// # Errors
+/// Any error from [`evaluate`].
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::evaluate_and_format;
+///
+/// let calculation = evaluate_and_format("2 + 3 * 4").unwrap();
+/// assert_eq!(calculation.result, 14.0);
+/// assert_eq!(calculation.to_string(), "2 + 3 * 4 = 14");
+/// ```
+pub fn evaluate_and_format(input: &str) -> Result<Calculation, CalcError> {
+    Ok(Calculation::new(input, evaluate(input)?))
+}
+
+/// What a successfully executed line of input produced.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// An expression was evaluated to a value
+    Value(f64),
+    /// An expression was evaluated exactly, see [`Context::number_mode`]
+    Exact(Exact),
+    /// A comparison was evaluated
+    Bool(bool),
+    /// A variable was assigned a value
+    Assignment(String, f64),
+}
+
+/// Executes a line of input, which is either an expression or an assignment
+/// of the form `name = expression`.
+///
+/// Expressions are evaluated like [`evaluate_value`], so they may be a
+/// comparison. Assignments store the value in [`Context::variables`] so later
+/// expressions can use it. Either way a numeric value becomes the new
+/// [`Context::ans`], unless it is a complex number with an imaginary part.
+/// An exact value is also kept as [`Context::exact_ans`], so that `ans` isn't
+/// rounded in the next exact calculation.
+///
+/// The `=` of an assignment is never part of `==`, `<=`, `>=` or `!=`, so
+/// `x == 3` is a comparison rather than an assignment to `x`.
+///
+/// An expression that starts with a binary operator continues from the
+/// previous result, so after `10` the input `+ 5` is `ans + 5`, which is `15`,
+/// and `* 2 + 1` is `ans * 2 + 1`. A leading `-` is only read this way if a
+/// space follows it: `- 5` subtracts 5 from `ans`, while `-5` is the negative
+/// number. The same applies to `xor`.
+///
+/// In a [`NumberMode`] other than [`NumberMode::Real`], an expression that
+/// only uses arithmetic the mode supports is evaluated exactly instead, such
+/// as `99999999999 * 99999999999` in integer mode; other expressions fall back
+/// to `f64`. Assignments always use `f64`.
+///
+/// # Arguments
+/// * `input` - A string slice containing the expression or assignment
+/// * `context` - Evaluation state, updated on success
+///
+/// # Returns
+/// * `Result<Outcome, CalcError>` - What the input produced
+///
+/// # Errors
+/// * [`CalcError::InvalidVariableName`] if the name being assigned doesn't
+///   start with a letter or underscore, contains anything other than letters,
+///   digits and underscores, or is a reserved name such as `ans` or `pi`
+/// * [`CalcError::InputTooLong`] if `input` has more than
+///   [`Context::max_input_len`] characters
+/// * Any error from evaluating the expression (see [`evaluate_with`]),
+///   including [`CalcError::UnknownIdentifier`] for undefined variables
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::{execute, Context, Outcome};
+///
+/// let mut context = Context::default();
+/// execute("x = 5", &mut context).unwrap();
+/// assert_eq!(execute("x * 3", &mut context), Ok(Outcome::Value(15.0)));
+/// ```
+pub fn execute(input: &str, context: &mut Context) -> Result<Outcome, CalcError> {
+    check_input_length(input, context)?;
+    let outcome = match split_assignment(input) {
+        Some((name, expression)) => {
+            let name = name.trim();
+            validate_variable_name(name)?;
+            let value = evaluate_with(expression, context)
+                .map_err(|e| e.shifted(input.len() - expression.len()))?;
+            context.variables.insert(name.to_string(), value);
+            Outcome::Assignment(name.to_string(), value)
+        }
+        None if starts_with_binary_operator(input) => {
+            let prefix = "ans ";
+            evaluate_line(&format!("{}{}", prefix, input), context)
+                .map_err(|e| e.shifted_back(prefix.len()))?
+        }
+        None => evaluate_line(input, context)?,
+    };
+
+    match &outcome {
+        Outcome::Value(value) | Outcome::Assignment(_, value) => context.set_ans(*value),
+        Outcome::Exact(exact) => {
+            context.ans = exact.approximate().unwrap_or(context.ans);
+            context.exact_ans = Some(exact.clone());
+        }
+        Outcome::Bool(_) => (),
+    }
+    Ok(outcome)
+}
+
+/// Defines a function of one argument from a definition such as
+/// `double(x) = x * 2`, as done by the `define` command, and returns its name.
+///
+/// The function is stored in [`Context::functions`] and can then be called
+/// like a built-in one, as in `double(21)`. Its body may call built-in
+/// functions and functions defined before it, but not itself. It is read with
+/// the locale, operator aliases and limits of `context`.
+///
+/// # Errors
+/// * [`CalcError::InvalidCommand`] if `definition` isn't of the form
+///   `name(parameter) = expression`, or a function of that name is already
+///   defined
+/// * [`CalcError::InvalidVariableName`] if the name or the parameter isn't a
+///   valid variable name, which also rules out built-in functions such as
+///   `sqrt`
+/// * [`CalcError::UnknownIdentifier`] if the body calls a function that isn't
+///   defined
+/// * Any error from parsing the body
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::{define_function, evaluate_with, Context};
+///
+/// let mut context = Context::default();
+/// define_function("double(x) = x * 2", &mut context).unwrap();
+/// assert_eq!(evaluate_with("double(21)", &context), Ok(42.0));
+/// assert!(define_function("double(y) = y + y", &mut context).is_err());
+/// ```
+pub fn define_function(definition: &str, context: &mut Context) -> Result<String, CalcError> {
+    let usage = || CalcError::InvalidCommand(format!("Usage: {}", DEFINE_USAGE));
+    let (header, body) = split_assignment(definition).ok_or_else(usage)?;
+    let (name, parameter) = header
+        .trim()
+        .strip_suffix(')')
+        .and_then(|header| header.split_once('('))
+        .ok_or_else(usage)?;
+    let (name, parameter) = (name.trim(), parameter.trim());
+    validate_variable_name(name)?;
+    validate_variable_name(parameter)?;
+    if context.functions.contains_key(name) {
+        return Err(CalcError::InvalidCommand(format!(
+            "Function '{}' is already defined",
+            name
+        )));
+    }
+
+    let body =
+        parser::parse_ast(body, context).map_err(|e| e.shifted(definition.len() - body.len()))?;
+    if let Some(unknown) = parser::called_functions(&body)
+        .into_iter()
+        .find(|called| !is_function(called) && !context.functions.contains_key(*called))
+    {
+        return Err(CalcError::UnknownIdentifier(unknown.to_string()));
+    }
+    context.functions.insert(
+        name.to_string(),
+        parser::UserFunction {
+            parameter: parameter.to_string(),
+            body,
+        },
+    );
+    Ok(name.to_string())
+}
+
+/// How a function is defined, shown when a definition is malformed.
+const DEFINE_USAGE: &str = "define name(x) = expression, e.g. define double(x) = x * 2";
+
+/// Evaluates an expression for [`execute`], exactly if the number mode allows.
+fn evaluate_line(input: &str, context: &Context) -> Result<Outcome, CalcError> {
+    Ok(match exact::evaluate_exact(input, context)? {
+        Some(exact) => Outcome::Exact(exact),
+        None => match evaluate_value(input, context)? {
+            Value::Number(value) => Outcome::Value(value),
+            Value::Bool(value) => Outcome::Bool(value),
+        },
+    })
+}
+
+/// Whether `input` starts with a binary operator and so continues from
+/// `ans`. `-` and `xor` only count if a space follows, since `-5` is a
+/// negative number and `xor` could start a name.
+fn starts_with_binary_operator(input: &str) -> bool {
+    let input = input.trim_start();
+    OPERATORS
+        .iter()
+        .chain(&COMPARISON_OPERATORS)
+        .filter(|operator| input.starts_with(*operator))
+        .max_by_key(|operator| operator.len())
+        .is_some_and(|operator| match *operator {
+            "-" | "xor" => input[operator.len()..].starts_with(char::is_whitespace),
+            _ => true,
+        })
+}
+
+/// Splits an assignment into the name and the expression at its `=`, or
+/// returns `None` if the first `=` belongs to a comparison such as `==`.
+pub(crate) fn split_assignment(input: &str) -> Option<(&str, &str)> {
+    let index = input.find('=')?;
+    let bytes = input.as_bytes();
+    let in_comparison = bytes.get(index + 1) == Some(&b'=')
+        || (index > 0 && matches!(bytes[index - 1], b'<' | b'>' | b'!'));
+    if in_comparison {
+        None
+    } else {
+        Some((&input[..index], &input[index + 1..]))
+    }
+}
+
+/// Checks that `name` can be used as a variable name.
+fn validate_variable_name(name: &str) -> Result<(), CalcError> {
+    let mut chars = name.chars();
+    let valid_start = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    let valid_rest = chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let reserved = ["ans", "inf", "mr", "xor"].contains(&name)
+        || constant(name).is_some()
+        || is_function(name);
+
+    if valid_start && valid_rest && !reserved {
+        Ok(())
+    } else {
+        Err(CalcError::InvalidVariableName(name.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_input_valid() {
+        let input = "5 + 5";
+        let result = parse_input(input);
+        assert!(result.is_ok());
+        let (num1, num2, operator) = result.unwrap();
+        assert_eq!(num1, 5.0);
+        assert_eq!(num2, 5.0);
+        assert_eq!(operator, "+");
+    }
+
+    #[test]
+    fn test_parse_input_invalid_input_variant() {
+        assert_eq!(parse_input("5 +"), Err(CalcError::InvalidInput));
+    }
+
+    #[test]
+    fn test_parse_input_parse_number_variant() {
+        assert_eq!(
+            parse_input("abc + 5"),
+            Err(CalcError::ParseNumber {
+                token: "abc".to_string(),
+                position: Some(0)
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_input_invalid_operator_variant() {
+        assert_eq!(
+            parse_input("5 $ 5"),
+            Err(CalcError::InvalidOperator {
+                operator: "$".to_string(),
+                position: Some(2)
+            })
+        );
+    }
+
+    #[test]
+    fn test_calculate_division_by_zero_variant() {
+        assert_eq!(calculate(5.0, 0.0, "/"), Err(CalcError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_calculate_domain_variant() {
+        assert!(matches!(
+            calculate(-8.0, 0.5, "^"),
+            Err(CalcError::Domain(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_input_rejects_nan() {
+        assert_eq!(parse_input("nan + 1"), Err(CalcError::NotANumber));
+        assert_eq!(parse_input("1 * NaN"), Err(CalcError::NotANumber));
+        assert_eq!(
+            CalcError::NotANumber.to_string(),
+            "NaN is not a valid number"
+        );
+    }
+
+    #[test]
+    fn test_calculate_rejects_nan() {
+        assert_eq!(calculate(f64::NAN, 1.0, "+"), Err(CalcError::NotANumber));
+        assert_eq!(calculate(0.0, 0.0, "/"), Err(CalcError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_precision_warning_for_large_integer() {
+        assert_eq!(
+            precision_warning("9007199254740993"),
+            Some(
+                "9007199254740993 can't be represented exactly and is rounded to 9007199254740992"
+                    .to_string()
+            )
+        );
+        assert!(precision_warning("-9007199254740993").is_some());
+        assert!(precision_warning("0xFFFFFFFFFFFFFFFF").is_some());
+        assert!(precision_warning("123456789012345678901234567890123456789012").is_some());
+        assert!(parse_input("9007199254740993 + 0").is_ok());
+    }
+
+    #[test]
+    fn test_precision_warning_for_safe_numbers() {
+        assert_eq!(precision_warning("1000000"), None);
+        assert_eq!(precision_warning("9007199254740992"), None);
+        assert_eq!(precision_warning("1e20"), None);
+        assert_eq!(precision_warning("9007199254740993.5"), None);
+        assert_eq!(precision_warning("+"), None);
+    }
+
+    #[test]
+    fn test_precision_warnings_scans_expression() {
+        assert_eq!(precision_warnings("sqrt(99999999999999999) * 2").len(), 1);
+        assert!(precision_warnings("1000000 * 1000000").is_empty());
+    }
+
+    #[test]
+    fn test_parse_input_spacing_variants() {
+        for input in ["5 + 5", "5+5", "5 +5", "5+ 5", "  5   +   5  "] {
+            assert_eq!(parse_input(input), Ok((5.0, 5.0, "+")), "{:?}", input);
+        }
+        assert_eq!(parse_input("7//2"), Ok((7.0, 2.0, "//")));
+        assert_eq!(parse_input("1<<4"), Ok((1.0, 4.0, "<<")));
+        assert_eq!(parse_input("2.5*4"), Ok((2.5, 4.0, "*")));
+        assert_eq!(parse_input("0xFF&0b1010"), Ok((255.0, 10.0, "&")));
+    }
+
+    #[test]
+    fn test_parse_input_signs() {
+        assert_eq!(parse_input("-5-3"), Ok((-5.0, 3.0, "-")));
+        assert_eq!(parse_input("5--3"), Ok((5.0, -3.0, "-")));
+        assert_eq!(parse_input("5 - -3"), Ok((5.0, -3.0, "-")));
+        assert_eq!(parse_input("-5 * -2"), Ok((-5.0, -2.0, "*")));
+        assert_eq!(
+            parse_input("5 - - 3"),
+            Err(CalcError::ParseNumber {
+                token: "-".to_string(),
+                position: Some(4)
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_input_exponent_sign() {
+        assert_eq!(parse_input("1e-3+1"), Ok((0.001, 1.0, "+")));
+        assert_eq!(parse_input("2E+2-1"), Ok((200.0, 1.0, "-")));
+        assert_eq!(parse_input("0x1e-5"), Ok((30.0, 5.0, "-")));
+    }
+
+    #[test]
+    fn test_parse_input_rejects_chains() {
+        assert_eq!(parse_input("5+3-2"), Err(CalcError::InvalidInput));
+        assert_eq!(parse_input("5 + 3 - 2"), Err(CalcError::InvalidInput));
+        assert_eq!(parse_input("5"), Err(CalcError::InvalidInput));
+        assert_eq!(parse_input(""), Err(CalcError::InvalidInput));
+    }
+
+    #[test]
+    fn test_parse_input_error_positions() {
+        let error = parse_input("  12 $ 3").unwrap_err();
+        assert_eq!(error.position(), Some(5));
+        assert!(error
+            .to_string()
+            .starts_with("Invalid operator '$' at position 5. Use +"));
+
+        let error = parse_input("12 + 3x").unwrap_err();
+        assert_eq!(error.to_string(), "Invalid number '3x' at position 5");
+    }
+
+    #[test]
+    fn test_execute_assignment_error_position() {
+        let mut context = Context::default();
+        let error = execute("x = 1 + $", &mut context).unwrap_err();
+        assert_eq!(error.position(), Some(8));
+    }
+
+    #[test]
+    fn test_parse_input_hex_and_binary() {
+        assert_eq!(parse_input("0xFF + 0b1010"), Ok((255.0, 10.0, "+")));
+        assert_eq!(parse_input("0XfF - 0B1"), Ok((255.0, 1.0, "-")));
+    }
+
+    #[test]
+    fn test_parse_number_invalid_digits() {
+        assert_eq!(
+            parse_number("0x1G"),
+            Err(CalcError::ParseNumber {
+                token: "0x1G".to_string(),
+                position: None
+            })
+        );
+        assert!(parse_number("0b102").is_err());
+        assert!(parse_number("0x").is_err());
+        assert!(parse_number("0x+5").is_err());
+    }
+
+    #[test]
+    fn test_infinite_literal_overflows() {
+        let context = Context::default();
+        assert_eq!(evaluate_with("1e400", &context), Err(CalcError::Overflow));
+        assert_eq!(
+            evaluate_with("1e400 - 1e400", &context),
+            Err(CalcError::Overflow)
+        );
+        assert_eq!(
+            evaluate_value("1e400 == 5", &context),
+            Err(CalcError::Overflow)
+        );
+        assert_eq!(parse_input("1e400 * 2"), Err(CalcError::Overflow));
+
+        let context = Context {
+            allow_infinity: true,
+            ..Context::default()
+        };
+        assert_eq!(evaluate_with("1e400 * 2", &context), Ok(f64::INFINITY));
+    }
+
+    #[test]
+    fn test_parse_number_digit_separators() {
+        assert_eq!(parse_number("1_000_000"), Ok(1_000_000.0));
+        assert_eq!(parse_number("1_000.000_1"), Ok(1000.0001));
+        assert_eq!(parse_number("0xff_ff"), Ok(65535.0));
+        assert_eq!(parse_number("0b1010_1010"), Ok(170.0));
+        for token in ["1__0", "5_", "_5", "1_.5", "1e_3", "0x_ff"] {
+            assert_eq!(
+                parse_number(token),
+                Err(CalcError::ParseNumber {
+                    token: token.to_string(),
+                    position: None
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_evaluate_digit_separators() {
+        assert_eq!(evaluate("1_000_000 + 1"), Ok(1_000_001.0));
+        assert_eq!(
+            evaluate("2 * 1__0"),
+            Err(CalcError::ParseNumber {
+                token: "1__0".to_string(),
+                position: Some(4)
+            })
+        );
+        assert_eq!(
+            evaluate("5_ + 1"),
+            Err(CalcError::ParseNumber {
+                token: "5_".to_string(),
+                position: Some(0)
+            })
+        );
+        assert_eq!(
+            evaluate("_5 + 1"),
+            Err(CalcError::UnknownIdentifier("_5".to_string()))
+        );
+        assert!(precision_warning("9_007_199_254_740_993").is_some());
+    }
+
+    #[test]
+    fn test_parse_input_invalid_format() {
+        let input = "5 + ";
+        assert!(parse_input(input).is_err());
+    }
+
+    #[test]
+    fn test_parse_input_invalid_number() {
+        let input = "abc + 5";
+        assert!(parse_input(input).is_err());
+    }
+
+    #[test]
+    fn test_parse_input_invalid_operator() {
+        let input = "5 $ 5";
+        assert!(parse_input(input).is_err());
+    }
+
+    #[test]
+    fn test_calculate_with_decimals() {
+        let result = calculate(5.5, 2.2, "+");
+        assert!(result.is_ok());
+        assert!(approx_eq(result.unwrap(), 7.7, 1e-10));
+    }
+
+    #[test]
+    fn test_calculate_negative_numbers() {
+        let result = calculate(-5.0, 3.0, "+");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), -2.0);
+    }
+
+    #[test]
+    fn test_calculate_addition() {
+        let result = calculate(5.0, 5.0, "+");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 10.0);
+    }
+
+    #[test]
+    fn test_calculate_subtraction() {
+        let result = calculate(5.0, 5.0, "-");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_calculate_multiplication() {
+        let result = calculate(5.0, 5.0, "*");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 25.0);
+    }
+
+    #[test]
+    fn test_calculate_division() {
+        let result = calculate(5.0, 5.0, "/");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_calculate_division_by_zero() {
+        let result = calculate(5.0, 0.0, "/");
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().to_string(), "Cannot divide by zero");
+    }
+
+    #[test]
+    fn test_calculate_floor_division() {
+        let result = calculate(7.0, 2.0, "//");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 3.0);
+    }
+
+    #[test]
+    fn test_calculate_floor_division_negative() {
+        let result = calculate(-7.0, 2.0, "//");
+        assert!(result.is_ok());
+        let result = result.unwrap();
+        assert_eq!(result, -4.0);
+        assert_ne!(result, (-7.0_f64 / 2.0).trunc());
+    }
+
+    #[test]
+    fn test_calculate_floor_division_by_zero() {
+        let result = calculate(7.0, 0.0, "//");
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().to_string(), "Cannot divide by zero");
+    }
+
+    #[test]
+    fn test_calculate_modulo() {
+        let result = calculate(17.0, 5.0, "%");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 2.0);
+    }
+
+    #[test]
+    fn test_calculate_modulo_negative_dividend() {
+        let result = calculate(-7.0, 3.0, "%");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), -1.0);
+    }
+
+    #[test]
+    fn test_calculate_modulo_by_zero() {
+        let result = calculate(5.0, 0.0, "%");
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().to_string(), "Cannot divide by zero");
+    }
+
+    #[test]
+    fn test_parse_input_bitwise() {
+        assert_eq!(parse_input("12 & 10"), Ok((12.0, 10.0, "&")));
+        assert_eq!(parse_input("12 | 3"), Ok((12.0, 3.0, "|")));
+        assert_eq!(parse_input("6 xor 3"), Ok((6.0, 3.0, "xor")));
+    }
+
+    #[test]
+    fn test_calculate_bitwise_and() {
+        assert_eq!(calculate(12.0, 10.0, "&"), Ok(8.0));
+    }
+
+    #[test]
+    fn test_calculate_bitwise_or() {
+        assert_eq!(calculate(12.0, 3.0, "|"), Ok(15.0));
+    }
+
+    #[test]
+    fn test_calculate_bitwise_xor() {
+        assert_eq!(calculate(6.0, 3.0, "xor"), Ok(5.0));
+        assert_eq!(calculate(-1.0, 1.0, "xor"), Ok(-2.0));
+    }
+
+    #[test]
+    fn test_calculate_bitwise_rejects_fractions() {
+        assert!(matches!(
+            calculate(2.5, 1.0, "&"),
+            Err(CalcError::Domain(_))
+        ));
+        assert!(matches!(
+            calculate(1.0, 0.1, "|"),
+            Err(CalcError::Domain(_))
+        ));
+        assert!(matches!(
+            calculate(1e300, 1.0, "xor"),
+            Err(CalcError::Domain(_))
+        ));
+    }
+
+    #[test]
+    fn test_calculate_shifts() {
+        assert_eq!(calculate(1.0, 4.0, "<<"), Ok(16.0));
+        assert_eq!(calculate(256.0, 2.0, ">>"), Ok(64.0));
+        assert_eq!(calculate(-16.0, 2.0, ">>"), Ok(-4.0));
+        assert_eq!(calculate(5.0, 0.0, "<<"), Ok(5.0));
+    }
+
+    #[test]
+    fn test_calculate_shift_rejects_invalid_amounts() {
+        assert!(matches!(
+            calculate(1.0, -1.0, "<<"),
+            Err(CalcError::Domain(_))
+        ));
+        assert!(matches!(
+            calculate(1.0, 64.0, "<<"),
+            Err(CalcError::Domain(_))
+        ));
+        assert!(matches!(
+            calculate(1.0, 100.0, ">>"),
+            Err(CalcError::Domain(_))
+        ));
+        assert!(matches!(
+            calculate(1.5, 1.0, "<<"),
+            Err(CalcError::Domain(_))
+        ));
+        assert!(matches!(
+            calculate(1.0, 1.5, ">>"),
+            Err(CalcError::Domain(_))
+        ));
+    }
+
+    #[test]
+    fn test_calculate_overflow() {
+        assert_eq!(calculate(1e308, 1e308, "*"), Err(CalcError::Overflow));
+        assert_eq!(calculate(-1e308, 1e308, "*"), Err(CalcError::Overflow));
+        assert_eq!(calculate(f64::MAX, f64::MAX, "+"), Err(CalcError::Overflow));
+        assert_eq!(calculate(2.0, 1024.0, "^"), Err(CalcError::Overflow));
+    }
+
+    #[test]
+    fn test_calculate_infinite_operand_is_not_overflow() {
+        assert_eq!(calculate(f64::INFINITY, 1.0, "+"), Ok(f64::INFINITY));
+        assert_eq!(
+            calculate(f64::NEG_INFINITY, 2.0, "*"),
+            Ok(f64::NEG_INFINITY)
+        );
+    }
+
+    #[test]
+    fn test_calculate_undefined_infinity() {
+        assert_eq!(
+            calculate(f64::INFINITY, f64::INFINITY, "-"),
+            Err(CalcError::Domain("inf - inf is undefined".to_string()))
+        );
+        assert_eq!(
+            calculate(f64::INFINITY, 0.0, "*"),
+            Err(CalcError::Domain("inf * 0 is undefined".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_input_rejects_inf() {
+        assert!(matches!(
+            parse_input("inf + 1"),
+            Err(CalcError::ParseNumber { token, .. }) if token == "inf"
+        ));
+        assert!(parse_input("1 - -Infinity").is_err());
+    }
+
+    #[test]
+    fn test_parse_input_exponentiation() {
+        let result = parse_input("2 ^ 8");
+        assert!(result.is_ok());
+        let (_, _, operator) = result.unwrap();
+        assert_eq!(operator, "^");
+    }
+
+    #[test]
+    fn test_calculate_exponentiation() {
+        let result = calculate(2.0, 10.0, "^");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 1024.0);
+    }
+
+    #[test]
+    fn test_calculate_negative_exponent() {
+        let result = calculate(2.0, -1.0, "^");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 0.5);
+    }
+
+    #[test]
+    fn test_calculate_zero_to_the_zero() {
+        let result = calculate(0.0, 0.0, "^");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_calculate_negative_base_fractional_exponent() {
+        let result = calculate(-8.0, 0.5, "^");
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Cannot raise a negative number to a fractional power"
+        );
+    }
+
+    #[test]
+    fn test_evaluate_single_operation() {
+        let result = evaluate("5 + 5");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 10.0);
+    }
+
+    #[test]
+    fn test_evaluate_precedence() {
+        let result = evaluate("2 + 3 * 4");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 14.0);
+    }
+
+    #[test]
+    fn test_evaluate_left_associative() {
+        let result = evaluate("10 - 4 - 3");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 3.0);
+    }
+
+    #[test]
+    fn test_evaluate_three_operator_chain() {
+        let result = evaluate("5 + 3 - 2 + 4");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 10.0);
+    }
+
+    #[test]
+    fn test_evaluate_four_operator_chain() {
+        let result = evaluate("10 - 2 + 3 - 1 + 6");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 16.0);
+        assert_eq!(evaluate("5 + 3 - 2"), Ok(6.0));
+    }
+
+    #[test]
+    fn test_evaluate_mixed_precedence() {
+        let result = evaluate("2 * 3 + 8 / 4 - 1");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 7.0);
+    }
+
+    #[test]
+    fn test_evaluate_rejects_double_operator() {
+        assert!(evaluate("2 + + 3").is_err());
+    }
+
+    #[test]
+    fn test_evaluate_rejects_trailing_operator() {
+        assert!(evaluate("2 +").is_err());
+    }
+
+    #[test]
+    fn test_evaluate_rejects_empty_input() {
+        assert!(evaluate("").is_err());
+    }
+
+    #[test]
+    fn test_evaluate_division_by_zero() {
+        let result = evaluate("1 + 5 / 0");
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().to_string(), "Cannot divide by zero");
+    }
+
+    #[test]
+    fn test_evaluate_with_degrees() {
+        let context = Context {
+            angle_mode: AngleMode::Degrees,
+            ..Context::default()
+        };
+        let result = evaluate_with("sin(90)", &context);
+        assert!(result.is_ok());
+        assert!(approx_eq(result.unwrap(), 1.0, 1e-10));
+    }
+
+    #[test]
+    fn test_evaluate_with_radians() {
+        let context = Context {
+            angle_mode: AngleMode::Radians,
+            ..Context::default()
+        };
+        let result = evaluate_with("sin(90)", &context);
+        assert!(result.is_ok());
+        assert!(approx_eq(result.unwrap(), 90.0_f64.sin(), 1e-10));
+    }
+
+    #[test]
+    fn test_evaluate_with_previous_answer() {
+        let mut context = Context::default();
+        context.ans = evaluate_with("5 + 5", &context).unwrap();
+        context.ans = evaluate_with("ans * 2", &context).unwrap();
+        assert_eq!(context.ans, 20.0);
+    }
+
+    #[test]
+    fn test_evaluate_and_format() {
+        let calculation = evaluate_and_format(" 6 * 7 ").unwrap();
+        assert_eq!(
+            calculation,
+            Calculation {
+                input: "6 * 7".to_string(),
+                result: 42.0
+            }
+        );
+        assert_eq!(evaluate_and_format("1 / 0"), Err(CalcError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_execute_integer_mode() {
+        let mut context = Context {
+            number_mode: NumberMode::Integer,
+            ..Context::default()
+        };
+        assert_eq!(
+            execute("99999999999 * 99999999999", &mut context),
+            Ok(Outcome::Exact(Exact::Integer(9999999999800000000001)))
+        );
+        assert_eq!(context.ans, 9999999999800000000001.0);
+        assert_eq!(
+            execute("ans + 1", &mut context),
+            Ok(Outcome::Exact(Exact::Integer(9999999999800000000002)))
+        );
+        assert_eq!(execute("sqrt(16)", &mut context), Ok(Outcome::Value(4.0)));
+        assert_eq!(context.exact_ans, None);
+        assert!(matches!(
+            execute("7 / 2", &mut context),
+            Err(CalcError::Domain(_))
+        ));
+    }
+
+    #[test]
+    fn test_approx_eq() {
+        assert!(approx_eq(0.1 + 0.2, 0.3, 1e-10));
+        assert!(approx_eq(1.0, 1.0, 0.0));
+        assert!(approx_eq(1.0, 1.05, 0.1));
+        assert!(!approx_eq(1.0, 1.2, 0.1));
+        assert!(!approx_eq(0.1 + 0.2, 0.3, 0.0));
+    }
+
+    #[test]
+    fn test_approx_eq_nan() {
+        assert!(!approx_eq(f64::NAN, f64::NAN, 1.0));
+        assert!(!approx_eq(f64::NAN, 1.0, f64::INFINITY));
+        assert!(!approx_eq(1.0, f64::NAN, 1.0));
+    }
+
+    #[test]
+    fn test_approx_eq_infinity() {
+        assert!(approx_eq(f64::INFINITY, f64::INFINITY, 0.0));
+        assert!(approx_eq(f64::NEG_INFINITY, f64::NEG_INFINITY, 0.0));
+        assert!(!approx_eq(f64::INFINITY, f64::NEG_INFINITY, 1e10));
+        assert!(!approx_eq(f64::INFINITY, f64::MAX, 1e300));
+    }
+
+    #[test]
+    fn test_compare_each_operator() {
+        assert_eq!(compare(2.0, 3.0, "<"), Ok(true));
+        assert_eq!(compare(3.0, 3.0, "<"), Ok(false));
+        assert_eq!(compare(5.0, 3.0, ">"), Ok(true));
+        assert_eq!(compare(3.0, 5.0, ">"), Ok(false));
+        assert_eq!(compare(3.0, 3.0, "<="), Ok(true));
+        assert_eq!(compare(4.0, 3.0, "<="), Ok(false));
+        assert_eq!(compare(3.0, 3.0, ">="), Ok(true));
+        assert_eq!(compare(2.0, 3.0, ">="), Ok(false));
+        assert_eq!(compare(3.0, 3.0, "=="), Ok(true));
+        assert_eq!(compare(3.0, 4.0, "=="), Ok(false));
+        assert_eq!(compare(3.0, 4.0, "!="), Ok(true));
+        assert_eq!(compare(3.0, 3.0, "!="), Ok(false));
+    }
+
+    #[test]
+    fn test_compare_uses_tolerance() {
+        assert_eq!(compare(0.1 + 0.2, 0.3, "=="), Ok(true));
+        assert_eq!(compare(0.1 + 0.2, 0.3, ">"), Ok(false));
+        assert_eq!(compare(0.1 + 0.2, 0.3, "<="), Ok(true));
+        assert_eq!(compare(1e12, 1e12 + 1e-3, "=="), Ok(true));
+        assert_eq!(compare(1.0, 1.0 + 1e-6, "=="), Ok(false));
+        assert_eq!(compare(f64::INFINITY, f64::INFINITY, "=="), Ok(true));
+    }
+
+    #[test]
+    fn test_compare_infinity_exactly() {
+        assert_eq!(compare(1.0, f64::INFINITY, "<"), Ok(true));
+        assert_eq!(compare(1.0, f64::INFINITY, "=="), Ok(false));
+        assert_eq!(compare(f64::INFINITY, f64::INFINITY, "=="), Ok(true));
+        assert_eq!(compare(-f64::INFINITY, f64::INFINITY, "!="), Ok(true));
+        assert_eq!(compare(-f64::INFINITY, f64::INFINITY, "<"), Ok(true));
+        assert_eq!(compare(f64::INFINITY, 1e308, ">="), Ok(true));
+    }
+
+    #[test]
+    fn test_compare_errors() {
+        assert_eq!(compare(f64::NAN, 1.0, "=="), Err(CalcError::NotANumber));
+        assert!(matches!(
+            compare(1.0, 2.0, "+"),
+            Err(CalcError::InvalidOperator { .. })
+        ));
+    }
+
+    #[test]
+    fn test_execute_leading_operator_uses_ans() {
+        let mut context = Context::default();
+        execute("10", &mut context).unwrap();
+        assert_eq!(execute("+ 5", &mut context), Ok(Outcome::Value(15.0)));
+        assert_eq!(execute("* 2", &mut context), Ok(Outcome::Value(30.0)));
+        assert_eq!(execute("/ 3 + 1", &mut context), Ok(Outcome::Value(11.0)));
+        assert_eq!(execute("+5", &mut context), Ok(Outcome::Value(16.0)));
+        assert_eq!(execute("> 15", &mut context), Ok(Outcome::Bool(true)));
+    }
+
+    #[test]
+    fn test_execute_leading_minus() {
+        let mut context = Context::default();
+        execute("10", &mut context).unwrap();
+        assert_eq!(execute("- 5", &mut context), Ok(Outcome::Value(5.0)));
+        assert_eq!(execute("-5", &mut context), Ok(Outcome::Value(-5.0)));
+        assert_eq!(execute("-(2)", &mut context), Ok(Outcome::Value(-2.0)));
+        assert_eq!(execute("- 1", &mut context), Ok(Outcome::Value(-3.0)));
+    }
+
+    #[test]
+    fn test_execute_leading_operator_error_position() {
+        let mut context = Context::default();
+        assert_eq!(
+            execute("+ * 2", &mut context).unwrap_err().position(),
+            Some(2)
+        );
+        assert!(execute("xor5", &mut context).is_err());
+    }
+
+    #[test]
+    fn test_input_length_limit() {
+        let context = Context::default();
+        let at_limit = format!("{}1", " ".repeat(DEFAULT_MAX_INPUT_LEN - 1));
+        assert_eq!(evaluate_with(&at_limit, &context), Ok(1.0));
+        let over_limit = format!(" {}", at_limit);
+        assert_eq!(
+            evaluate_with(&over_limit, &context),
+            Err(CalcError::InputTooLong(DEFAULT_MAX_INPUT_LEN))
+        );
+    }
+
+    #[test]
+    fn test_input_length_limit_counts_characters() {
+        let mut context = Context {
+            max_input_len: 5,
+            ..Context::default()
+        };
+        assert_eq!(evaluate_with("2 × 3", &context), Ok(6.0));
+        assert_eq!(
+            evaluate_with("2 × 30", &context),
+            Err(CalcError::InputTooLong(5))
+        );
+        assert_eq!(
+            execute("x = 12", &mut context),
+            Err(CalcError::InputTooLong(5))
+        );
+        assert_eq!(
+            evaluate_rpn("1 2 + 3 +", &context),
+            Err(CalcError::InputTooLong(5))
+        );
+        assert_eq!(
+            CalcError::InputTooLong(5).to_string(),
+            "Input too long: the limit is 5 characters"
+        );
+    }
+
+    #[test]
+    fn test_define_function() {
+        let mut context = Context::default();
+        assert_eq!(
+            define_function("double(x) = x * 2", &mut context),
+            Ok("double".to_string())
+        );
+        assert_eq!(evaluate_with("double(21)", &context), Ok(42.0));
+        assert_eq!(evaluate_with("double(double(1)) + 1", &context), Ok(5.0));
+
+        define_function("quad(y) = double(double(y))", &mut context).unwrap();
+        assert_eq!(evaluate_with("quad(3)", &context), Ok(12.0));
+    }
+
+    #[test]
+    fn test_define_function_parameter_shadows_variable() {
+        let mut context = Context::default();
+        context.variables.insert("x".to_string(), 100.0);
+        define_function("inc(x) = x + 1", &mut context).unwrap();
+        assert_eq!(evaluate_with("inc(1)", &context), Ok(2.0));
+        assert_eq!(evaluate_with("x", &context), Ok(100.0));
+    }
+
+    #[test]
+    fn test_define_function_uses_context_settings() {
+        let mut context = Context {
+            operator_aliases: false,
+            ..Context::default()
+        };
+        context.variables.insert("x".to_string(), 3.0);
+        // `x` is a variable, so `y` can't follow it.
+        assert!(matches!(
+            define_function("f(y) = 2 x y", &mut context),
+            Err(CalcError::UnexpectedToken { .. })
+        ));
+        define_function("f(y) = 2x * y", &mut context).unwrap();
+        assert_eq!(evaluate_with("f(2)", &context), Ok(12.0));
+
+        context.max_depth = 2;
+        assert_eq!(
+            define_function("g(y) = ((((y))))", &mut context),
+            Err(CalcError::TooDeeplyNested(2))
+        );
+    }
+
+    #[test]
+    fn test_define_function_wrong_arity() {
+        let mut context = Context::default();
+        define_function("double(x) = x * 2", &mut context).unwrap();
+        assert_eq!(
+            evaluate_with("double(1, 2)", &context),
+            Err(CalcError::ArgumentCount {
+                function: "double".to_string(),
+                found: 2,
+            })
+        );
+        assert!(evaluate_with("double()", &context).is_err());
+    }
+
+    #[test]
+    fn test_define_function_errors() {
+        let mut context = Context::default();
+        define_function("double(x) = x * 2", &mut context).unwrap();
+        assert_eq!(
+            define_function("double(y) = y + y", &mut context),
+            Err(CalcError::InvalidCommand(
+                "Function 'double' is already defined".to_string()
+            ))
+        );
+        assert!(matches!(
+            define_function("sqrt(x) = x", &mut context),
+            Err(CalcError::InvalidVariableName(_))
+        ));
+        assert_eq!(
+            define_function("f(x) = f(x)", &mut context),
+            Err(CalcError::UnknownIdentifier("f".to_string()))
+        );
+        assert!(matches!(
+            define_function("g = 2", &mut context),
+            Err(CalcError::InvalidCommand(_))
+        ));
+    }
+
+    #[test]
+    fn test_execute_comparison() {
+        let mut context = Context {
+            ans: 7.0,
+            ..Context::default()
+        };
+        assert_eq!(execute("5 > 3", &mut context), Ok(Outcome::Bool(true)));
+        assert_eq!(
+            execute("2 + 2 == 5", &mut context),
+            Ok(Outcome::Bool(false))
+        );
+        assert_eq!(context.ans, 7.0);
+        context.variables.insert("x".to_string(), 1.0);
+        assert_eq!(execute("x <= 1", &mut context), Ok(Outcome::Bool(true)));
+        assert_eq!(execute("x != 1", &mut context), Ok(Outcome::Bool(false)));
+        assert_eq!(execute("x >= 2", &mut context), Ok(Outcome::Bool(false)));
+        assert!(execute("y = 1 < 2", &mut context).is_err());
+    }
+
+    #[test]
+    fn test_execute_assignment() {
+        let mut context = Context::default();
+        let result = execute("x = 5", &mut context);
+        assert_eq!(result, Ok(Outcome::Assignment("x".to_string(), 5.0)));
+        assert_eq!(context.variables.get("x"), Some(&5.0));
+    }
+
+    #[test]
+    fn test_execute_recall() {
+        let mut context = Context::default();
+        execute("x = 2 + 3", &mut context).unwrap();
+        assert_eq!(execute("x * 3", &mut context), Ok(Outcome::Value(15.0)));
+    }
+
+    #[test]
+    fn test_execute_reassignment() {
+        let mut context = Context::default();
+        execute("total = 1", &mut context).unwrap();
+        execute("total = total + 10", &mut context).unwrap();
+        assert_eq!(context.variables.get("total"), Some(&11.0));
+    }
+
+    #[test]
+    fn test_execute_undefined_variable() {
+        let mut context = Context::default();
+        assert_eq!(
+            execute("y * 2", &mut context),
+            Err(CalcError::UnknownIdentifier("y".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_execute_invalid_variable_names() {
+        let mut context = Context::default();
+        for name in ["1x", "x-y", "", "pi", "sqrt", "ans", "xor"] {
+            let input = format!("{} = 1", name);
+            assert_eq!(
+                execute(&input, &mut context),
+                Err(CalcError::InvalidVariableName(name.to_string()))
+            );
+        }
+        assert!(context.variables.is_empty());
+    }
+
+    #[test]
+    fn test_execute_underscore_variable_name() {
+        let mut context = Context::default();
+        execute("_rate_2 = 0.5", &mut context).unwrap();
+        assert_eq!(
+            execute("_rate_2 * 4", &mut context),
+            Ok(Outcome::Value(2.0))
+        );
+    }
+}
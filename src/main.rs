@@ -1,218 +1,847 @@
-/// Parses user input into two numbers and an operator.
-///
-/// # Arguments
-/// * `input` - A string slice containing the input to be parsed
-///
-/// # Returns
-/// * `Result<(f64, f64, &str), Box<dyn std::error::Error>>` - A tuple containing:
-///   - first number (f64)
-///   - second number (f64)
-///   - operator (&str)
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use rust_calculator_cli::parser::{check_expression, evaluation_steps};
+use rust_calculator_cli::{
+    color_enabled, colorize, csv_row, evaluate_value, execute, install_interrupt_handler,
+    is_quit_command, json_result, json_value, load_config, load_history, precision_warnings,
+    save_history, take_interrupt, Calculation, Color, Context, InterruptAction, Interrupts,
+    Outcome, Response, Rng, Session, Settings, Value,
+};
+
+/// Name of the file in the home directory that keeps the history between sessions.
+const HISTORY_FILE: &str = ".rust_calculator_history";
+
+/// Name of the configuration file in the home directory, see [`load_config`].
+const CONFIG_FILE: &str = ".calculatorrc";
+
+/// Largest number of decimal places accepted by `--precision`; an `f64` has
+/// no more significant digits than that.
+const MAX_PRECISION: usize = 17;
+
+/// Environment variable holding the expression evaluated with `--from-env`.
+const EXPRESSION_VARIABLE: &str = "CALC_EXPR";
+
+/// ANSI escape sequence that clears the terminal and moves the cursor home.
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+/// How often the interactive calculator checks for Ctrl-C while it waits for
+/// input.
+const INTERRUPT_POLL: Duration = Duration::from_millis(50);
+
+/// Returns the path of the history file, if the home directory is known.
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
+
+/// Returns the path of the configuration file, if the home directory is known.
+fn config_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(CONFIG_FILE))
+}
+
+/// How results of a single expression and of `--file` are printed, as chosen
+/// by `--format`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum OutputFormat {
+    /// Human-readable output (the default)
+    #[default]
+    Plain,
+    /// A JSON object per result, as also selected by `--json`
+    Json,
+    /// An `expression,result` line per result
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    /// Parses the argument of `--format`: `plain`, `json` or `csv`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(OutputFormat::Plain),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(format!("Unknown format '{}'. Use plain, json or csv", s)),
+        }
+    }
+}
+
+/// Command-line options, parsed from the arguments by [`Options::parse`].
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    /// How results are printed; `--json` is short for `--format json`
+    format: OutputFormat,
+    /// Decimal places for results, instead of the configured precision
+    precision: Option<usize>,
+    /// Never color the output, even on a terminal
+    no_color: bool,
+    /// Only check the syntax of the expression instead of evaluating it
+    check: bool,
+    /// Accept `inf` as a value
+    allow_inf: bool,
+    /// Print each calculation step before the result of a single expression
+    verbose: bool,
+    /// Print only results, without the expression or the `--file` summary
+    quiet: bool,
+    /// Seed for `rand`, so that its numbers are the same on every run
+    seed: Option<u64>,
+    /// Most characters accepted in an input, instead of the configured limit
+    max_input_len: Option<usize>,
+    /// How deeply expressions may be nested, instead of the configured limit
+    max_depth: Option<usize>,
+    /// File of expressions to evaluate in batch mode
+    file: Option<PathBuf>,
+    /// Answer expressions from stdin line by line, for other programs
+    serve: bool,
+    /// Evaluate the expression, if one was given, and then keep reading
+    /// input like the interactive calculator instead of exiting
+    interactive: bool,
+    /// Statements separated by `;` to evaluate in order
+    eval: Option<String>,
+    /// Take the expression from the `CALC_EXPR` environment variable
+    from_env: bool,
+    /// The expression to evaluate once, if one was given
+    expression: Option<String>,
+}
+
+impl Options {
+    /// Parses the command-line arguments, without the program name.
+    ///
+    /// Options start with `--`; all other arguments are joined into a single
+    /// expression.
+    fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut words = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--json" => options.format = OutputFormat::Json,
+                "--no-color" => options.no_color = true,
+                "--check" => options.check = true,
+                "--allow-inf" => options.allow_inf = true,
+                "--verbose" => options.verbose = true,
+                "--quiet" => options.quiet = true,
+                "--serve" => options.serve = true,
+                "--interactive" | "-i" => options.interactive = true,
+                "--from-env" => options.from_env = true,
+                "--precision" => {
+                    let precision = args.next().ok_or("Option '--precision' needs a number")?;
+                    options.precision = Some(
+                        precision
+                            .parse()
+                            .ok()
+                            .filter(|precision| *precision <= MAX_PRECISION)
+                            .ok_or_else(|| {
+                                format!(
+                                    "Invalid precision '{}'. Use a number from 0 to {}",
+                                    precision, MAX_PRECISION
+                                )
+                            })?,
+                    );
+                }
+                "--file" => {
+                    let path = args.next().ok_or("Option '--file' needs a file name")?;
+                    options.file = Some(PathBuf::from(path));
+                }
+                "--format" => {
+                    let format = args.next().ok_or("Option '--format' needs a format")?;
+                    options.format = format.parse()?;
+                }
+                "--seed" => {
+                    let seed = args.next().ok_or("Option '--seed' needs a number")?;
+                    options.seed = Some(seed.parse().map_err(|_| {
+                        format!("Invalid seed '{}'. Use a non-negative integer", seed)
+                    })?);
+                }
+                "--max-input-len" => {
+                    let length = args
+                        .next()
+                        .ok_or("Option '--max-input-len' needs a number")?;
+                    options.max_input_len = Some(
+                        length
+                            .parse()
+                            .ok()
+                            .filter(|length| *length > 0)
+                            .ok_or_else(|| {
+                                format!("Invalid length '{}'. Use a positive integer", length)
+                            })?,
+                    );
+                }
+                "--max-depth" => {
+                    let depth = args.next().ok_or("Option '--max-depth' needs a number")?;
+                    options.max_depth = Some(
+                        depth
+                            .parse()
+                            .ok()
+                            .filter(|depth| *depth > 0)
+                            .ok_or_else(|| {
+                                format!("Invalid depth '{}'. Use a positive integer", depth)
+                            })?,
+                    );
+                }
+                "--eval" => {
+                    let script = args.next().ok_or("Option '--eval' needs statements")?;
+                    options.eval = Some(script.clone());
+                }
+                option if option.starts_with("--") => {
+                    return Err(format!("Unknown option '{}'", option))
+                }
+                word => words.push(word),
+            }
+        }
+        if !words.is_empty() {
+            options.expression = Some(words.join(" "));
+        }
+        Ok(options)
+    }
+}
+
+/// Reads the expression for `--from-env` from [`EXPRESSION_VARIABLE`].
 ///
 /// # Errors
-/// Returns an error if:
-/// * The input doesn't contain exactly 3 parts (two numbers and an operator)
-/// * The numbers cannot be converted to f64
-/// * The operator is not one of the allowed operators (+, -, *, /)
+/// Returns a message if the variable is unset or not valid Unicode, or if an
+/// expression was also given on the command line.
+fn expression_from_env(options: &Options) -> Result<String, String> {
+    if options.expression.is_some() {
+        return Err("Option '--from-env' can't be combined with an expression".to_string());
+    }
+    std::env::var(EXPRESSION_VARIABLE).map_err(|e| match e {
+        std::env::VarError::NotPresent => format!(
+            "Option '--from-env' needs the {} environment variable to be set",
+            EXPRESSION_VARIABLE
+        ),
+        std::env::VarError::NotUnicode(_) => {
+            format!(
+                "The {} environment variable is not valid text",
+                EXPRESSION_VARIABLE
+            )
+        }
+    })
+}
+
+/// Colors `text` if `enabled`, and returns it unchanged otherwise.
+fn paint(text: String, color: Color, enabled: bool) -> String {
+    if enabled {
+        colorize(&text, color)
+    } else {
+        text
+    }
+}
+
+/// Evaluates a single expression given on the command line and exits.
 ///
-/// # Examples
-/// ```
-/// let input = "5.5 + 3.2";
-/// let result = parse_input(input);
-/// assert!(result.is_ok());
-/// let (num1, num2, op) = result.unwrap();
-/// assert_eq!(num1, 5.5);
-/// assert_eq!(num2, 3.2);
-/// assert_eq!(op, "+");
-/// ```
-fn parse_input(input: &str) -> Result<(f64, f64, &str), Box<dyn std::error::Error>> {
-    let values: Vec<&str> = input.split_whitespace().collect();
+/// Prints the result to stdout and exits with code 0, or prints the error
+/// to stderr and exits with code 1. In [`OutputFormat::Json`], both the
+/// result and the error are printed to stdout as a JSON object instead, and in
+/// [`OutputFormat::Csv`] the result is printed as `expression,result`. The
+/// result is shown with `settings`; in JSON only their precision applies,
+/// rounding the number to that many decimal places. With `verbose` set, each
+/// step of the calculation is printed before the result in plain output.
+fn run_once(
+    expression: &str,
+    format: OutputFormat,
+    verbose: bool,
+    settings: &Settings,
+    context: &Context,
+) -> ! {
+    let json = format == OutputFormat::Json;
+    let result = if verbose && format == OutputFormat::Plain {
+        evaluation_steps(expression, context).map(|(value, steps)| {
+            for step in steps {
+                println!("{}", step);
+            }
+            value
+        })
+    } else {
+        evaluate_value(expression, context)
+    };
+    match &result {
+        Ok(Value::Number(mut value)) if json => {
+            if let Some(precision) = settings.precision {
+                // Round through the decimal representation, so the JSON number
+                // has exactly the requested digits.
+                value = format!("{:.*}", precision, value).parse().unwrap_or(value);
+            }
+            println!("{}", json_value(expression, Value::Number(value)))
+        }
+        Ok(value) if json => println!("{}", json_value(expression, *value)),
+        Ok(Value::Number(value)) if format == OutputFormat::Csv => {
+            println!("{}", csv_row(&[expression, &settings.format(*value)]))
+        }
+        Ok(Value::Bool(value)) if format == OutputFormat::Csv => {
+            println!("{}", csv_row(&[expression, &value.to_string()]))
+        }
+        Ok(Value::Number(value)) => println!("{}", settings.format(*value)),
+        Ok(Value::Bool(value)) => println!("{}", value),
+        Err(e) if json => println!("{}", json_result(expression, &Err(e.clone()))),
+        Err(e) => eprintln!("Error: {}", e),
+    }
+    std::process::exit(if result.is_ok() { 0 } else { 1 });
+}
 
-    if values.len() != 3 {
-        return Err("Invalid input".into());
+/// Checks the syntax of a single expression and exits, as requested by
+/// `--check`.
+///
+/// The expression is checked against `context`, as it would be evaluated, so
+/// options such as `--max-depth` and `--allow-inf` apply. Nothing is printed
+/// for a valid expression, which exits with code 0. An invalid one is
+/// reported on stderr and exits with code 1.
+fn run_check(expression: &str, context: &Context) -> ! {
+    match check_expression(expression, context) {
+        Ok(()) => std::process::exit(0),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
     }
+}
 
-    let num1: f64 = values[0].parse()?;
-    let num2: f64 = values[2].parse()?;
-    let operator = values[1];
+/// Evaluates every line of a file and exits, as requested by `--file`.
+///
+/// Blank lines and lines starting with `#` are skipped. Each result is printed
+/// as `expression = result`; errors are reported on stderr and don't stop the
+/// remaining lines. A summary of how many expressions were evaluated and how
+/// many failed follows, and the exit code is 1 if any of them failed. The
+/// lines are evaluated in a session that starts with `settings` and
+/// `context`. With `quiet` set, plain output has only the result on each line
+/// and the summary goes to stderr, so the results can be piped elsewhere.
+///
+/// In [`OutputFormat::Json`] each result and each error is printed as a JSON
+/// object on its own line. [`OutputFormat::Csv`] starts with an
+/// `expression,result` header row and prints a row per result, with errors
+/// still on stderr. In both formats the summary goes to stderr, so stdout
+/// holds only the data.
+fn run_file(
+    path: &Path,
+    format: OutputFormat,
+    quiet: bool,
+    settings: Settings,
+    context: Context,
+) -> ! {
+    let contents = std::fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Error: Could not read {}: {}", path.display(), e);
+        std::process::exit(1);
+    });
 
-    if !["+", "-", "*", "/"].contains(&operator) {
-        return Err("Invalid operator. Use +, -, *, /".into());
+    let mut session = Session {
+        settings,
+        context,
+        ..Session::default()
+    };
+    if format == OutputFormat::Csv {
+        println!("{}", csv_row(&["expression", "result"]));
+    }
+    let mut evaluated = 0;
+    let mut errors = 0;
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        evaluated += 1;
+        let (expression, result, value) = match session.handle(line) {
+            Ok(Response::Value(calculation)) => (
+                calculation.input,
+                session.format(calculation.result),
+                Value::Number(calculation.result),
+            ),
+            Ok(Response::Exact { expression, value }) => {
+                let approximation = value.approximate().unwrap_or(f64::NAN);
+                let result = session.format_exact(&value);
+                (expression, result, Value::Number(approximation))
+            }
+            Ok(Response::Bool { expression, value }) => {
+                (expression, value.to_string(), Value::Bool(value))
+            }
+            Ok(Response::Assignment { name, value }) => {
+                (name, session.format(value), Value::Number(value))
+            }
+            Ok(Response::Output(output)) => {
+                println!("{}", output);
+                continue;
+            }
+            Ok(Response::Info(_) | Response::Empty) => continue,
+            Err(e) => {
+                errors += 1;
+                match format {
+                    OutputFormat::Json => println!("{}", json_result(line, &Err(e))),
+                    _ => eprintln!("Error: {}: {}", line, e),
+                }
+                continue;
+            }
+        };
+        match format {
+            OutputFormat::Plain if quiet => println!("{}", result),
+            OutputFormat::Plain => println!("{} = {}", expression, result),
+            OutputFormat::Json => println!("{}", json_value(&expression, value)),
+            OutputFormat::Csv => println!("{}", csv_row(&[&expression, &result])),
+        }
     }
 
-    Ok((num1, num2, operator))
+    let summary = format!(
+        "Evaluated {} expression{}, {} error{}",
+        evaluated,
+        if evaluated == 1 { "" } else { "s" },
+        errors,
+        if errors == 1 { "" } else { "s" }
+    );
+    match format {
+        OutputFormat::Plain if !quiet => println!("{}", summary),
+        _ => eprintln!("{}", summary),
+    }
+    std::process::exit(if errors == 0 { 0 } else { 1 });
 }
 
-/// Performs a mathematical calculation with two numbers and an operator.
+/// Evaluates statements separated by `;` and exits, as requested by
+/// `--eval`.
 ///
-/// # Arguments
-/// * `num1` - First number (f64)
-/// * `num2` - Second number (f64)
-/// * `operator` - Mathematical operator as string slice
+/// The statements share `ans` and variables, starting from `context`, so
+/// `x = 5; x * 2` prints `10`. The result of every statement other than an
+/// assignment is printed on its own line with `settings`; empty statements are
+/// skipped. The first error is reported on stderr and exits with code 1
+/// without evaluating the remaining statements.
+fn run_eval(script: &str, settings: &Settings, mut context: Context) -> ! {
+    for statement in script.split(';').map(str::trim) {
+        if statement.is_empty() {
+            continue;
+        }
+        match execute(statement, &mut context) {
+            Ok(Outcome::Value(value)) => println!("{}", settings.format(value)),
+            Ok(Outcome::Exact(value)) => println!("{}", settings.format_exact(&value)),
+            Ok(Outcome::Bool(value)) => println!("{}", value),
+            Ok(Outcome::Assignment(..)) => (),
+            Err(e) => {
+                eprintln!("Error: {}: {}", statement, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    std::process::exit(0);
+}
+
+/// Answers expressions read from stdin until it ends, as requested by
+/// `--serve`.
 ///
-/// # Returns
-/// * `Result<f64, Box<dyn std::error::Error>>` - The result of the calculation
+/// Every line of input gets exactly one line of output, flushed right away,
+/// so another program can write an expression and read its answer. A result
+/// is printed on its own and an error as `ERR: message`; there is no prompt.
+/// Variables and `ans` carry over between lines, starting from `context`.
+/// Results are shown with `settings`.
+fn run_serve(settings: Settings, mut context: Context) -> ! {
+    let mut stdout = std::io::stdout().lock();
+    for line in std::io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+        let answer = match execute(line.trim(), &mut context) {
+            Ok(Outcome::Value(value) | Outcome::Assignment(_, value)) => settings.format(value),
+            Ok(Outcome::Exact(value)) => settings.format_exact(&value),
+            Ok(Outcome::Bool(value)) => value.to_string(),
+            Err(e) => format!("ERR: {}", e),
+        };
+        if writeln!(stdout, "{}", answer)
+            .and_then(|()| stdout.flush())
+            .is_err()
+        {
+            break;
+        }
+    }
+    std::process::exit(0);
+}
+
+/// Reads the next line of input from stdin, joining lines that end with a `\`
+/// with the line after them.
 ///
-/// # Supported Operators
-/// * `+` - Addition
-/// * `-` - Subtraction
-/// * `*` - Multiplication
-/// * `/` - Division
+/// The backslash itself is removed, so `1 + \` followed by `2` reads as
+/// `1 + 2`. Returns `None` at the end of input.
 ///
 /// # Errors
-/// Returns an error if:
-/// * Division by zero is attempted
-/// * An unsupported operator is used
-///
-/// # Examples
-/// ```
-/// let result = calculate(10.0, 5.0, "+");
-/// assert_eq!(result.unwrap(), 15.0);
+/// An error of kind [`std::io::ErrorKind::UnexpectedEof`] if the input ends
+/// right after a continued line, and any error from reading stdin.
+fn read_input() -> std::io::Result<Option<String>> {
+    let mut input = String::new();
+    loop {
+        let mut line = String::new();
+        if std::io::stdin().read_line(&mut line)? == 0 {
+            if input.is_empty() {
+                return Ok(None);
+            }
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "Input ended after a line continued with '\\'",
+            ));
+        }
+        match line.trim_end().strip_suffix('\\') {
+            Some(start) => input.push_str(start),
+            None => {
+                input.push_str(&line);
+                return Ok(Some(input));
+            }
+        }
+    }
+}
+
+/// Reads stdin with [`read_input`] on a separate thread, so that the
+/// interactive calculator can notice Ctrl-C while it waits for a line.
+fn spawn_reader() -> Receiver<std::io::Result<Option<String>>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || loop {
+        let input = read_input();
+        let more = matches!(input, Ok(Some(_)));
+        if sender.send(input).is_err() || !more {
+            break;
+        }
+    });
+    receiver
+}
+
+/// Waits for the next line from [`spawn_reader`], handling Ctrl-C meanwhile.
 ///
-/// let divide_by_zero = calculate(5.0, 0.0, "/");
-/// assert!(divide_by_zero.is_err());
-/// ```
-fn calculate(num1: f64, num2: f64, operator: &str) -> Result<f64, Box<dyn std::error::Error>> {
-    match operator {
-        "+" => Ok(num1 + num2),
-        "-" => Ok(num1 - num2),
-        "*" => Ok(num1 * num2),
-        "/" => {
-            if num2 == 0.0 {
-                Err("Cannot divide by zero".into())
-            } else {
-                Ok(num1 / num2)
+/// The first Ctrl-C prints a hint and shows the prompt again; a second one
+/// before the next line returns `None`, like the end of input, so the
+/// calculator still exits cleanly and saves the history.
+fn wait_for_input(
+    lines: &Receiver<std::io::Result<Option<String>>>,
+    interrupts: &mut Interrupts,
+    session: &Session,
+) -> std::io::Result<Option<String>> {
+    loop {
+        match lines.recv_timeout(INTERRUPT_POLL) {
+            Ok(input) => {
+                interrupts.reset();
+                return input;
             }
+            Err(RecvTimeoutError::Timeout) if take_interrupt() => match interrupts.interrupt() {
+                InterruptAction::Warn => {
+                    println!("\nInterrupted. Type q to quit.");
+                    session.write_prompt(&mut std::io::stdout())?;
+                }
+                InterruptAction::Exit => {
+                    println!();
+                    return Ok(None);
+                }
+            },
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => return Ok(None),
         }
-        _ => Err("Invalid operator".into()),
     }
 }
 
 /// Entry point of the calculator application.
 ///
-/// This function runs an interactive command-line calculator that:
-/// - Continuously prompts for user input
+/// When arguments other than options are given, they are joined into a single
+/// expression that is evaluated once (see [`run_once`]); `--format json` or
+/// `--json` prints that result as JSON, `--format csv` prints it as CSV, and
+/// `--precision N` rounds it to `N` decimal places, while `--check` only
+/// validates its syntax (see [`run_check`]) and `--verbose` prints each
+/// calculation step first. `--quiet` prints nothing but the results, for piping
+/// into other tools: it overrides `--verbose` and drops the expression and
+/// summary from `--file` output. `--allow-inf` accepts `inf` as a value in
+/// every mode, `--seed N` makes the numbers from `rand` the same on every run,
+/// `--max-input-len N` rejects input longer than `N` characters, and
+/// `--max-depth N` rejects expressions nested more than `N` levels deep.
+/// `--file PATH` evaluates each line of a file instead (see [`run_file`]),
+/// `--eval "x = 5; x * 2"` evaluates statements separated by `;` (see
+/// [`run_eval`]), `--from-env` takes the expression from the `CALC_EXPR`
+/// environment variable instead of the arguments, and `--serve` answers
+/// expressions from stdin line by line (see [`run_serve`]). Defaults such as
+/// the precision and angle mode are read from `~/.calculatorrc` (see
+/// [`load_config`]); options given on the command line take precedence. With
+/// `--interactive` (or `-i`), the expression is instead handled like a first
+/// line of input and the calculator keeps running, with `ans` set to its
+/// result. Without an expression, or after it with `--interactive`, this
+/// function runs an interactive command-line calculator that:
+/// - Continuously prompts for user input (the prompt is suppressed when stdin
+///   is not a terminal, so piped input produces only results)
 /// - Processes mathematical expressions
 /// - Handles errors gracefully
-/// - Allows clean program termination
+/// - Shows results in green and errors in red on a terminal, unless
+///   `--no-color` is given or the `NO_COLOR` environment variable is set
+/// - Allows clean program termination, including on end of input; Ctrl-C
+///   returns to the prompt, and a second Ctrl-C in a row quits
 ///
 /// # Usage
-/// The program accepts input in the format: "number operator number"
-/// - Valid operators: +, -, *, /
+/// The program accepts expressions in the format: "number operator number ..."
+/// - Operators follow the usual precedence rules (e.g. `2 + 3 * 4` is `14`)
+/// - Parentheses group sub-expressions (e.g. `(2 + 3) * 4` is `20`)
+/// - A number in front of `(` or a name multiplies it (e.g. `2pi` is `2 * pi`)
+/// - Valid operators: +, -, *, /, //, %, ^, & (and), | (or), xor, << and >> (shifts)
+/// - Comparisons with <, >, <=, >=, == and != give `true` or `false`, e.g.
+///   `5 > 3`; `==` allows for floating-point rounding
 /// - Numbers can be integers or floating-point
-/// - Enter 'q' to quit the program
+/// - Enter 'mode deg' or 'mode rad' to switch the angle unit used by trigonometric
+///   functions (radians by default)
+/// - Enter 'mode int' or 'mode frac' for exact integer or fraction arithmetic,
+///   'mode complex' for complex numbers such as `2 + 3i`, and 'mode real' to go
+///   back
+/// - Enter 'intwidth 8' (or 16, 32 or 64) to emulate unsigned integers of that
+///   width in integer mode, and 'overflow wrap', 'saturate' or 'error' to
+///   choose what happens to results that don't fit; 'intwidth off' removes the
+///   limit. A width also shows negative integers in two's complement in
+///   'base hex' and 'base bin', e.g. `-1` as `0xff` at 8 bits
+/// - Enter 'mode tape' to keep a running total like an adding machine: '+5'
+///   or '* 2' updates it, 'total' shows it and 'reset' zeroes it; 'mode std'
+///   goes back to expressions
+/// - Enter 'mode rpn' to type expressions in Reverse Polish Notation, e.g.
+///   '2 3 4 * +'
+/// - Enter 'mode sci' to accept `inf` as a value and 'mode finite' to reject
+///   it again (the default, unless `--allow-inf` is given)
+/// - Assign variables with 'name = expression' and use them in later expressions
+/// - Define functions of one argument with 'define double(x) = x * 2' and call
+///   them as in 'double(21)'
+/// - Use 'ans' to refer to the previous result (0 before the first calculation);
+///   a line starting with an operator such as '+ 5' or '* 2' continues from it,
+///   while '-5' without a space is a negative number
+/// - Enter 'M+' or 'M-' to add the previous result to the memory register or
+///   subtract it, 'MR' to show the register ('mr' in expressions) and 'MC' to
+///   clear it
+/// - Enter 'history' to list previous calculations, 'search TERM' to list those
+///   containing TERM and '!N' to repeat entry N; in interactive sessions the
+///   history is kept in `~/.rust_calculator_history`
+/// - Enter 'last_error' to show the most recent error again
+/// - Enter 'precision N' to show results with N decimal places ('precision full'
+///   restores the default)
+/// - Enter 'round_mode up', 'down', 'truncate' or 'nearest' to choose how
+///   results are rounded to the precision
+/// - Enter 'sep on' or 'sep off' to toggle thousands separators in results
+/// - Enter 'sci on' or 'sci off' to toggle scientific notation in results
+/// - Enter 'eng on' or 'eng off' to toggle engineering notation in results
+/// - Enter 'base hex', 'base bin' or 'base dec' to choose the base for integer results
+/// - Enter 'currency on' to show results as amounts of money such as `$1,234.50`,
+///   'currency €' for another symbol and 'currency off' to go back
+/// - Enter 'locale eu' to accept a decimal comma (`5,5`) and 'locale us' to go back
+/// - `x` between two values, `×` and `÷` can be used for `*` and `/` (e.g.
+///   `5 x 5`); enter 'aliases off' if `x` should always be a variable
+/// - Enter 'prompt "calc> "' to change the prompt (`> ` by default)
+/// - Enter 'convert 100 km mi' to convert between units of length, weight or
+///   temperature
+/// - Enter 'clear' to clear the screen, or 'clear all' to also forget `ans`,
+///   variables and the history
+/// - End a line with '\' to continue the expression on the next line
+/// - Enter 'help' to list the supported operators, functions and commands, and
+///   'explain NAME' (e.g. 'explain sqrt') to describe one of them in more detail
+/// - Enter 'tokens EXPRESSION' to show the tokens an expression is split into,
+///   for debugging how it is read, or 'ast EXPRESSION' to show the tree it is
+///   parsed into
+/// - Enter 'q', 'quit', 'exit' or ':q' to quit the program
 ///
 /// # Returns
 /// * `Result<(), Box<dyn std::error::Error>>` - Ok(()) on successful execution
 ///
 /// # Examples
 /// ```text
-/// Please enter your calculation (e.g. 5 + 5) or 'q' to quit:
-/// 5 + 5
+/// $ rust-calculator-cli "5 + 5"
+/// 10
+///
+/// $ rust-calculator-cli
+/// Enter a calculation (e.g. 5 + 5), 'help' or 'q' to quit.
+/// > 5 + 5
 /// 5 + 5 = 10
 /// ```
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    loop {
-        println!("Please enter your calculation (e.g. 5 + 5) or 'q' to quit:");
-        let mut input = String::new();
-        std::io::stdin().read_line(&mut input)?;
-
-        if input.trim().to_lowercase() == "q" {
-            println!("Thanks for using.");
-            break;
-        }
-
-        match parse_input(&input).and_then(|(num1, num2, operator)| {
-            let result = calculate(num1, num2, operator)?;
-            println!("{} {} {} = {}", num1, operator, num2, result);
-            Ok(())
-        }) {
-            Ok(_) => (),
-            Err(e) => eprintln!("Error: {}", e),
-        }
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut options = Options::parse(&args).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
+    if options.from_env {
+        let expression = expression_from_env(&options).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        });
+        options.expression = Some(expression);
     }
-    Ok(())
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_parse_input_valid() {
-        let input = "5 + 5";
-        let result = parse_input(input);
-        assert!(result.is_ok());
-        let (num1, num2, operator) = result.unwrap();
-        assert_eq!(num1, 5.0);
-        assert_eq!(num2, 5.0);
-        assert_eq!(operator, "+");
+    let config = config_path()
+        .map(|path| load_config(&path))
+        .unwrap_or_default();
+    let settings = Settings {
+        precision: options.precision.or(config.settings.precision),
+        ..config.settings
+    };
+    let context = Context {
+        allow_infinity: options.allow_inf,
+        angle_mode: config.angle_mode,
+        locale: config.locale,
+        operator_aliases: config.operator_aliases,
+        rng: options.seed.map(Rng::with_seed).unwrap_or_default(),
+        max_input_len: options.max_input_len.unwrap_or(config.max_input_len),
+        max_depth: options.max_depth.unwrap_or(config.max_depth),
+        ..Context::default()
+    };
+    if let Some(path) = &options.file {
+        run_file(path, options.format, options.quiet, settings, context);
     }
-
-    #[test]
-    fn test_parse_input_invalid_format() {
-        let input = "5 + ";
-        assert!(parse_input(input).is_err());
+    if let Some(script) = &options.eval {
+        run_eval(script, &settings, context);
     }
-
-    #[test]
-    fn test_parse_input_invalid_number() {
-        let input = "abc + 5";
-        assert!(parse_input(input).is_err());
+    if options.serve {
+        run_serve(settings, context);
     }
-
-    #[test]
-    fn test_parse_input_invalid_operator() {
-        let input = "5 % 5";
-        assert!(parse_input(input).is_err());
+    if options.check {
+        match &options.expression {
+            Some(expression) => run_check(expression, &context),
+            None => {
+                eprintln!("Error: Option '--check' needs an expression");
+                std::process::exit(1);
+            }
+        }
     }
-
-    #[test]
-    fn test_calculate_with_decimals() {
-        let result = calculate(5.5, 2.2, "+");
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), 7.7);
+    if let Some(expression) = options.expression.as_ref().filter(|_| !options.interactive) {
+        run_once(
+            expression,
+            options.format,
+            options.verbose && !options.quiet,
+            &settings,
+            &context,
+        );
     }
 
-    #[test]
-    fn test_calculate_negative_numbers() {
-        let result = calculate(-5.0, 3.0, "+");
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), -2.0);
+    let interactive = std::io::stdin().is_terminal();
+    let no_color = std::env::var_os("NO_COLOR");
+    let no_color_flag = options.no_color || !config.color;
+    let color_results = color_enabled(
+        no_color_flag,
+        no_color.as_deref(),
+        std::io::stdout().is_terminal(),
+    );
+    let color_errors = color_enabled(
+        no_color_flag,
+        no_color.as_deref(),
+        std::io::stderr().is_terminal(),
+    );
+    let mut session = Session {
+        settings,
+        context,
+        prompt: config.prompt,
+        ..Session::default()
+    };
+    let history_path = history_path().filter(|_| interactive);
+    if let Some(path) = &history_path {
+        for entry in load_history(path) {
+            session.history.push(entry);
+        }
     }
 
-    #[test]
-    fn test_calculate_addition() {
-        let result = calculate(5.0, 5.0, "+");
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), 10.0);
-    }
+    let lines = interactive.then(|| {
+        install_interrupt_handler();
+        spawn_reader()
+    });
+    let mut interrupts = Interrupts::default();
 
-    #[test]
-    fn test_calculate_subtraction() {
-        let result = calculate(5.0, 5.0, "-");
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), 0.0);
+    if interactive {
+        println!("Enter a calculation (e.g. 5 + 5), 'help' or 'q' to quit.");
     }
+    // With `--interactive`, the expression from the command line is handled
+    // as if it had been typed as the first line.
+    let mut first_input = options.expression.filter(|_| options.interactive);
+    loop {
+        if interactive {
+            session.write_prompt(&mut std::io::stdout())?;
+        }
+        let input = match (first_input.take(), &lines) {
+            (Some(expression), _) => {
+                if interactive {
+                    println!("{}", expression);
+                }
+                Ok(Some(expression))
+            }
+            (None, Some(lines)) => wait_for_input(lines, &mut interrupts, &session),
+            (None, None) => read_input(),
+        };
+        let input = match input {
+            Ok(Some(input)) => input,
+            Ok(None) => break,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                let error = format!("Error: {}", e);
+                eprintln!("{}", paint(error, Color::Red, color_errors));
+                break;
+            }
+            Err(e) => return Err(e.into()),
+        };
 
-    #[test]
-    fn test_calculate_multiplication() {
-        let result = calculate(5.0, 5.0, "*");
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), 25.0);
-    }
+        if is_quit_command(&input) {
+            if interactive {
+                println!("Thanks for using.");
+            }
+            break;
+        }
+
+        let command = input.trim();
+        if command == "clear" || command == "clear all" {
+            if command == "clear all" {
+                session.reset();
+            }
+            if interactive {
+                print!("{}", CLEAR_SCREEN);
+            }
+            continue;
+        }
+
+        let response = session.handle(&input);
+        if !matches!(response, Ok(Response::Exact { .. })) {
+            for warning in precision_warnings(&input) {
+                eprintln!("Warning: {}", warning);
+            }
+        }
+        if let Ok(Response::Value(_)) = response {
+            if let Some(warning) = session.context.number_mode.fallback_warning() {
+                eprintln!("Warning: {}", warning);
+            }
+        }
+        if let Ok(
+            Response::Value(Calculation { result: value, .. }) | Response::Assignment { value, .. },
+        ) = response
+        {
+            if let Some(warning) = session.settings.warning(value) {
+                eprintln!("Warning: {}", warning);
+            }
+        }
 
-    #[test]
-    fn test_calculate_division() {
-        let result = calculate(5.0, 5.0, "/");
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), 1.0);
+        let result = match response {
+            Ok(Response::Exact { expression, value }) if interactive => {
+                format!("{} = {}", expression, session.format_exact(&value))
+            }
+            Ok(Response::Exact { value, .. }) => session.format_exact(&value),
+            Ok(Response::Bool { expression, value }) if interactive => {
+                format!("{} = {}", expression, value)
+            }
+            Ok(Response::Bool { value, .. }) => value.to_string(),
+            Ok(Response::Value(calculation)) if interactive => format!(
+                "{} = {}",
+                calculation.input,
+                session.format(calculation.result)
+            ),
+            Ok(Response::Value(calculation)) => session.format(calculation.result),
+            Ok(Response::Assignment { name, value }) => {
+                format!("{} = {}", name, session.format(value))
+            }
+            Ok(Response::Output(output)) => {
+                println!("{}", output);
+                continue;
+            }
+            Ok(Response::Info(info)) if interactive => {
+                println!("{}", info);
+                continue;
+            }
+            Ok(Response::Info(_) | Response::Empty) => continue,
+            Err(e) => {
+                let error = format!("Error: {}", e);
+                eprintln!("{}", paint(error, Color::Red, color_errors));
+                continue;
+            }
+        };
+        println!("{}", paint(result, Color::Green, color_results));
     }
 
-    #[test]
-    fn test_calculate_division_by_zero() {
-        let result = calculate(5.0, 0.0, "/");
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err().to_string(), "Cannot divide by zero");
+    if let Some(path) = &history_path {
+        if let Err(e) = save_history(path, &session.history) {
+            eprintln!(
+                "Warning: Could not save history to {}: {}",
+                path.display(),
+                e
+            );
+        }
     }
+    Ok(())
 }
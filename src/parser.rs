@@ -0,0 +1,1600 @@
+//! Recursive-descent parser for calculator expressions.
+//!
+//! The grammar, from lowest to highest precedence:
+//!
+//! ```text
+//! comparison := expression (("<" | ">" | "<=" | ">=" | "==" | "!=") expression)?
+//! expression := bit_xor ("|" bit_xor)*
+//! bit_xor    := bit_and ("xor" bit_and)*
+//! bit_and    := shift ("&" shift)*
+//! shift      := sum (("<<" | ">>") sum)*
+//! sum        := term (("+" | "-") term)*
+//! term       := unary (("*" | "/" | "//" | "%")? unary)*
+//! unary      := "-" unary | power
+//! power      := postfix ("^" unary)?
+//! postfix    := primary ("!" | "%")*
+//! primary    := number | constant | "(" expression ")" | call
+//! call       := function ("(" arguments? ")" | unary) | name "(" arguments? ")"
+//! arguments  := expression ("," expression)*
+//! ```
+//!
+//! Identifiers are either `ans` (the previous result), `mr` (the memory
+//! register), constants (`pi`, `e`, `tau`), variables from the [`Context`] or
+//! function names; any other identifier is rejected.
+//!
+//! A function name followed by `(` takes exactly the parenthesised,
+//! comma-separated list as its arguments, so `sqrt(16) + 1` is `5`. Without
+//! parentheses the argument extends over the following unary expression, so
+//! `sqrt 16` also works. Any other name followed by `(` is a call of a
+//! [`UserFunction`] from the [`Context`], as in `double(21)`.
+//!
+//! A number directly followed by `(` or an identifier is multiplied by it, so
+//! `2(3 + 4)` is `14` and `2pi` is `2 * pi`. This only applies after a
+//! number: `pi2` is a single identifier, and `(1 + 1)(2)` is an error. The
+//! implied `*` has its usual precedence, so `1 / 2pi` is `(1 / 2) * pi`.
+//!
+//! A `-` is negation when it appears where an operand is expected (at the
+//! start, after another operator or after `(`) and subtraction otherwise.
+//! Negation binds looser than `^`, so `-2 ^ 2` is `-4`. Unlike the other
+//! binary operators, `^` is right-associative, so `2 ^ 3 ^ 2` is `2 ^ 9`.
+//! The factorial `!` binds tightest, so `2 ^ 3!` is `2 ^ 6`.
+//!
+//! A `%` followed by something that can start an operand (a number,
+//! identifier, `(` or a negating `-`) is the remainder operator, so
+//! `7 % -3` is `1`. Before `)`, `,`, another operator or the end of the input
+//! it is a postfix percentage, which depends on its context:
+//! * As the right operand of `+` or `-` it is a percentage of the left
+//!   operand, so `200 + 10%` is `220` and `200 - 10%` is `180`.
+//! * Anywhere else it simply divides by 100, so `50%` is `0.5` and
+//!   `200 * 10%` is `20`.
+//!
+//! The bitwise and shift operators bind looser than arithmetic, so
+//! `1 + 2 & 3` is `(1 + 2) & 3` and `1 << 2 + 1` is `1 << 3`. Exclusive or
+//! is spelled `xor` because `^` is exponentiation.
+//!
+//! A comparison can only be the outermost operator and can't be chained. It
+//! is accepted by [`parse_value`], where it produces a [`Value::Bool`], but not
+//! by [`parse_expression`], which always produces a number.
+//!
+//! Nesting is limited to [`Context::max_depth`] levels, 256 by default: every
+//! pair of parentheses, function call, negation and `^` adds one, as does
+//! every further operator in a chain such as `1 + 2 + 3`, which nests the
+//! tree on the left. Deeper input is rejected with
+//! [`CalcError::TooDeeplyNested`] rather than overflowing the stack while it
+//! is parsed or evaluated.
+//!
+//! With [`Locale::Eu`](crate::Locale::Eu) a comma between two digits is a decimal comma, so
+//! `5,5` is `5.5`. Only the decimal point is remapped: there are no thousands
+//! separators in either locale, and function arguments are still separated by
+//! commas, so write `log(8, 2)` with a space to pass two arguments.
+//!
+//! The input is split into tokens by the [`tokenizer`](crate::tokenizer) and
+//! parsed into an [`Expr`] tree, which is then evaluated against a
+//! [`Context`]. Identifiers are looked up during evaluation, so the same tree
+//! can be evaluated again after a variable changes. [`check_expression`] only
+//! checks the syntax, ignoring errors such as division by zero.
+
+use crate::tokenizer::{tokenize_with_positions, Token};
+use crate::{
+    calculate, call_function, check_input_length, compare, constant, factorial, finite_literal,
+    is_function, CalcError, Context, Locale, Value, COMPARISON_OPERATORS,
+};
+
+/// An expression parsed into a tree, as returned by [`parse_to_ast`].
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::parser::{parse_to_ast, Expr};
+///
+/// let expr = parse_to_ast("2 + 3").unwrap();
+/// assert_eq!(
+///     expr,
+///     Expr::BinaryOp {
+///         op: "+",
+///         lhs: Box::new(Expr::Num(2.0)),
+///         rhs: Box::new(Expr::Num(3.0)),
+///     }
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// A number literal
+    Num(f64),
+    /// An integer literal beyond 2^53, which an `f64` can't hold exactly; it
+    /// is rounded when evaluated with `f64`, but kept exact in integer mode
+    Integer(i128),
+    /// A name such as `ans`, `pi` or a variable, looked up when evaluated
+    Ident(String),
+    /// An expression in parentheses. It is kept so that `200 + (10%)` can be
+    /// told apart from the percentage `200 + 10%`.
+    Group(Box<Expr>),
+    /// Negation or a postfix operator applied to `operand`
+    UnaryOp {
+        op: UnaryOperator,
+        operand: Box<Expr>,
+    },
+    /// A binary operator such as `+` or `<<`, or a comparison such as `<`
+    BinaryOp {
+        op: &'static str,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    /// A call of a built-in function or a [`UserFunction`]
+    FuncCall { name: String, args: Vec<Expr> },
+}
+
+/// A function of one argument defined with `define`, as in
+/// `define double(x) = x * 2`.
+///
+/// The body is parsed once when the function is defined. When it is called,
+/// the parameter stands for the argument; other names in the body, such as
+/// variables, are looked up in the [`Context`] at that time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserFunction {
+    /// The name of the argument in the body, e.g. `x`
+    pub parameter: String,
+    /// The expression computed from the argument
+    pub body: Expr,
+}
+
+/// The operator of an [`Expr::UnaryOp`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOperator {
+    /// A leading `-`
+    Neg,
+    /// A postfix `!`
+    Factorial,
+    /// A postfix `%`, see the module documentation for its meaning
+    Percent,
+}
+
+/// Parses and evaluates an expression such as `(2 + 3) * 4`.
+///
+/// # Arguments
+/// * `input` - A string slice containing the expression
+/// * `context` - Evaluation settings, e.g. the angle mode for trigonometry
+///
+/// # Returns
+/// * `Result<f64, CalcError>` - The value of the expression
+///
+/// # Errors
+/// * [`CalcError::InvalidInput`] if the input is empty
+/// * [`CalcError::UnmatchedParenthesis`] or [`CalcError::EmptyParentheses`]
+///   if parentheses are unbalanced or empty
+/// * [`CalcError::UnexpectedToken`] or [`CalcError::UnexpectedEnd`] if a
+///   number or operator is missing (e.g. `2 + + 3`)
+/// * Any error from a single calculation (see [`calculate`])
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::parser::parse_expression;
+/// use rust_calculator_cli::Context;
+///
+/// let result = parse_expression("((1 + 2) * (3 + 4))", &Context::default());
+/// assert_eq!(result.unwrap(), 21.0);
+/// ```
+pub fn parse_expression(input: &str, context: &Context) -> Result<f64, CalcError> {
+    let expr = parse(input, context, Parser::expression)?;
+    eval_ast_with(&expr, context)
+}
+
+/// Parses and evaluates an expression that may be a comparison, such as
+/// `2 + 3 > 4`.
+///
+/// # Returns
+/// * `Result<Value, CalcError>` - A [`Value::Bool`] for a comparison and a
+///   [`Value::Number`] otherwise
+///
+/// # Errors
+/// The same as [`parse_expression`], and [`CalcError::UnexpectedToken`] for
+/// a chained comparison such as `1 < 2 < 3`.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::parser::parse_value;
+/// use rust_calculator_cli::{Context, Value};
+///
+/// let result = parse_value("2 + 3 > 4", &Context::default());
+/// assert_eq!(result, Ok(Value::Bool(true)));
+/// ```
+pub fn parse_value(input: &str, context: &Context) -> Result<Value, CalcError> {
+    let expr = parse(input, context, Parser::comparison)?;
+    Evaluator::new(context, false).value(&expr)
+}
+
+/// Parses and evaluates an expression like [`parse_value`], listing each
+/// calculation on the way, as shown by `--verbose`.
+///
+/// Every operator, factorial and function call adds a step such as
+/// `3 * 4 = 12`, in the order they are evaluated, so the last step gives the
+/// result. A percentage of the left operand adds a step such as
+/// `10% of 200 = 20`. Negation and plain numbers add no steps.
+///
+/// # Returns
+/// * `Result<(Value, Vec<String>), CalcError>` - The value and the steps
+///
+/// # Errors
+/// The same as [`parse_value`].
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::parser::evaluation_steps;
+/// use rust_calculator_cli::{Context, Value};
+///
+/// let (value, steps) = evaluation_steps("2 + 3 * 4", &Context::default()).unwrap();
+/// assert_eq!(value, Value::Number(14.0));
+/// assert_eq!(steps, ["3 * 4 = 12", "2 + 12 = 14"]);
+/// ```
+pub fn evaluation_steps(input: &str, context: &Context) -> Result<(Value, Vec<String>), CalcError> {
+    let expr = parse(input, context, Parser::comparison)?;
+    let mut evaluator = Evaluator::new(context, false);
+    evaluator.steps = Some(Vec::new());
+    let value = evaluator.value(&expr)?;
+    Ok((value, evaluator.steps.unwrap_or_default()))
+}
+
+/// Checks that `input` is a well-formed expression without needing its value.
+///
+/// The expression is parsed like [`parse_value`], but errors that only
+/// depend on the values involved, such as [`CalcError::DivisionByZero`],
+/// [`CalcError::Overflow`], [`CalcError::NotANumber`] and
+/// [`CalcError::Domain`], are ignored. Unknown identifiers and wrong argument
+/// counts are still reported.
+///
+/// # Errors
+/// The same as [`parse_expression`], apart from the errors listed above.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::parser::check_expression;
+/// use rust_calculator_cli::{CalcError, Context};
+///
+/// let context = Context::default();
+/// assert_eq!(check_expression("(1 / 0) * 2", &context), Ok(()));
+/// assert_eq!(check_expression("5 +", &context), Err(CalcError::UnexpectedEnd));
+/// ```
+pub fn check_expression(input: &str, context: &Context) -> Result<(), CalcError> {
+    let expr = parse(input, context, Parser::comparison)?;
+    Evaluator::new(context, true).value(&expr).map(|_| ())
+}
+
+/// Parses an expression into an [`Expr`] without evaluating it.
+///
+/// Numbers are read with [`Locale::Us`]; use [`parse_to_ast_with`] for a
+/// decimal comma. Comparisons are rejected, as by [`parse_expression`].
+///
+/// # Errors
+/// The syntax errors of [`parse_expression`]. Unknown identifiers are only
+/// reported by [`eval_ast`].
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::parser::{eval_ast, parse_to_ast};
+///
+/// let expr = parse_to_ast("2 + 3 * 4").unwrap();
+/// assert_eq!(eval_ast(&expr), Ok(14.0));
+/// ```
+pub fn parse_to_ast(input: &str) -> Result<Expr, CalcError> {
+    parse_to_ast_with(input, Locale::Us)
+}
+
+/// Parses an expression into an [`Expr`], reading numbers in `locale`.
+///
+/// # Errors
+/// The same as [`parse_to_ast`].
+pub fn parse_to_ast_with(input: &str, locale: Locale) -> Result<Expr, CalcError> {
+    let context = Context {
+        locale,
+        ..Context::default()
+    };
+    parse_ast(input, &context)
+}
+
+/// Parses an expression into an [`Expr`] like [`parse_to_ast_with`], using
+/// the locale, operator aliases and limits of `context`.
+pub(crate) fn parse_ast(input: &str, context: &Context) -> Result<Expr, CalcError> {
+    parse(input, context, Parser::expression)
+}
+
+/// Evaluates an [`Expr`] with the default [`Context`].
+///
+/// # Errors
+/// * [`CalcError::UnknownIdentifier`] for a name that isn't defined
+/// * Any error from a single calculation or function call
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::parser::{eval_ast, Expr, UnaryOperator};
+///
+/// let expr = Expr::UnaryOp {
+///     op: UnaryOperator::Factorial,
+///     operand: Box::new(Expr::Num(4.0)),
+/// };
+/// assert_eq!(eval_ast(&expr), Ok(24.0));
+/// ```
+pub fn eval_ast(expr: &Expr) -> Result<f64, CalcError> {
+    eval_ast_with(expr, &Context::default())
+}
+
+/// Evaluates an [`Expr`] with the variables and settings in `context`.
+///
+/// # Errors
+/// The same as [`eval_ast`]. A comparison gives
+/// [`CalcError::InvalidOperator`], since its result isn't a number.
+pub fn eval_ast_with(expr: &Expr, context: &Context) -> Result<f64, CalcError> {
+    Evaluator::new(context, false).number(expr)
+}
+
+/// Parses `input` as a whole with the grammar rule `rule`, tokenizing it as
+/// set in `context`.
+fn parse(
+    input: &str,
+    context: &Context,
+    rule: fn(&mut Parser) -> Result<Expr, CalcError>,
+) -> Result<Expr, CalcError> {
+    check_input_length(input, context)?;
+    let (tokens, positions) = tokenize_with_positions(
+        input,
+        context.locale,
+        context.operator_aliases,
+        context.angle_mode,
+    )?
+    .into_iter()
+    .unzip();
+
+    let mut parser = Parser {
+        tokens,
+        positions,
+        position: 0,
+        depth: 0,
+        max_depth: context.max_depth,
+        allow_infinity: context.allow_infinity,
+    };
+    if parser.tokens.is_empty() {
+        return Err(CalcError::InvalidInput);
+    }
+    let expr = rule(&mut parser)?;
+
+    match parser.peek() {
+        None => Ok(expr),
+        Some(Token::RightParen) => Err(CalcError::UnmatchedParenthesis),
+        Some(_) => Err(parser.unexpected(parser.position)),
+    }
+}
+
+/// Builds a binary [`Expr`] node.
+fn binary(op: &'static str, lhs: Expr, rhs: Expr) -> Expr {
+    Expr::BinaryOp {
+        op,
+        lhs: Box::new(lhs),
+        rhs: Box::new(rhs),
+    }
+}
+
+/// Builds a unary [`Expr`] node.
+fn unary(op: UnaryOperator, operand: Expr) -> Expr {
+    Expr::UnaryOp {
+        op,
+        operand: Box::new(operand),
+    }
+}
+
+/// Cursor over the token stream, with one method per grammar rule.
+struct Parser {
+    tokens: Vec<Token>,
+    /// Byte offset of each token in the input, for error messages
+    positions: Vec<usize>,
+    position: usize,
+    /// How deeply the expression is nested at the current token: every
+    /// `unary` rule and every operator of a chain such as `1 + 2 + 3` adds a
+    /// level, which bounds both the recursion here and the depth of the tree
+    depth: usize,
+    /// The largest `depth` allowed, see [`Context::max_depth`]
+    max_depth: usize,
+    /// Whether a number literal may overflow to infinity, see
+    /// [`Context::allow_infinity`]
+    allow_infinity: bool,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    /// Reports the token at `index` as unexpected, with its position.
+    fn unexpected(&self, index: usize) -> CalcError {
+        CalcError::UnexpectedToken {
+            token: describe(&self.tokens[index]),
+            position: self.positions.get(index).copied(),
+        }
+    }
+
+    /// Consumes the next token if it is one of the given operators.
+    fn next_operator(&mut self, operators: &[&str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Operator(op)) if operators.contains(op) => {
+                let op = *op;
+                self.position += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    /// Goes one level deeper into the expression, failing beyond
+    /// `max_depth`.
+    fn descend(&mut self) -> Result<(), CalcError> {
+        if self.depth > self.max_depth {
+            return Err(CalcError::TooDeeplyNested(self.max_depth));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Parses a left-associative chain of `operators` between operands
+    /// parsed by `operand`.
+    ///
+    /// Each operator nests the tree one level deeper on the left, so it
+    /// counts towards `max_depth` like a pair of parentheses.
+    fn left_assoc(
+        &mut self,
+        operators: &[&str],
+        operand: fn(&mut Parser) -> Result<Expr, CalcError>,
+    ) -> Result<Expr, CalcError> {
+        let depth = self.depth;
+        let mut lhs = operand(self)?;
+        while let Some(operator) = self.next_operator(operators) {
+            self.descend()?;
+            let rhs = operand(self)?;
+            lhs = binary(operator, lhs, rhs);
+        }
+        self.depth = depth;
+        Ok(lhs)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, CalcError> {
+        let lhs = self.expression()?;
+        match self.next_operator(&COMPARISON_OPERATORS) {
+            Some(operator) => Ok(binary(operator, lhs, self.expression()?)),
+            None => Ok(lhs),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expr, CalcError> {
+        self.left_assoc(&["|"], Parser::bit_xor)
+    }
+
+    fn bit_xor(&mut self) -> Result<Expr, CalcError> {
+        self.left_assoc(&["xor"], Parser::bit_and)
+    }
+
+    fn bit_and(&mut self) -> Result<Expr, CalcError> {
+        self.left_assoc(&["&"], Parser::shift)
+    }
+
+    fn shift(&mut self) -> Result<Expr, CalcError> {
+        self.left_assoc(&["<<", ">>"], Parser::sum)
+    }
+
+    fn sum(&mut self) -> Result<Expr, CalcError> {
+        self.left_assoc(&["+", "-"], Parser::term)
+    }
+
+    fn term(&mut self) -> Result<Expr, CalcError> {
+        let depth = self.depth;
+        let mut lhs = self.unary()?;
+        loop {
+            let operator = match self.next_operator(&["*", "/", "//", "%"]) {
+                Some(operator) => operator,
+                None if self.implicit_multiplication() => "*",
+                None => {
+                    self.depth = depth;
+                    return Ok(lhs);
+                }
+            };
+            self.descend()?;
+            let rhs = self.unary()?;
+            lhs = binary(operator, lhs, rhs);
+        }
+    }
+
+    /// Whether a number is directly followed by `(` or an identifier, as in
+    /// `2(3 + 4)` or `2pi`, which multiplies them.
+    fn implicit_multiplication(&self) -> bool {
+        let after_number = matches!(
+            self.position
+                .checked_sub(1)
+                .map(|index| &self.tokens[index]),
+            Some(Token::Number(_) | Token::Integer(_))
+        );
+        let operand_follows = matches!(self.peek(), Some(Token::LeftParen | Token::Identifier(_)));
+        after_number && operand_follows
+    }
+
+    fn unary(&mut self) -> Result<Expr, CalcError> {
+        let depth = self.depth;
+        self.descend()?;
+        let expr = self.negation();
+        self.depth = depth;
+        expr
+    }
+
+    fn negation(&mut self) -> Result<Expr, CalcError> {
+        // Only reached where an operand is expected, so `-` here is negation.
+        if self.next_operator(&["-"]).is_some() {
+            return Ok(unary(UnaryOperator::Neg, self.unary()?));
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Expr, CalcError> {
+        let base = self.postfix()?;
+        if let Some(operator) = self.next_operator(&["^"]) {
+            // Recursing through `unary` makes `^` right-associative and
+            // allows a negative exponent such as `2 ^ -1`.
+            return Ok(binary(operator, base, self.unary()?));
+        }
+        Ok(base)
+    }
+
+    fn postfix(&mut self) -> Result<Expr, CalcError> {
+        let mut expr = self.primary()?;
+        loop {
+            if self.next_operator(&["!"]).is_some() {
+                expr = unary(UnaryOperator::Factorial, expr);
+            } else if self.percent_is_postfix() {
+                self.position += 1;
+                expr = unary(UnaryOperator::Percent, expr);
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    /// Whether the next token is a `%` that isn't followed by the start of
+    /// an operand, making it a percentage rather than the remainder operator.
+    fn percent_is_postfix(&self) -> bool {
+        let is_percent = self.peek() == Some(&Token::Operator("%"));
+        let followed_by_operand = matches!(
+            self.tokens.get(self.position + 1),
+            Some(
+                Token::Number(_)
+                    | Token::Integer(_)
+                    | Token::Identifier(_)
+                    | Token::LeftParen
+                    | Token::Operator("-")
+            )
+        );
+        is_percent && !followed_by_operand
+    }
+
+    /// Parses a comma-separated argument list up to and including the `)`.
+    fn arguments(&mut self) -> Result<Vec<Expr>, CalcError> {
+        let mut arguments = Vec::new();
+        if self.peek() != Some(&Token::RightParen) {
+            arguments.push(self.expression()?);
+            while self.peek() == Some(&Token::Comma) {
+                self.position += 1;
+                arguments.push(self.expression()?);
+            }
+        }
+        self.close_parenthesis()?;
+        Ok(arguments)
+    }
+
+    /// Consumes the `)` that closes a group or argument list.
+    fn close_parenthesis(&mut self) -> Result<(), CalcError> {
+        match self.peek() {
+            Some(Token::RightParen) => {
+                self.position += 1;
+                Ok(())
+            }
+            Some(_) => Err(self.unexpected(self.position)),
+            None => Err(CalcError::UnmatchedParenthesis),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, CalcError> {
+        let token = self.peek().cloned();
+        self.position += 1;
+
+        match token {
+            Some(Token::Number(number)) => {
+                Ok(Expr::Num(finite_literal(number, self.allow_infinity)?))
+            }
+            Some(Token::Integer(integer)) => Ok(Expr::Integer(integer)),
+            Some(Token::LeftParen) => {
+                if self.peek() == Some(&Token::RightParen) {
+                    return Err(CalcError::EmptyParentheses);
+                }
+                let expr = self.expression()?;
+                self.close_parenthesis()?;
+                Ok(Expr::Group(Box::new(expr)))
+            }
+            // Function names can't be used as variables, so a function name
+            // is always a call.
+            Some(Token::Identifier(name)) if is_function(&name) => {
+                let args = if self.peek() == Some(&Token::LeftParen) {
+                    self.position += 1;
+                    self.arguments()?
+                } else {
+                    vec![self.unary()?]
+                };
+                Ok(Expr::FuncCall { name, args })
+            }
+            Some(Token::Identifier(name)) if self.peek() == Some(&Token::LeftParen) => {
+                self.position += 1;
+                let args = self.arguments()?;
+                Ok(Expr::FuncCall { name, args })
+            }
+            Some(Token::Identifier(name)) => Ok(Expr::Ident(name)),
+            Some(Token::RightParen) => Err(CalcError::UnmatchedParenthesis),
+            Some(_) => Err(self.unexpected(self.position - 1)),
+            None => Err(CalcError::UnexpectedEnd),
+        }
+    }
+}
+
+/// Describes a token for use in error messages.
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Number(number) => format!("number '{}'", number),
+        Token::Integer(integer) => format!("number '{}'", integer),
+        Token::Operator(op) => format!("operator '{}'", op),
+        Token::Identifier(name) => format!("identifier '{}'", name),
+        Token::LeftParen => "'('".to_string(),
+        Token::RightParen => "')'".to_string(),
+        Token::Comma => "','".to_string(),
+    }
+}
+
+/// Computes the value of an [`Expr`] in a [`Context`].
+struct Evaluator<'a> {
+    context: &'a Context,
+    /// Whether only the syntax matters, see [`check_expression`].
+    check_only: bool,
+    /// The steps so far, if they are wanted, see [`evaluation_steps`].
+    steps: Option<Vec<String>>,
+    /// The parameter of the [`UserFunction`] being evaluated and its value.
+    parameter: Option<(&'a str, f64)>,
+}
+
+impl<'a> Evaluator<'a> {
+    fn new(context: &'a Context, check_only: bool) -> Self {
+        Evaluator {
+            context,
+            check_only,
+            steps: None,
+            parameter: None,
+        }
+    }
+
+    /// Adds the step described by `step` if steps are being recorded.
+    fn record(&mut self, step: impl FnOnce() -> String) {
+        if let Some(steps) = &mut self.steps {
+            steps.push(step());
+        }
+    }
+
+    /// Passes on the result of a calculation. In check mode, errors caused by
+    /// the values rather than the syntax become NaN, so evaluation goes on.
+    fn evaluated(&self, result: Result<f64, CalcError>) -> Result<f64, CalcError> {
+        match result {
+            Err(
+                CalcError::DivisionByZero
+                | CalcError::Overflow
+                | CalcError::NotANumber
+                | CalcError::Domain(_),
+            ) if self.check_only => Ok(f64::NAN),
+            result => result,
+        }
+    }
+
+    /// Evaluates an expression whose outermost operator may be a comparison.
+    fn value(&mut self, expr: &Expr) -> Result<Value, CalcError> {
+        match expr {
+            Expr::BinaryOp { op, lhs, rhs } if COMPARISON_OPERATORS.contains(op) => {
+                let lhs = self.number(lhs)?;
+                let rhs = self.number(rhs)?;
+                let result = match compare(lhs, rhs, op) {
+                    Err(CalcError::NotANumber) if self.check_only => Ok(false),
+                    result => result,
+                }?;
+                self.record(|| format!("{} {} {} is {}", lhs, op, rhs, result));
+                Ok(Value::Bool(result))
+            }
+            _ => self.number(expr).map(Value::Number),
+        }
+    }
+
+    fn number(&mut self, expr: &Expr) -> Result<f64, CalcError> {
+        match expr {
+            Expr::Num(number) => Ok(*number),
+            Expr::Integer(integer) => Ok(*integer as f64),
+            Expr::Ident(name) => self.identifier(name),
+            Expr::Group(inner) => self.number(inner),
+            Expr::UnaryOp { op, operand } => {
+                let value = self.number(operand)?;
+                match op {
+                    UnaryOperator::Neg => Ok(-value),
+                    UnaryOperator::Factorial => {
+                        let result = self.evaluated(factorial(value))?;
+                        self.record(|| format!("{}! = {}", value, result));
+                        Ok(result)
+                    }
+                    UnaryOperator::Percent => Ok(value / 100.0),
+                }
+            }
+            Expr::BinaryOp { op, lhs, rhs } => {
+                let lhs = self.number(lhs)?;
+                let mut rhs_value = self.number(rhs)?;
+                if matches!(*op, "+" | "-") && is_percentage(rhs) {
+                    let percent = rhs_value * 100.0;
+                    rhs_value *= lhs;
+                    self.record(|| format!("{}% of {} = {}", percent, lhs, rhs_value));
+                }
+                let result = self.evaluated(calculate(lhs, rhs_value, op))?;
+                self.record(|| format!("{} {} {} = {}", lhs, op, rhs_value, result));
+                Ok(result)
+            }
+            Expr::FuncCall { name, args } => {
+                let arguments = args
+                    .iter()
+                    .map(|arg| self.number(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let result = match self.context.functions.get(name) {
+                    Some(function) => self.call_user_function(name, function, &arguments)?,
+                    None => self.evaluated(call_function(name, &arguments, self.context))?,
+                };
+                self.record(|| {
+                    let arguments: Vec<String> =
+                        arguments.iter().map(|arg| arg.to_string()).collect();
+                    format!("{}({}) = {}", name, arguments.join(", "), result)
+                });
+                Ok(result)
+            }
+        }
+    }
+
+    /// Evaluates the body of `function` with its parameter set to the only
+    /// argument. Steps in the body are recorded with the others.
+    fn call_user_function(
+        &mut self,
+        name: &str,
+        function: &'a UserFunction,
+        arguments: &[f64],
+    ) -> Result<f64, CalcError> {
+        match *arguments {
+            [argument] => {
+                let mut body = Evaluator {
+                    steps: self.steps.take(),
+                    parameter: Some((&function.parameter, argument)),
+                    ..Evaluator::new(self.context, self.check_only)
+                };
+                let result = body.number(&function.body);
+                self.steps = body.steps;
+                result
+            }
+            _ => Err(CalcError::ArgumentCount {
+                function: name.to_string(),
+                found: arguments.len(),
+            }),
+        }
+    }
+
+    fn identifier(&self, name: &str) -> Result<f64, CalcError> {
+        if let Some((parameter, value)) = self.parameter {
+            if name == parameter {
+                return Ok(value);
+            }
+        }
+        if name == "ans" {
+            return Ok(self.context.ans);
+        }
+        if name == "mr" {
+            return Ok(self.context.memory);
+        }
+        if let Some(value) = constant(name) {
+            return Ok(value);
+        }
+        if name == "inf" && self.context.allow_infinity {
+            return Ok(f64::INFINITY);
+        }
+        match self.context.variables.get(name) {
+            Some(&value) => Ok(value),
+            None => Err(CalcError::UnknownIdentifier(name.to_string())),
+        }
+    }
+}
+
+/// Formats `expr` as an indented tree, one node per line, as shown by the
+/// `ast` command.
+///
+/// Each operator or function call is followed by its operands, indented by
+/// two more spaces, so the structure shows which operators bind tighter.
+/// Negation is shown as `neg` and parentheses as `()`.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::parser::{format_ast, parse_to_ast};
+///
+/// let expr = parse_to_ast("2 + 3 * 4").unwrap();
+/// assert_eq!(format_ast(&expr), "+\n  2\n  *\n    3\n    4");
+/// ```
+pub fn format_ast(expr: &Expr) -> String {
+    let mut lines = Vec::new();
+    push_ast_lines(expr, 0, &mut lines);
+    lines.join("\n")
+}
+
+/// Adds the lines of [`format_ast`] for `expr`, indented by `level`.
+fn push_ast_lines(expr: &Expr, level: usize, lines: &mut Vec<String>) {
+    let indent = "  ".repeat(level);
+    let children: Vec<&Expr> = match expr {
+        Expr::Num(number) => {
+            lines.push(format!("{}{}", indent, number));
+            Vec::new()
+        }
+        Expr::Integer(integer) => {
+            lines.push(format!("{}{}", indent, integer));
+            Vec::new()
+        }
+        Expr::Ident(name) => {
+            lines.push(format!("{}{}", indent, name));
+            Vec::new()
+        }
+        Expr::Group(inner) => {
+            lines.push(format!("{}()", indent));
+            vec![inner]
+        }
+        Expr::UnaryOp { op, operand } => {
+            let label = match op {
+                UnaryOperator::Neg => "neg",
+                UnaryOperator::Factorial => "!",
+                UnaryOperator::Percent => "%",
+            };
+            lines.push(format!("{}{}", indent, label));
+            vec![operand]
+        }
+        Expr::BinaryOp { op, lhs, rhs } => {
+            lines.push(format!("{}{}", indent, op));
+            vec![lhs, rhs]
+        }
+        Expr::FuncCall { name, args } => {
+            lines.push(format!("{}{}()", indent, name));
+            args.iter().collect()
+        }
+    };
+    for child in children {
+        push_ast_lines(child, level + 1, lines);
+    }
+}
+
+/// Returns the names of all functions called in `expr`.
+pub(crate) fn called_functions(expr: &Expr) -> Vec<&str> {
+    match expr {
+        Expr::Num(_) | Expr::Integer(_) | Expr::Ident(_) => Vec::new(),
+        Expr::Group(inner) => called_functions(inner),
+        Expr::UnaryOp { operand, .. } => called_functions(operand),
+        Expr::BinaryOp { lhs, rhs, .. } => {
+            let mut names = called_functions(lhs);
+            names.extend(called_functions(rhs));
+            names
+        }
+        Expr::FuncCall { name, args } => {
+            let mut names = vec![name.as_str()];
+            names.extend(args.iter().flat_map(called_functions));
+            names
+        }
+    }
+}
+
+/// Whether `expr` ends in a postfix `%` outside parentheses, which makes it
+/// a percentage of the left operand of `+` or `-`.
+fn is_percentage(expr: &Expr) -> bool {
+    match expr {
+        Expr::UnaryOp {
+            op: UnaryOperator::Percent,
+            ..
+        } => true,
+        Expr::UnaryOp {
+            op: UnaryOperator::Neg,
+            operand,
+        } => is_percentage(operand),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{approx_eq, execute, AngleMode, Locale, NumberMode, DEFAULT_MAX_DEPTH};
+
+    fn parse(input: &str) -> Result<f64, CalcError> {
+        parse_expression(input, &Context::default())
+    }
+
+    fn parse_eu(input: &str) -> Result<f64, CalcError> {
+        let context = Context {
+            locale: Locale::Eu,
+            ..Context::default()
+        };
+        parse_expression(input, &context)
+    }
+
+    #[test]
+    fn test_parse_expression_scientific_notation() {
+        assert_eq!(parse("1e3 + 1"), Ok(1001.0));
+        assert_eq!(parse("2 * 1.5E2"), Ok(300.0));
+        assert_eq!(parse("1e3-1"), Ok(999.0));
+    }
+
+    #[test]
+    fn test_parse_expression_e_without_exponent_is_a_name() {
+        assert_eq!(parse("1e- 3"), Ok(std::f64::consts::E - 3.0));
+        assert_eq!(parse("2e-1"), Ok(0.2));
+    }
+
+    #[test]
+    fn test_parse_expression_decimal_comma() {
+        assert_eq!(parse_eu("5,5 + 1,5"), Ok(7.0));
+        assert_eq!(parse_eu("2.5 * 2"), Ok(5.0));
+        assert_eq!(parse_eu("log(8, 2)"), Ok(3.0));
+    }
+
+    #[test]
+    fn test_parse_expression_us_rejects_decimal_comma() {
+        assert!(matches!(
+            parse("5,5 + 1,5"),
+            Err(CalcError::UnexpectedToken { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_expression_parentheses() {
+        let result = parse("(2 + 3) * 4");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 20.0);
+    }
+
+    #[test]
+    fn test_parse_expression_nested_parentheses() {
+        let result = parse("((1 + 2) * (3 + 4))");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 21.0);
+    }
+
+    #[test]
+    fn test_parse_expression_deeply_nested() {
+        let result = parse("(((((2)))))");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 2.0);
+    }
+
+    #[test]
+    fn test_parse_expression_without_spaces() {
+        let result = parse("(2+3)*4");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 20.0);
+    }
+
+    #[test]
+    fn test_parse_expression_negative_literal() {
+        let result = parse("-5 + 3");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), -2.0);
+    }
+
+    #[test]
+    fn test_parse_expression_negation_after_operator() {
+        let result = parse("3 * -2");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), -6.0);
+    }
+
+    #[test]
+    fn test_parse_expression_negated_group() {
+        let result = parse("-(2 + 3)");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), -5.0);
+    }
+
+    #[test]
+    fn test_parse_expression_separated_minus() {
+        let result = parse("- 5 + 3");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), -2.0);
+    }
+
+    #[test]
+    fn test_parse_expression_subtracting_negative() {
+        let result = parse("2 - -3");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 5.0);
+    }
+
+    #[test]
+    fn test_parse_expression_negation_binds_looser_than_power() {
+        let result = parse("-2 ^ 2");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), -4.0);
+    }
+
+    #[test]
+    fn test_parse_expression_degrees_minutes_seconds() {
+        let context = Context {
+            angle_mode: AngleMode::Degrees,
+            ..Context::default()
+        };
+        assert_eq!(parse_expression("30d30m", &context), Ok(30.5));
+        let sine = parse_expression("sin(30d0m)", &context).unwrap();
+        assert!(approx_eq(sine, 0.5, 1e-12));
+        let sine = parse_expression("sin 30d30m", &context).unwrap();
+        assert!(approx_eq(sine, 30.5f64.to_radians().sin(), 1e-12));
+    }
+
+    #[test]
+    fn test_parse_expression_degrees_minutes_seconds_in_radian_mode() {
+        let context = Context::default();
+        let sine = parse_expression("sin(30d0m)", &context).unwrap();
+        assert!(approx_eq(sine, 0.5, 1e-12));
+        assert!(approx_eq(
+            parse_expression("90d0s", &context).unwrap(),
+            std::f64::consts::FRAC_PI_2,
+            1e-12
+        ));
+    }
+
+    #[test]
+    fn test_parse_expression_number_before_d_multiplies() {
+        let mut context = Context::default();
+        context.variables.insert("d".to_string(), 2.0);
+        assert_eq!(parse_expression("3d", &context), Ok(6.0));
+        assert_eq!(parse_expression("3 d", &context), Ok(6.0));
+    }
+
+    #[test]
+    fn test_parse_expression_power_is_right_associative() {
+        assert_eq!(parse("2 ^ 3 ^ 2"), Ok(512.0));
+        assert_ne!(parse("2 ^ 3 ^ 2"), parse("(2 ^ 3) ^ 2"));
+        assert_eq!(parse("(2 ^ 3) ^ 2"), Ok(64.0));
+        assert_eq!(
+            parse_to_ast("2 ^ 3 ^ 2"),
+            Ok(binary(
+                "^",
+                Expr::Num(2.0),
+                binary("^", Expr::Num(3.0), Expr::Num(2.0))
+            ))
+        );
+        // The other operators still group from the left.
+        assert_eq!(parse("100 / 10 / 5"), Ok(2.0));
+        assert_eq!(parse("2 * 3 - 4 - 1"), Ok(1.0));
+    }
+
+    #[test]
+    fn test_parse_expression_function_with_parentheses() {
+        let result = parse("sqrt(16)");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 4.0);
+    }
+
+    #[test]
+    fn test_parse_expression_function_without_parentheses() {
+        let result = parse("sqrt 16");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 4.0);
+    }
+
+    #[test]
+    fn test_parse_expression_function_in_expression() {
+        let result = parse("sqrt(9) * 2 + 1");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), 7.0);
+    }
+
+    #[test]
+    fn test_parse_expression_function_of_negative() {
+        let result = parse("sqrt -4");
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().to_string(), "sqrt of negative number");
+    }
+
+    #[test]
+    fn test_parse_expression_trig_functions() {
+        assert_eq!(parse("sin(0) + cos(0)").unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_parse_expression_constants() {
+        assert_eq!(parse("pi * 2"), Ok(std::f64::consts::TAU));
+        assert!(approx_eq(
+            parse("e ^ 2").unwrap(),
+            std::f64::consts::E.powf(2.0),
+            1e-10
+        ));
+        assert!(approx_eq(parse("cos(pi) + 1").unwrap(), 0.0, 1e-10));
+    }
+
+    #[test]
+    fn test_parse_expression_ans() {
+        let context = Context {
+            ans: 10.0,
+            ..Context::default()
+        };
+        assert_eq!(parse_expression("ans * 2", &context), Ok(20.0));
+        assert_eq!(parse("ans + 1"), Ok(1.0));
+    }
+
+    #[test]
+    fn test_parse_expression_unknown_identifier() {
+        let result = parse("pie * 2");
+        assert_eq!(result, Err(CalcError::UnknownIdentifier("pie".to_string())));
+        assert_eq!(result.unwrap_err().to_string(), "Unknown identifier: pie");
+        assert!(parse("foo").is_err());
+    }
+
+    #[test]
+    fn test_parse_value_comparisons() {
+        let context = Context::default();
+        assert_eq!(parse_value("2 + 3 > 4", &context), Ok(Value::Bool(true)));
+        assert_eq!(parse_value("2 * 3 < 6", &context), Ok(Value::Bool(false)));
+        assert_eq!(parse_value("1 << 2 == 4", &context), Ok(Value::Bool(true)));
+        assert_eq!(parse_value("5!=5", &context), Ok(Value::Bool(false)));
+        assert_eq!(parse_value("(1 + 1)", &context), Ok(Value::Number(2.0)));
+    }
+
+    #[test]
+    fn test_comparisons_are_not_chained() {
+        let context = Context::default();
+        assert_eq!(
+            parse_value("1 < 2 < 3", &context),
+            Err(CalcError::UnexpectedToken {
+                token: "operator '<'".to_string(),
+                position: Some(6),
+            })
+        );
+        assert!(parse("1 < 2").is_err());
+        assert_eq!(check_expression("1 / 0 == 1", &context), Ok(()));
+    }
+
+    #[test]
+    fn test_parse_expression_infinity() {
+        assert_eq!(
+            parse("inf + 1"),
+            Err(CalcError::UnknownIdentifier("inf".to_string()))
+        );
+
+        let context = Context {
+            allow_infinity: true,
+            ..Context::default()
+        };
+        assert_eq!(parse_expression("inf + 1", &context), Ok(f64::INFINITY));
+        assert_eq!(
+            parse_expression("-inf * 2", &context),
+            Ok(f64::NEG_INFINITY)
+        );
+        assert_eq!(parse_expression("1 / inf", &context), Ok(0.0));
+        assert_eq!(
+            parse_expression("inf - inf", &context),
+            Err(CalcError::Domain("inf - inf is undefined".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_expression_logarithms() {
+        assert!(approx_eq(parse("ln(e)").unwrap(), 1.0, 1e-10));
+        assert!(approx_eq(parse("log(1000)").unwrap(), 3.0, 1e-10));
+        assert!(approx_eq(parse("log(8, 2)").unwrap(), 3.0, 1e-10));
+        assert!(approx_eq(
+            parse("log(2 * 4, 1 + 1) + 1").unwrap(),
+            4.0,
+            1e-10
+        ));
+    }
+
+    #[test]
+    fn test_parse_expression_pow() {
+        assert_eq!(parse("pow(2, 10)"), Ok(1024.0));
+        assert_eq!(parse("pow(2, 3) + 1"), parse("2 ^ 3 + 1"));
+        assert!(matches!(parse("pow(-8, 1 / 3)"), Err(CalcError::Domain(_))));
+        assert_eq!(parse("pow(-8, 1 / 3)"), parse("(-8) ^ (1 / 3)"));
+    }
+
+    #[test]
+    fn test_parse_expression_min_max() {
+        assert_eq!(parse("max(3, 7)"), Ok(7.0));
+        assert_eq!(parse("min(-1, -5)"), Ok(-5.0));
+        assert_eq!(parse("min(2 + 2, 4) * 2"), Ok(8.0));
+    }
+
+    #[test]
+    fn test_parse_expression_rounding_functions() {
+        assert_eq!(parse("abs(-3)"), Ok(3.0));
+        assert_eq!(parse("floor(2.7) + ceil(2.1)"), Ok(5.0));
+        assert_eq!(parse("round 2.5"), Ok(3.0));
+    }
+
+    #[test]
+    fn test_parse_expression_ln_of_zero() {
+        assert!(matches!(parse("ln(0)"), Err(CalcError::Domain(_))));
+    }
+
+    #[test]
+    fn test_parse_expression_comma_outside_call() {
+        assert_eq!(
+            parse("(1, 2)"),
+            Err(CalcError::UnexpectedToken {
+                token: "','".to_string(),
+                position: Some(2)
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_expression_function_missing_argument() {
+        assert_eq!(parse("sqrt"), Err(CalcError::UnexpectedEnd));
+    }
+
+    #[test]
+    fn test_parse_expression_error_positions() {
+        let error = parse("(1 + 2) 3").unwrap_err();
+        assert_eq!(error.to_string(), "Unexpected number '3' at position 8");
+        let error = parse("1 + * 2").unwrap_err();
+        assert_eq!(error.to_string(), "Unexpected operator '*' at position 4");
+        let error = parse("2 * (3 4)").unwrap_err();
+        assert_eq!(error.position(), Some(7));
+        let error = parse("  1 + 0b12").unwrap_err();
+        assert_eq!(error.to_string(), "Invalid number '0b12' at position 6");
+    }
+
+    #[test]
+    fn test_check_expression_ignores_value_errors() {
+        let context = Context::default();
+        for input in [
+            "1 / 0",
+            "(1 / 0) + 2 * 3",
+            "sqrt(-1)",
+            "171! * 2",
+            "ln(0) / 0",
+        ] {
+            assert_eq!(check_expression(input, &context), Ok(()), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_check_expression_reports_syntax_errors() {
+        let context = Context::default();
+        assert_eq!(
+            check_expression("5 +", &context),
+            Err(CalcError::UnexpectedEnd)
+        );
+        assert_eq!(
+            check_expression("1 / 0 + (2", &context),
+            Err(CalcError::UnmatchedParenthesis)
+        );
+        assert_eq!(
+            check_expression("pie * 2", &context),
+            Err(CalcError::UnknownIdentifier("pie".to_string()))
+        );
+        assert!(matches!(
+            check_expression("min(1)", &context),
+            Err(CalcError::ArgumentCount { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_expression_factorial() {
+        assert_eq!(parse("0!"), Ok(1.0));
+        assert_eq!(parse("5!"), Ok(120.0));
+        assert_eq!(parse("3!!"), Ok(720.0));
+        assert_eq!(parse("2 ^ 3!"), Ok(64.0));
+        assert_eq!(parse("-3!"), Ok(-6.0));
+    }
+
+    #[test]
+    fn test_parse_expression_factorial_of_negative() {
+        assert!(matches!(parse("(-1)!"), Err(CalcError::Domain(_))));
+    }
+
+    #[test]
+    fn test_parse_expression_factorial_of_fraction() {
+        assert!(matches!(parse("2.5!"), Err(CalcError::Domain(_))));
+    }
+
+    #[test]
+    fn test_parse_expression_hex_and_binary() {
+        assert_eq!(parse("0xFF"), Ok(255.0));
+        assert_eq!(parse("0b1010"), Ok(10.0));
+        assert_eq!(parse("0xFF + 1"), Ok(256.0));
+        assert_eq!(parse("0b1010 * 2"), Ok(20.0));
+        assert_eq!(parse("-0x10"), Ok(-16.0));
+    }
+
+    #[test]
+    fn test_parse_expression_invalid_hex_digit() {
+        assert_eq!(
+            parse("0x1G + 1"),
+            Err(CalcError::ParseNumber {
+                token: "0x1G".to_string(),
+                position: Some(0)
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_expression_bitwise() {
+        assert_eq!(parse("12 & 10"), Ok(8.0));
+        assert_eq!(parse("12 | 3"), Ok(15.0));
+        assert_eq!(parse("6 xor 3"), Ok(5.0));
+        assert_eq!(parse("0xF0 | 0x0F & 0x3C"), Ok(252.0));
+        assert_eq!(parse("1 + 2 & 3"), Ok(3.0));
+    }
+
+    #[test]
+    fn test_parse_expression_shifts() {
+        assert_eq!(parse("1 << 4"), Ok(16.0));
+        assert_eq!(parse("256 >> 2"), Ok(64.0));
+        assert_eq!(parse("1 << 2 + 1"), Ok(8.0));
+        assert_eq!(parse("(1 << 4) | 1"), Ok(17.0));
+    }
+
+    #[test]
+    fn test_parse_expression_shift_overflow_guard() {
+        assert!(matches!(parse("1 << 64"), Err(CalcError::Domain(_))));
+        assert!(matches!(parse("1 >> -1"), Err(CalcError::Domain(_))));
+    }
+
+    #[test]
+    fn test_parse_expression_bitwise_rejects_fraction() {
+        assert!(matches!(parse("2.5 & 1"), Err(CalcError::Domain(_))));
+    }
+
+    #[test]
+    fn test_parse_expression_percentage_added() {
+        assert_eq!(parse("200 + 10%"), Ok(220.0));
+    }
+
+    #[test]
+    fn test_parse_expression_percentage_subtracted() {
+        assert_eq!(parse("200 - 10%"), Ok(180.0));
+        assert_eq!(parse("200 - 10% + 5"), Ok(185.0));
+    }
+
+    #[test]
+    fn test_parse_expression_bare_percentage() {
+        assert_eq!(parse("50%"), Ok(0.5));
+        assert_eq!(parse("200 * 10%"), Ok(20.0));
+        assert!(approx_eq(parse("200 + (10%)").unwrap(), 200.1, 1e-10));
+    }
+
+    #[test]
+    fn test_parse_expression_remainder_still_works() {
+        assert_eq!(parse("17 % 5"), Ok(2.0));
+        assert_eq!(parse("17 % (2 + 3)"), Ok(2.0));
+        assert_eq!(parse("17 % 5 + 1"), Ok(3.0));
+    }
+
+    #[test]
+    fn test_parse_expression_remainder_of_negative_operand() {
+        assert_eq!(parse("7 % -3"), Ok(1.0));
+        assert_eq!(parse("-7 % -3"), Ok(-1.0));
+    }
+
+    #[test]
+    fn test_parse_expression_missing_closing_parenthesis() {
+        let result = parse("(2 + 3");
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().to_string(), "Unmatched parenthesis");
+    }
+
+    #[test]
+    fn test_parse_expression_extra_closing_parenthesis() {
+        let result = parse("2 + 3)");
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().to_string(), "Unmatched parenthesis");
+    }
+
+    #[test]
+    fn test_parse_expression_empty_parentheses() {
+        let result = parse("()");
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().to_string(), "Empty parentheses");
+    }
+
+    #[test]
+    fn test_parse_expression_unexpected_character() {
+        assert_eq!(
+            parse("2 $ 3"),
+            Err(CalcError::UnexpectedToken {
+                token: "character '$'".to_string(),
+                position: Some(2)
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_expression_unexpected_end() {
+        assert_eq!(parse("2 +"), Err(CalcError::UnexpectedEnd));
+    }
+
+    #[test]
+    fn test_parse_expression_implicit_multiplication() {
+        assert_eq!(parse("2(3+4)"), Ok(14.0));
+        assert_eq!(parse("2pi"), Ok(std::f64::consts::TAU));
+        assert_eq!(parse("2e"), Ok(2.0 * std::f64::consts::E));
+        assert_eq!(parse("3 (2) + 1"), Ok(7.0));
+        assert_eq!(
+            parse_to_ast("2x"),
+            Ok(binary("*", Expr::Num(2.0), Expr::Ident("x".to_string())))
+        );
+    }
+
+    #[test]
+    fn test_parse_expression_no_implicit_multiplication_after_identifier() {
+        assert_eq!(
+            parse("pi2"),
+            Err(CalcError::UnknownIdentifier("pi2".to_string()))
+        );
+        assert!(matches!(
+            parse("(1 + 1)(2)"),
+            Err(CalcError::UnexpectedToken { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_to_ast_precedence() {
+        let expected = binary(
+            "+",
+            Expr::Num(2.0),
+            binary("*", Expr::Num(3.0), Expr::Num(4.0)),
+        );
+        assert_eq!(parse_to_ast("2 + 3 * 4"), Ok(expected));
+    }
+
+    #[test]
+    fn test_eval_ast() {
+        let expr = parse_to_ast("2 + 3 * 4").unwrap();
+        assert_eq!(eval_ast(&expr), Ok(14.0));
+    }
+
+    #[test]
+    fn test_format_ast() {
+        let format = |input| format_ast(&parse_to_ast(input).unwrap());
+        assert_eq!(format("2 + 3 * 4"), "+\n  2\n  *\n    3\n    4");
+        assert_eq!(
+            format("(2 + 3) * 4"),
+            "*\n  ()\n    +\n      2\n      3\n  4"
+        );
+        assert_eq!(format("-max(x, 3!)"), "neg\n  max()\n    x\n    !\n      3");
+        assert_eq!(format("2.5"), "2.5");
+    }
+
+    #[test]
+    fn test_parse_to_ast_function_call() {
+        let expected = Expr::FuncCall {
+            name: "max".to_string(),
+            args: vec![Expr::Num(1.0), unary(UnaryOperator::Neg, Expr::Num(2.0))],
+        };
+        assert_eq!(parse_to_ast("max(1, -2)"), Ok(expected));
+    }
+
+    /// Runs `test` on a thread with the stack of a main thread. Debug builds
+    /// use much more stack per level of nesting than release builds, more
+    /// than the smaller default stack of test threads allows at the default
+    /// depth limit.
+    fn with_main_thread_stack(test: impl FnOnce() + Send + 'static) {
+        std::thread::Builder::new()
+            .stack_size(8 << 20)
+            .spawn(test)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_deep_nesting_is_an_error() {
+        with_main_thread_stack(|| {
+            let nested = format!("{}1{}", "(".repeat(1000), ")".repeat(1000));
+            assert_eq!(
+                parse(&nested),
+                Err(CalcError::TooDeeplyNested(DEFAULT_MAX_DEPTH))
+            );
+            assert_eq!(
+                parse(&"-".repeat(1000)),
+                Err(CalcError::TooDeeplyNested(DEFAULT_MAX_DEPTH))
+            );
+            assert_eq!(
+                parse(&vec!["1"; 1000].join(" + ")),
+                Err(CalcError::TooDeeplyNested(DEFAULT_MAX_DEPTH))
+            );
+
+            let mut context = Context {
+                number_mode: NumberMode::Integer,
+                ..Context::default()
+            };
+            assert_eq!(
+                execute(&nested, &mut context),
+                Err(CalcError::TooDeeplyNested(DEFAULT_MAX_DEPTH))
+            );
+        });
+    }
+
+    #[test]
+    fn test_nesting_up_to_the_limit() {
+        with_main_thread_stack(|| {
+            let nested = format!(
+                "{}1{}",
+                "(".repeat(DEFAULT_MAX_DEPTH),
+                ")".repeat(DEFAULT_MAX_DEPTH)
+            );
+            assert_eq!(parse(&nested), Ok(1.0));
+            assert_eq!(parse(&vec!["1"; DEFAULT_MAX_DEPTH].join(" + ")), Ok(256.0));
+        });
+
+        let context = Context {
+            max_depth: 2,
+            ..Context::default()
+        };
+        assert_eq!(parse_expression("((1))", &context), Ok(1.0));
+        assert_eq!(
+            parse_expression("(((1)))", &context),
+            Err(CalcError::TooDeeplyNested(2))
+        );
+        assert_eq!(
+            CalcError::TooDeeplyNested(2).to_string(),
+            "Expression too deeply nested: the limit is 2 levels"
+        );
+    }
+
+    #[test]
+    fn test_parse_to_ast_user_function_call() {
+        let expected = Expr::FuncCall {
+            name: "double".to_string(),
+            args: vec![Expr::Num(21.0)],
+        };
+        assert_eq!(parse_to_ast("double(21)"), Ok(expected));
+    }
+
+    #[test]
+    fn test_eval_ast_with_variables() {
+        let expr = parse_to_ast("x * 2").unwrap();
+        assert_eq!(
+            eval_ast(&expr),
+            Err(CalcError::UnknownIdentifier("x".to_string()))
+        );
+
+        let mut context = Context::default();
+        context.variables.insert("x".to_string(), 21.0);
+        assert_eq!(eval_ast_with(&expr, &context), Ok(42.0));
+    }
+
+    #[test]
+    fn test_evaluation_steps() {
+        let (value, steps) = evaluation_steps("2 + 3 * 4 - sqrt(16)", &Context::default()).unwrap();
+        assert_eq!(value, Value::Number(10.0));
+        assert_eq!(
+            steps,
+            ["3 * 4 = 12", "2 + 12 = 14", "sqrt(16) = 4", "14 - 4 = 10"]
+        );
+    }
+
+    #[test]
+    fn test_evaluation_steps_percentage_and_comparison() {
+        let context = Context::default();
+        let (_, steps) = evaluation_steps("200 + 10%", &context).unwrap();
+        assert_eq!(steps, ["10% of 200 = 20", "200 + 20 = 220"]);
+
+        let (value, steps) = evaluation_steps("3! > 5", &context).unwrap();
+        assert_eq!(value, Value::Bool(true));
+        assert_eq!(steps, ["3! = 6", "6 > 5 is true"]);
+    }
+
+    #[test]
+    fn test_evaluation_steps_in_user_function() {
+        let mut context = Context::default();
+        crate::define_function("f(x) = x * 2 + 1", &mut context).unwrap();
+        let (value, steps) = evaluation_steps("f(3) - 1", &context).unwrap();
+        assert_eq!(value, Value::Number(6.0));
+        assert_eq!(steps, ["3 * 2 = 6", "6 + 1 = 7", "f(3) = 7", "7 - 1 = 6"]);
+    }
+}
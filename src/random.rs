@@ -0,0 +1,111 @@
+use std::cell::Cell;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A small xorshift64* pseudo-random number generator used by `rand`.
+///
+/// A generator created with [`Rng::with_seed`] always produces the same
+/// sequence, which makes scripts using `rand` reproducible. The default
+/// generator is seeded from the clock the first time a number is drawn.
+///
+/// The state is kept in a [`Cell`] so that numbers can be drawn through the
+/// shared [`Context`](crate::Context) an expression is evaluated with.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::Rng;
+///
+/// let first = Rng::with_seed(42);
+/// let second = Rng::with_seed(42);
+/// assert_eq!(first.next_f64(), second.next_f64());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rng {
+    /// The xorshift state; `0` until the generator has been seeded
+    state: Cell<u64>,
+}
+
+impl Rng {
+    /// Creates a generator whose sequence is determined by `seed`.
+    pub fn with_seed(seed: u64) -> Rng {
+        Rng {
+            state: Cell::new(scramble(seed)),
+        }
+    }
+
+    /// Returns the next number in `[0, 1)`.
+    pub fn next_f64(&self) -> f64 {
+        // The top 53 bits fill the mantissa of an f64 exactly.
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Advances the state and returns the next 64 random bits.
+    fn next_u64(&self) -> u64 {
+        let mut x = self.state.get();
+        if x == 0 {
+            x = scramble(clock_seed());
+        }
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state.set(x);
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+/// Spreads the bits of `seed` with the SplitMix64 finalizer, so that similar
+/// seeds start far apart. Never returns `0`, which xorshift cannot leave.
+fn scramble(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    if z == 0 {
+        0x9e37_79b9_7f4a_7c15
+    } else {
+        z
+    }
+}
+
+/// A seed taken from the current time, for generators without a seed.
+fn clock_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+    nanos ^ u64::from(std::process::id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_gives_same_sequence() {
+        let first = Rng::with_seed(7);
+        let second = Rng::with_seed(7);
+        let first: Vec<f64> = (0..100).map(|_| first.next_f64()).collect();
+        let second: Vec<f64> = (0..100).map(|_| second.next_f64()).collect();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_different_seeds_give_different_sequences() {
+        assert_ne!(Rng::with_seed(1).next_f64(), Rng::with_seed(2).next_f64());
+    }
+
+    #[test]
+    fn test_numbers_are_in_unit_interval() {
+        let rng = Rng::with_seed(0);
+        for _ in 0..10_000 {
+            let number = rng.next_f64();
+            assert!((0.0..1.0).contains(&number), "{} is out of range", number);
+        }
+    }
+
+    #[test]
+    fn test_default_is_seeded_on_first_use() {
+        let rng = Rng::default();
+        let number = rng.next_f64();
+        assert!((0.0..1.0).contains(&number));
+        assert_ne!(rng, Rng::default());
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{
+    calculate, check_input_length, constant, finite_literal, parse_number, CalcError, Context,
+    OPERATORS,
+};
+
+/// Evaluates an expression in Reverse Polish Notation, such as `2 3 4 * +`.
+///
+/// Tokens are separated by whitespace. Numbers, constants, `ans` and
+/// variables are pushed onto a stack; each of the [`OPERATORS`] pops two
+/// values, applies [`calculate`] and pushes the result. The expression must
+/// leave exactly one value on the stack.
+///
+/// # Arguments
+/// * `input` - The RPN expression
+/// * `context` - Supplies `ans` and the variables
+///
+/// # Returns
+/// The single value left on the stack.
+///
+/// # Errors
+/// * [`CalcError::InvalidInput`] if `input` is blank
+/// * [`CalcError::InputTooLong`] if `input` is longer than
+///   [`Context::max_input_len`]
+/// * [`CalcError::InsufficientOperands`] if an operator finds fewer than two
+///   values on the stack, as in `5 +`
+/// * [`CalcError::TooManyOperands`] if more than one value is left, as in
+///   `1 2 3 +`
+/// * [`CalcError::UnknownIdentifier`] for a name that isn't a constant or
+///   variable, and [`CalcError::ParseNumber`] for a malformed number
+/// * Any error from [`calculate`], such as [`CalcError::DivisionByZero`]
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::{evaluate_rpn, Context};
+///
+/// let context = Context::default();
+/// assert_eq!(evaluate_rpn("5 5 +", &context), Ok(10.0));
+/// assert_eq!(evaluate_rpn("2 3 4 * +", &context), Ok(14.0));
+/// ```
+pub fn evaluate_rpn(input: &str, context: &Context) -> Result<f64, CalcError> {
+    check_input_length(input, context)?;
+    let mut stack = Vec::new();
+    for token in input.split_whitespace() {
+        if OPERATORS.contains(&token) {
+            let (Some(rhs), Some(lhs)) = (stack.pop(), stack.pop()) else {
+                return Err(CalcError::InsufficientOperands(token.to_string()));
+            };
+            stack.push(calculate(lhs, rhs, token)?);
+        } else if token.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            let value = match token {
+                "ans" => Some(context.ans),
+                "mr" => Some(context.memory),
+                "inf" if context.allow_infinity => Some(f64::INFINITY),
+                _ => constant(token).or_else(|| context.variables.get(token).copied()),
+            };
+            stack.push(value.ok_or_else(|| CalcError::UnknownIdentifier(token.to_string()))?);
+        } else {
+            let position = token.as_ptr() as usize - input.as_ptr() as usize;
+            let number = parse_number(token).map_err(|e| e.at(position))?;
+            stack.push(finite_literal(number, context.allow_infinity)?);
+        }
+    }
+
+    match stack[..] {
+        [] => Err(CalcError::InvalidInput),
+        [value] => Ok(value),
+        _ => Err(CalcError::TooManyOperands(stack.len())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rpn(input: &str) -> Result<f64, CalcError> {
+        evaluate_rpn(input, &Context::default())
+    }
+
+    #[test]
+    fn test_simple_expression() {
+        assert_eq!(rpn("5 5 +"), Ok(10.0));
+        assert_eq!(rpn("10 4 -"), Ok(6.0));
+        assert_eq!(rpn("42"), Ok(42.0));
+    }
+
+    #[test]
+    fn test_nested_expression() {
+        assert_eq!(rpn("2 3 4 * +"), Ok(14.0));
+        assert_eq!(rpn("2 3 + 4 *"), Ok(20.0));
+        assert_eq!(rpn("5 1 2 + 4 * + 3 -"), Ok(14.0));
+    }
+
+    #[test]
+    fn test_operands_and_identifiers() {
+        let mut context = Context {
+            ans: 3.0,
+            ..Context::default()
+        };
+        context.variables.insert("x".to_string(), 4.0);
+        assert_eq!(evaluate_rpn("ans x ^", &context), Ok(81.0));
+        assert_eq!(evaluate_rpn("-1.5 0x10 *", &context), Ok(-24.0));
+        assert_eq!(
+            evaluate_rpn("y 1 +", &context),
+            Err(CalcError::UnknownIdentifier("y".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_invalid_number_position() {
+        assert_eq!(rpn("1 2x +").unwrap_err().position(), Some(2));
+    }
+
+    #[test]
+    fn test_insufficient_operands() {
+        assert_eq!(
+            rpn("5 +"),
+            Err(CalcError::InsufficientOperands("+".to_string()))
+        );
+        assert_eq!(
+            rpn("*"),
+            Err(CalcError::InsufficientOperands("*".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_too_many_operands() {
+        assert_eq!(rpn("1 2 3 +"), Err(CalcError::TooManyOperands(2)));
+    }
+
+    #[test]
+    fn test_errors_from_calculate() {
+        assert_eq!(rpn("1 0 /"), Err(CalcError::DivisionByZero));
+        assert_eq!(rpn(""), Err(CalcError::InvalidInput));
+    }
+}
@@ -0,0 +1,1738 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::time::Instant;
+
+use crate::parser::{format_ast, parse_ast};
+use crate::tokenizer::{tokenize_with_positions, Token};
+use crate::{
+    aggregate, calculate, check_input_length, convert, define_function, evaluate_rpn,
+    evaluate_with, execute, explain, finite_literal, help_text, parse_number, AngleMode, CalcError,
+    Calculation, Context, Exact, History, Locale, NumberMode, Outcome, OutputBase, Overflow,
+    RoundMode, Settings, DEFAULT_CURRENCY_SYMBOL, INT_WIDTHS, LIST_COMMANDS, OPERATORS,
+};
+
+/// What the calculator should show in response to a line of input.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Response {
+    /// An expression was evaluated
+    Value(Calculation),
+    /// An expression was evaluated exactly in a number mode such as `mode int`
+    Exact { expression: String, value: Exact },
+    /// A comparison such as `5 > 3` was evaluated
+    Bool { expression: String, value: bool },
+    /// A variable was assigned `value`
+    Assignment { name: String, value: f64 },
+    /// Output of a command, such as the history listing
+    Output(String),
+    /// Confirmation of a setting change, only worth showing interactively
+    Info(String),
+    /// The input was blank, so there is nothing to show
+    Empty,
+}
+
+/// How a session reads lines that aren't commands.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InputMode {
+    /// Every line is an expression or assignment (the default)
+    #[default]
+    Standard,
+    /// Like an adding machine, every line such as `+5` or `* 2` applies an
+    /// operator to a running total
+    Tape,
+    /// Every line is in Reverse Polish Notation, such as `2 3 4 * +`
+    Rpn,
+}
+
+impl FromStr for InputMode {
+    type Err = CalcError;
+
+    /// Parses the argument of the `mode` command: `std`, `tape` or `rpn`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "std" => Ok(InputMode::Standard),
+            "tape" => Ok(InputMode::Tape),
+            "rpn" => Ok(InputMode::Rpn),
+            _ => Err(CalcError::UnknownIdentifier(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for InputMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputMode::Standard => write!(f, "standard"),
+            InputMode::Tape => write!(f, "tape"),
+            InputMode::Rpn => write!(f, "RPN"),
+        }
+    }
+}
+
+/// State of an interactive calculator session.
+///
+/// A session owns the evaluation [`Context`] and the [`History`], and
+/// understands the REPL commands on top of plain expressions:
+/// * `mode deg` / `mode rad` - switch the angle unit
+/// * `mode int` / `mode frac` / `mode complex` / `mode real` - evaluate with
+///   exact integers, exact fractions or complex numbers where possible, or
+///   always with floating point
+/// * `intwidth 8|16|32|64` / `intwidth off` - emulate unsigned integers of that
+///   many bits in integer mode, or lift the limit; negative integers are then
+///   shown in two's complement at that width in hexadecimal and binary
+/// * `overflow wrap|saturate|error` - what integer mode does with a result
+///   outside the integer width
+/// * `help` - list the supported operators, functions and commands
+/// * `explain NAME` - describe what an operator, function or constant does,
+///   e.g. `explain /` or `explain sqrt`
+/// * `tokens EXPRESSION` - show the tokens an expression is split into, for
+///   debugging how it is read
+/// * `ast EXPRESSION` - show the tree an expression is parsed into, with the
+///   operands of each operator indented below it
+/// * `history` - list previous calculations
+/// * `search TERM` - list the previous calculations containing `TERM`,
+///   ignoring case
+/// * `last_error` - show the most recent error again
+/// * `M+` / `M-` - add `ans` to or subtract it from the memory register,
+///   `MR` - show the register (also available as `mr` in expressions) and
+///   `MC` - clear it
+/// * `!N` - re-evaluate history entry `N`
+/// * `define name(x) = expression` - define a function of one argument, as
+///   in `define double(x) = x * 2`
+/// * `undo` - restore `ans` and the variables to what they were before the
+///   last line that changed them
+/// * `precision N` / `precision full` - show results with `N` decimal places
+///   or with full precision
+/// * `round_mode nearest|up|down|truncate` - how results are rounded to the
+///   precision
+/// * `sep on` / `sep off` - toggle thousands separators in results
+/// * `sci on` / `sci off` - toggle scientific notation in results
+/// * `eng on` / `eng off` - toggle engineering notation in results, with an
+///   exponent that is a multiple of 3
+/// * `base dec` / `base hex` / `base bin` - show integer results in that base
+/// * `currency on` / `currency off` / `currency €` - show results as amounts
+///   of money such as `$1,234.50`, with `$` or the given symbol
+/// * `locale us` / `locale eu` - read `.` or also `,` as the decimal point
+/// * `aliases on` / `aliases off` - read `x`, `×` and `÷` as `*` and `/` or not
+/// * `prompt "calc> "` - change the prompt shown before each line; quotes keep
+///   leading and trailing spaces, and `prompt ""` removes it
+/// * `convert AMOUNT FROM TO` - convert an amount between units, e.g.
+///   `convert 100 km mi`
+/// * `time EXPRESSION` - evaluate an expression and show how long it took
+/// * `repeat N EXPRESSION` - evaluate an expression `N` times, showing each
+///   result and the total time
+/// * `sum 1 2 3` / `mean 1 2 3` / `median 1 2 3` / `stddev 1 2 3` - add up,
+///   average or take the median or population standard deviation of a list of
+///   numbers
+/// * `mode tape` / `mode rpn` / `mode std` - switch between a running total,
+///   Reverse Polish Notation and plain expressions; in tape mode `total` shows
+///   the total and `reset` zeroes it
+/// * `mode sci` / `mode finite` - accept or reject `inf` as a value
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::{Calculation, Response, Session};
+///
+/// let mut session = Session::default();
+/// session.handle("2 + 3").unwrap();
+/// let response = session.handle("!1").unwrap();
+/// assert_eq!(
+///     response,
+///     Response::Value(Calculation::new("2 + 3", 5.0))
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    /// Angle mode, `ans` and variables
+    pub context: Context,
+    /// Successful calculations, oldest first
+    pub history: History,
+    /// How results are displayed
+    pub settings: Settings,
+    /// How lines that aren't commands are read
+    pub input_mode: InputMode,
+    /// Running total of [`InputMode::Tape`]
+    pub total: f64,
+    /// The most recent error, shown again by `last_error`
+    pub last_error: Option<CalcError>,
+    /// Earlier values of `ans` and the variables, most recent last, restored
+    /// by `undo`. Only the 50 most recent are kept.
+    pub undo_stack: Vec<(f64, HashMap<String, f64>)>,
+    /// Shown before each line of interactive input, [`DEFAULT_PROMPT`] unless
+    /// changed with `prompt`
+    pub prompt: String,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session {
+            context: Context::default(),
+            history: History::default(),
+            settings: Settings::default(),
+            input_mode: InputMode::default(),
+            total: 0.0,
+            last_error: None,
+            undo_stack: Vec::new(),
+            prompt: DEFAULT_PROMPT.to_string(),
+        }
+    }
+}
+
+impl Session {
+    /// Handles one line of input, which is either a command or something for
+    /// [`execute`].
+    ///
+    /// # Errors
+    /// * [`CalcError::InvalidCommand`] if a command is malformed, such as an
+    ///   unknown mode or a missing history entry
+    /// * Any error from [`execute`]
+    ///
+    /// The error is also kept in [`Session::last_error`], and if the line
+    /// changed `ans` or a variable, the previous state is kept for `undo`.
+    pub fn handle(&mut self, input: &str) -> Result<Response, CalcError> {
+        let input = input.trim();
+        if input == "undo" {
+            return Ok(self.undo());
+        }
+        let before = (self.context.ans, self.context.variables.clone());
+        let response = self.dispatch(input);
+        if let Err(e) = &response {
+            self.last_error = Some(e.clone());
+        }
+        if self.context.ans.to_bits() != before.0.to_bits() || self.context.variables != before.1 {
+            if self.undo_stack.len() == MAX_UNDO {
+                self.undo_stack.remove(0);
+            }
+            self.undo_stack.push(before);
+        }
+        response
+    }
+
+    /// Restores the state before the most recent change, as done by `undo`,
+    /// and describes what was restored.
+    fn undo(&mut self) -> Response {
+        let Some((ans, variables)) = self.undo_stack.pop() else {
+            return Response::Output("Nothing to undo".to_string());
+        };
+        let mut names: Vec<&String> = variables
+            .keys()
+            .chain(self.context.variables.keys())
+            .filter(|name| variables.get(*name) != self.context.variables.get(*name))
+            .collect();
+        names.sort();
+        names.dedup();
+        let mut changes: Vec<String> = names
+            .into_iter()
+            .map(|name| match variables.get(name) {
+                Some(value) => format!("{} = {}", name, self.format(*value)),
+                None => format!("{} removed", name),
+            })
+            .collect();
+        if ans.to_bits() != self.context.ans.to_bits() {
+            changes.push(format!("ans = {}", self.format(ans)));
+        }
+
+        let response = Response::Output(format!("Undone: {}", changes.join(", ")));
+        self.context.set_ans(ans);
+        self.context.variables = variables;
+        response
+    }
+
+    /// Runs the command or calculation in a trimmed line of input.
+    fn dispatch(&mut self, input: &str) -> Result<Response, CalcError> {
+        if input.is_empty() {
+            return Ok(Response::Empty);
+        }
+        check_input_length(input, &self.context)?;
+        if input == "last_error" {
+            return Ok(Response::Output(match &self.last_error {
+                Some(e) => format!("Error: {}", e),
+                None => "No errors yet".to_string(),
+            }));
+        }
+        if let Some(response) = self.memory_command(input) {
+            return Ok(response);
+        }
+        if input == "help" {
+            return Ok(Response::Output(help_text()));
+        }
+        if let Some(definition) = input.strip_prefix("define ") {
+            let name = define_function(definition, &mut self.context)
+                .map_err(|e| e.shifted(input.len() - definition.len()))?;
+            return Ok(Response::Info(format!("Function '{}' defined", name)));
+        }
+        if let Some(expression) = input.strip_prefix("tokens ") {
+            let tokens: Vec<Token> = tokenize_with_positions(
+                expression,
+                self.context.locale,
+                self.context.operator_aliases,
+                self.context.angle_mode,
+            )
+            .map_err(|e| e.shifted(input.len() - expression.len()))?
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect();
+            return Ok(Response::Output(format!("{:?}", tokens)));
+        }
+        if let Some(expression) = input.strip_prefix("ast ") {
+            let expr = parse_ast(expression, &self.context)
+                .map_err(|e| e.shifted(input.len() - expression.len()))?;
+            return Ok(Response::Output(format_ast(&expr)));
+        }
+        if let Some(name) = input.strip_prefix("explain ") {
+            let name = name.trim();
+            return Ok(Response::Output(match explain(name) {
+                Some(explanation) => explanation.to_string(),
+                None => format!("No explanation for {}", name),
+            }));
+        }
+        if input == "history" {
+            if self.history.entries().is_empty() {
+                return Ok(Response::Output("No history yet".to_string()));
+            }
+            return Ok(Response::Output(self.history.to_string()));
+        }
+        if let Some(term) = input.strip_prefix("search ") {
+            let matches = self.history.search(term.trim());
+            if matches.is_empty() {
+                return Ok(Response::Output("No results".to_string()));
+            }
+            let lines: Vec<String> = matches
+                .iter()
+                .map(|(number, entry)| format!("{}: {}", number, entry))
+                .collect();
+            return Ok(Response::Output(lines.join("\n")));
+        }
+        if let Some(mode) = input.strip_prefix("mode ") {
+            return self.set_mode(mode.trim());
+        }
+        if let Some(precision) = input.strip_prefix("precision ") {
+            self.settings.precision = parse_precision(precision)?;
+            return Ok(Response::Info(match self.settings.precision {
+                Some(precision) => format!("Precision set to {} decimal places", precision),
+                None => "Precision set to full".to_string(),
+            }));
+        }
+        if let Some(round_mode) = input.strip_prefix("round_mode ") {
+            let round_mode: RoundMode = round_mode.trim().parse()?;
+            self.settings.round_mode = round_mode;
+            return Ok(Response::Info(format!(
+                "Rounding mode set to {}",
+                round_mode
+            )));
+        }
+        if let Some(separators) = input.strip_prefix("sep ") {
+            self.settings.separators = parse_switch(separators)?;
+            return Ok(Response::Info(format!(
+                "Thousands separators {}",
+                if self.settings.separators {
+                    "on"
+                } else {
+                    "off"
+                }
+            )));
+        }
+        if let Some(scientific) = input.strip_prefix("sci ") {
+            self.settings.scientific = parse_switch(scientific)?;
+            return Ok(Response::Info(format!(
+                "Scientific notation {}",
+                if self.settings.scientific {
+                    "on"
+                } else {
+                    "off"
+                }
+            )));
+        }
+        if let Some(engineering) = input.strip_prefix("eng ") {
+            self.settings.engineering = parse_switch(engineering)?;
+            return Ok(Response::Info(format!(
+                "Engineering notation {}",
+                if self.settings.engineering {
+                    "on"
+                } else {
+                    "off"
+                }
+            )));
+        }
+        if let Some(aliases) = input.strip_prefix("aliases ") {
+            self.context.operator_aliases = parse_switch(aliases)?;
+            return Ok(Response::Info(format!(
+                "Operator aliases {}",
+                if self.context.operator_aliases {
+                    "on"
+                } else {
+                    "off"
+                }
+            )));
+        }
+        if let Some(currency) = input.strip_prefix("currency ") {
+            self.settings.currency = parse_currency(currency);
+            return Ok(Response::Info(match &self.settings.currency {
+                Some(symbol) => format!("Currency format on ({})", symbol),
+                None => "Currency format off".to_string(),
+            }));
+        }
+        if let Some(base) = input.strip_prefix("base ") {
+            let base: OutputBase = base.trim().parse()?;
+            self.settings.base = base;
+            return Ok(Response::Info(format!("Output base set to {}", base)));
+        }
+        if let Some(locale) = input.strip_prefix("locale ") {
+            let locale: Locale = locale.trim().parse()?;
+            self.context.locale = locale;
+            return Ok(Response::Info(format!("Locale set to {}", locale)));
+        }
+        if let Some(width) = input.strip_prefix("intwidth ") {
+            self.context.int_width = parse_int_width(width)?;
+            return Ok(Response::Info(match self.context.int_width {
+                Some(width) => format!("Integer width set to {} bits", width),
+                None => "Integer width off".to_string(),
+            }));
+        }
+        if let Some(overflow) = input.strip_prefix("overflow ") {
+            let overflow: Overflow = overflow.trim().parse()?;
+            self.context.overflow = overflow;
+            return Ok(Response::Info(format!("Overflow set to {}", overflow)));
+        }
+        if let Some(prompt) = input.strip_prefix("prompt ") {
+            self.prompt = parse_prompt(prompt);
+            return Ok(Response::Info(format!("Prompt set to \"{}\"", self.prompt)));
+        }
+        if let Some(arguments) = input.strip_prefix("convert ") {
+            return self.convert(input, arguments);
+        }
+        if let Some(expression) = input.strip_prefix("time ") {
+            return self.time(expression.trim());
+        }
+        if let Some(arguments) = input.strip_prefix("repeat ") {
+            return self.repeat(arguments);
+        }
+        let command = input.split_whitespace().next().unwrap_or_default();
+        if LIST_COMMANDS.contains(&command) {
+            return self.list_command(input, command);
+        }
+        if let Some(number) = input
+            .strip_prefix('!')
+            .filter(|rest| !rest.starts_with('='))
+        {
+            let expression = number
+                .trim()
+                .parse()
+                .ok()
+                .and_then(|number| self.history.expression(number))
+                .ok_or_else(|| {
+                    CalcError::InvalidCommand(format!("No history entry '{}'", number.trim()))
+                })?
+                .to_string();
+            return self.dispatch(&expression);
+        }
+        match self.input_mode {
+            InputMode::Standard => self.calculate(input),
+            InputMode::Tape => self.tape(input),
+            InputMode::Rpn => {
+                let calculation = Calculation::new(input, evaluate_rpn(input, &self.context)?);
+                self.context.set_ans(calculation.result);
+                self.history.record(&calculation);
+                Ok(Response::Value(calculation))
+            }
+        }
+    }
+
+    /// Switches the angle unit or the number mode, as done by `mode`.
+    fn set_mode(&mut self, mode: &str) -> Result<Response, CalcError> {
+        if let Ok(angle_mode) = mode.parse::<AngleMode>() {
+            self.context.angle_mode = angle_mode;
+            return Ok(Response::Info(format!("Angle mode set to {}", angle_mode)));
+        }
+        if let Ok(input_mode) = mode.parse::<InputMode>() {
+            self.input_mode = input_mode;
+            return Ok(Response::Info(format!("Input mode set to {}", input_mode)));
+        }
+        if let Some(allow_infinity) = match mode {
+            "sci" => Some(true),
+            "finite" => Some(false),
+            _ => None,
+        } {
+            self.context.allow_infinity = allow_infinity;
+            return Ok(Response::Info(if allow_infinity {
+                "Infinity is allowed".to_string()
+            } else {
+                "Infinity is not allowed".to_string()
+            }));
+        }
+        if let Ok(number_mode) = mode.parse::<NumberMode>() {
+            self.context.number_mode = number_mode;
+            return Ok(Response::Info(format!(
+                "Number mode set to {}",
+                number_mode
+            )));
+        }
+        Err(CalcError::InvalidCommand(format!(
+            "Unknown mode '{}'. Use deg, rad, real, int, frac, complex, std, tape, rpn, sci or finite",
+            mode
+        )))
+    }
+
+    /// Runs `input` if it is one of the memory commands `M+`, `M-`, `MR` and
+    /// `MC`, in any case.
+    fn memory_command(&mut self, input: &str) -> Option<Response> {
+        let memory = &mut self.context.memory;
+        match input.to_ascii_uppercase().as_str() {
+            "M+" => *memory += self.context.ans,
+            "M-" => *memory -= self.context.ans,
+            "MC" => *memory = 0.0,
+            "MR" => return Some(Response::Output(self.format(self.context.memory))),
+            _ => return None,
+        }
+        Some(Response::Info(format!(
+            "Memory: {}",
+            self.format(self.context.memory)
+        )))
+    }
+
+    /// Writes [`Session::prompt`] to `out` and flushes it, so the prompt
+    /// shows up before the input is read.
+    ///
+    /// # Errors
+    /// Any error from writing to `out`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rust_calculator_cli::Session;
+    ///
+    /// let mut out = Vec::new();
+    /// Session::default().write_prompt(&mut out).unwrap();
+    /// assert_eq!(out, b"> ");
+    /// ```
+    pub fn write_prompt(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "{}", self.prompt)?;
+        out.flush()
+    }
+
+    /// Formats a result with [`Session::settings`], showing negative integers
+    /// in two's complement at the width set with `intwidth`.
+    pub fn format(&self, value: f64) -> String {
+        self.settings
+            .format_with_width(value, self.context.int_width)
+    }
+
+    /// Formats an exact result like [`Session::format`].
+    pub fn format_exact(&self, value: &Exact) -> String {
+        self.settings
+            .format_exact_with_width(value, self.context.int_width)
+    }
+
+    /// Forgets `ans`, the memory register, all variables and defined
+    /// functions, the tape total, the last error, the history and what `undo`
+    /// could restore, as done by `clear all`.
+    ///
+    /// Settings such as the angle mode and output precision are kept.
+    pub fn reset(&mut self) {
+        self.context.set_ans(0.0);
+        self.context.memory = 0.0;
+        self.total = 0.0;
+        self.last_error = None;
+        self.context.variables.clear();
+        self.context.functions.clear();
+        self.history.clear();
+        self.undo_stack.clear();
+    }
+
+    /// Converts between units, as done by `convert AMOUNT FROM TO`.
+    ///
+    /// The amount may be any expression, such as `ans` or `2 * 50`. The result
+    /// becomes the new `ans` and is recorded in the history like a calculation.
+    fn convert(&mut self, input: &str, arguments: &str) -> Result<Response, CalcError> {
+        let mut words = arguments.rsplitn(3, char::is_whitespace);
+        let (to, from, amount) = match (words.next(), words.next(), words.next()) {
+            (Some(to), Some(from), Some(amount)) if !amount.trim().is_empty() => (to, from, amount),
+            _ => {
+                return Err(CalcError::InvalidCommand(
+                    "Usage: convert AMOUNT FROM TO, e.g. convert 100 km mi".to_string(),
+                ))
+            }
+        };
+        let amount = evaluate_with(amount, &self.context)?;
+        let calculation = Calculation::new(input, convert(amount, from, to)?);
+        self.context.set_ans(calculation.result);
+        self.history.record(&calculation);
+        Ok(Response::Value(calculation))
+    }
+
+    /// Evaluates `expression` like a plain calculation and reports the result
+    /// together with the time the evaluation took, as done by `time`.
+    fn time(&mut self, expression: &str) -> Result<Response, CalcError> {
+        let start = Instant::now();
+        let response = self.calculate(expression)?;
+        let elapsed = start.elapsed();
+        Ok(Response::Output(format!(
+            "{}\nTime: {:?}",
+            self.result_line(&response),
+            elapsed
+        )))
+    }
+
+    /// Evaluates an expression the given number of times, as done by
+    /// `repeat N EXPRESSION`, listing every result and the total time.
+    ///
+    /// Each evaluation is a calculation of its own, so `repeat 3 ans + 1`
+    /// counts up from `ans`. The count must be a whole number from 1 to
+    /// [`MAX_REPEAT`]; the first error stops the repetition.
+    fn repeat(&mut self, arguments: &str) -> Result<Response, CalcError> {
+        let (count, expression) = arguments
+            .trim()
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| {
+                CalcError::InvalidCommand(
+                    "Usage: repeat N EXPRESSION, e.g. repeat 3 2 + 2".to_string(),
+                )
+            })?;
+        let count = count
+            .parse::<usize>()
+            .ok()
+            .filter(|count| (1..=MAX_REPEAT).contains(count))
+            .ok_or_else(|| {
+                CalcError::InvalidCommand(format!(
+                    "Invalid repeat count '{}'. Use a whole number from 1 to {}",
+                    count, MAX_REPEAT
+                ))
+            })?;
+
+        let start = Instant::now();
+        let mut lines = Vec::with_capacity(count + 1);
+        for _ in 0..count {
+            let response = self.calculate(expression.trim())?;
+            lines.push(self.result_line(&response));
+        }
+        lines.push(format!(
+            "Time: {:?} for {} evaluation{}",
+            start.elapsed(),
+            count,
+            if count == 1 { "" } else { "s" }
+        ));
+        Ok(Response::Output(lines.join("\n")))
+    }
+
+    /// Describes the response of [`Session::calculate`] as `input = result`.
+    fn result_line(&self, response: &Response) -> String {
+        match response {
+            Response::Value(calculation) => format!(
+                "{} = {}",
+                calculation.input,
+                self.format(calculation.result)
+            ),
+            Response::Exact { expression, value } => {
+                format!("{} = {}", expression, self.format_exact(value))
+            }
+            Response::Bool { expression, value } => format!("{} = {}", expression, value),
+            Response::Assignment { name, value } => {
+                format!("{} = {}", name, self.format(*value))
+            }
+            Response::Output(output) | Response::Info(output) => output.clone(),
+            Response::Empty => String::new(),
+        }
+    }
+
+    /// Aggregates the numbers after `command`, one of the [`LIST_COMMANDS`],
+    /// as done by `sum 1 2 3`.
+    ///
+    /// Every word after the command must be a number; expressions aren't
+    /// accepted, so that each word is one item of the list. The result
+    /// becomes the new `ans` and is recorded in the history.
+    fn list_command(&mut self, input: &str, command: &str) -> Result<Response, CalcError> {
+        let mut values = Vec::new();
+        let mut rest = input[command.len()..].trim_start();
+        while !rest.is_empty() {
+            let position = input.len() - rest.len();
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let word = match self.context.locale {
+                Locale::Eu => rest[..end].replace(',', "."),
+                Locale::Us => rest[..end].to_string(),
+            };
+            let value = parse_number(&word).map_err(|e| e.at(position))?;
+            values.push(finite_literal(value, self.context.allow_infinity)?);
+            rest = rest[end..].trim_start();
+        }
+        let calculation = Calculation::new(input, aggregate(command, &values)?);
+        self.context.set_ans(calculation.result);
+        self.history.record(&calculation);
+        Ok(Response::Value(calculation))
+    }
+
+    /// Handles a line in [`InputMode::Tape`].
+    ///
+    /// `total` shows the running total and `reset` sets it back to zero. Any
+    /// other line is an operator from [`OPERATORS`] followed by an expression,
+    /// such as `* 2` or `-3`, which is applied to the total; without an
+    /// operator the value is added. The new total becomes `ans` and is
+    /// recorded in the history.
+    fn tape(&mut self, input: &str) -> Result<Response, CalcError> {
+        match input {
+            "total" => return Ok(Response::Value(Calculation::new(input, self.total))),
+            "reset" => {
+                self.total = 0.0;
+                return Ok(Response::Info("Total reset to 0".to_string()));
+            }
+            _ => (),
+        }
+
+        // Try longer operators first, so `//` isn't read as `/`.
+        let operator = OPERATORS
+            .iter()
+            .filter(|operator| input.starts_with(*operator))
+            .max_by_key(|operator| operator.len());
+        let (operator, operand) = match operator {
+            Some(operator) => (*operator, &input[operator.len()..]),
+            None => ("+", input),
+        };
+        let value = evaluate_with(operand, &self.context)
+            .map_err(|e| e.shifted(input.len() - operand.len()))?;
+        self.total = calculate(self.total, value, operator)?;
+        self.context.set_ans(self.total);
+
+        let calculation = Calculation::new(input, self.total);
+        self.history.record(&calculation);
+        Ok(Response::Value(calculation))
+    }
+
+    /// Executes an expression or assignment and records it in the history.
+    fn calculate(&mut self, input: &str) -> Result<Response, CalcError> {
+        let response = match execute(input, &mut self.context)? {
+            Outcome::Value(value) => Response::Value(Calculation::new(input, value)),
+            Outcome::Exact(value) => {
+                self.history.push(format!("{} = {}", input.trim(), value));
+                return Ok(Response::Exact {
+                    expression: input.trim().to_string(),
+                    value,
+                });
+            }
+            Outcome::Bool(value) => {
+                self.history.push(format!("{} = {}", input.trim(), value));
+                return Ok(Response::Bool {
+                    expression: input.trim().to_string(),
+                    value,
+                });
+            }
+            Outcome::Assignment(name, value) => Response::Assignment { name, value },
+        };
+        self.history
+            .record(&Calculation::new(input, self.context.ans));
+        Ok(response)
+    }
+}
+
+/// The prompt of a new [`Session`].
+pub const DEFAULT_PROMPT: &str = "> ";
+
+/// Number of earlier states kept for `undo`.
+const MAX_UNDO: usize = 50;
+
+/// Largest count accepted by `repeat N EXPRESSION`.
+const MAX_REPEAT: usize = 1_000_000;
+
+/// Keywords that end an interactive session.
+const QUIT_COMMANDS: [&str; 4] = ["q", "quit", "exit", ":q"];
+
+/// Returns `true` if `input` asks to leave the calculator.
+///
+/// The keywords `q`, `quit`, `exit` and `:q` are accepted in any case and
+/// with surrounding whitespace.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::is_quit_command;
+///
+/// assert!(is_quit_command(" Quit\n"));
+/// assert!(!is_quit_command("question"));
+/// ```
+pub fn is_quit_command(input: &str) -> bool {
+    let input = input.trim();
+    QUIT_COMMANDS
+        .iter()
+        .any(|command| input.eq_ignore_ascii_case(command))
+}
+
+/// Parses the argument of the `precision` command: a number of decimal
+/// places, or `full` for `None`.
+pub(crate) fn parse_precision(argument: &str) -> Result<Option<usize>, CalcError> {
+    match argument.trim() {
+        "full" => Ok(None),
+        precision => precision.parse().map(Some).map_err(|_| {
+            CalcError::InvalidCommand(format!(
+                "Invalid precision '{}'. Use a number of decimal places or 'full'",
+                precision
+            ))
+        }),
+    }
+}
+
+/// Parses the `on` / `off` argument of a toggle command.
+pub(crate) fn parse_switch(argument: &str) -> Result<bool, CalcError> {
+    match argument.trim() {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        other => Err(CalcError::InvalidCommand(format!(
+            "Invalid setting '{}'. Use on or off",
+            other
+        ))),
+    }
+}
+
+/// Parses the argument of `intwidth`: one of [`INT_WIDTHS`], or `off` for no
+/// limit.
+pub(crate) fn parse_int_width(argument: &str) -> Result<Option<u32>, CalcError> {
+    let argument = argument.trim();
+    if argument == "off" {
+        return Ok(None);
+    }
+    argument
+        .parse()
+        .ok()
+        .filter(|width| INT_WIDTHS.contains(width))
+        .map(Some)
+        .ok_or_else(|| {
+            CalcError::InvalidCommand(format!(
+                "Invalid integer width '{}'. Use 8, 16, 32, 64 or off",
+                argument
+            ))
+        })
+}
+
+/// Parses the argument of `currency`: `on` for [`DEFAULT_CURRENCY_SYMBOL`],
+/// `off`, or the symbol to use.
+pub(crate) fn parse_currency(argument: &str) -> Option<String> {
+    match argument.trim() {
+        "on" => Some(DEFAULT_CURRENCY_SYMBOL.to_string()),
+        "off" => None,
+        symbol => Some(symbol.to_string()),
+    }
+}
+
+/// Parses the argument of `prompt`, which may be wrapped in double quotes to
+/// keep leading and trailing spaces.
+pub(crate) fn parse_prompt(argument: &str) -> String {
+    let argument = argument.trim();
+    argument
+        .strip_prefix('"')
+        .and_then(|argument| argument.strip_suffix('"'))
+        .unwrap_or(argument)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handle_expression() {
+        let mut session = Session::default();
+        assert_eq!(
+            session.handle("5 + 5\n"),
+            Ok(Response::Value(Calculation::new("5 + 5", 10.0)))
+        );
+    }
+
+    #[test]
+    fn test_handle_mode() {
+        let mut session = Session::default();
+        let response = session.handle("mode deg");
+        assert_eq!(
+            response,
+            Ok(Response::Info("Angle mode set to degrees".to_string()))
+        );
+        assert_eq!(session.context.angle_mode, AngleMode::Degrees);
+        assert!(matches!(
+            session.handle("mode grad"),
+            Err(CalcError::InvalidCommand(_))
+        ));
+    }
+
+    #[test]
+    fn test_handle_comparison() {
+        let mut session = Session::default();
+        assert_eq!(
+            session.handle("5 > 3"),
+            Ok(Response::Bool {
+                expression: "5 > 3".to_string(),
+                value: true,
+            })
+        );
+        assert_eq!(session.history.entries(), ["5 > 3 = true"]);
+        assert_eq!(
+            session.handle("!1"),
+            Ok(Response::Bool {
+                expression: "5 > 3".to_string(),
+                value: true,
+            })
+        );
+    }
+
+    #[test]
+    fn test_handle_infinity_mode() {
+        let mut session = Session::default();
+        assert!(session.handle("inf").is_err());
+        assert_eq!(
+            session.handle("mode sci"),
+            Ok(Response::Info("Infinity is allowed".to_string()))
+        );
+        assert_eq!(
+            session.handle("inf * 2"),
+            Ok(Response::Value(Calculation::new("inf * 2", f64::INFINITY)))
+        );
+        session.handle("mode finite").unwrap();
+        assert!(session.handle("inf").is_err());
+    }
+
+    #[test]
+    fn test_handle_integer_mode() {
+        let mut session = Session::default();
+        assert_eq!(
+            session.handle("mode int"),
+            Ok(Response::Info("Number mode set to integer".to_string()))
+        );
+        assert_eq!(
+            session.handle("99999999999 * 99999999999"),
+            Ok(Response::Exact {
+                expression: "99999999999 * 99999999999".to_string(),
+                value: Exact::Integer(9999999999800000000001)
+            })
+        );
+        assert_eq!(
+            session.history.entries(),
+            ["99999999999 * 99999999999 = 9999999999800000000001"]
+        );
+        session.handle("mode real").unwrap();
+        assert!(matches!(
+            session.handle("99999999999 * 99999999999"),
+            Ok(Response::Value(_))
+        ));
+    }
+
+    #[test]
+    fn test_integer_width_and_overflow_commands() {
+        let mut session = Session::default();
+        session.handle("mode int").unwrap();
+        assert_eq!(
+            session.handle("intwidth 8"),
+            Ok(Response::Info("Integer width set to 8 bits".to_string()))
+        );
+        assert_eq!(session.handle("255 + 1"), Err(CalcError::Overflow));
+
+        assert_eq!(
+            session.handle("overflow wrap"),
+            Ok(Response::Info("Overflow set to wrap".to_string()))
+        );
+        assert_eq!(
+            session.handle("255 + 1"),
+            Ok(Response::Exact {
+                expression: "255 + 1".to_string(),
+                value: Exact::Integer(0)
+            })
+        );
+
+        session.handle("overflow saturate").unwrap();
+        match session.handle("255 + 1") {
+            Ok(Response::Exact { value, .. }) => assert_eq!(value, Exact::Integer(255)),
+            other => panic!("unexpected response: {:?}", other),
+        }
+
+        assert_eq!(
+            session.handle("intwidth off"),
+            Ok(Response::Info("Integer width off".to_string()))
+        );
+        match session.handle("255 + 1") {
+            Ok(Response::Exact { value, .. }) => assert_eq!(value, Exact::Integer(256)),
+            other => panic!("unexpected response: {:?}", other),
+        }
+        assert!(session.handle("intwidth 12").is_err());
+        assert!(session.handle("overflow clamp").is_err());
+    }
+
+    #[test]
+    fn test_integer_width_shows_twos_complement() {
+        let mut session = Session::default();
+        session.handle("base hex").unwrap();
+        assert_eq!(session.format(-1.0), "-0x1");
+        session.handle("intwidth 8").unwrap();
+        assert_eq!(session.format(-1.0), "0xff");
+        session.handle("intwidth 16").unwrap();
+        assert_eq!(session.format(-1.0), "0xffff");
+        session.handle("intwidth off").unwrap();
+        assert_eq!(session.format(-1.0), "-0x1");
+    }
+
+    #[test]
+    fn test_handle_fraction_mode() {
+        let mut session = Session::default();
+        session.handle("mode frac").unwrap();
+        assert_eq!(session.context.number_mode, NumberMode::Fraction);
+        match session.handle("1 / 3") {
+            Ok(Response::Exact { value, .. }) => assert_eq!(value.to_string(), "1/3"),
+            other => panic!("unexpected response: {:?}", other),
+        }
+        assert_eq!(session.context.ans, 1.0 / 3.0);
+    }
+
+    #[test]
+    fn test_handle_complex_mode() {
+        let mut session = Session::default();
+        session.handle("mode complex").unwrap();
+        session.handle("5").unwrap();
+        match session.handle("(1 + 2i) * 2") {
+            Ok(Response::Exact { value, .. }) => assert_eq!(value.to_string(), "2 + 4i"),
+            other => panic!("unexpected response: {:?}", other),
+        }
+        assert_eq!(session.context.ans, 5.0);
+    }
+
+    #[test]
+    fn test_handle_precision() {
+        let mut session = Session::default();
+        let value = 10.0 / 3.0;
+        assert_eq!(session.settings.format(value), "3.3333333333333335");
+
+        session.handle("precision 2").unwrap();
+        assert_eq!(session.settings.precision, Some(2));
+        assert_eq!(session.settings.format(value), "3.33");
+
+        session.handle("precision full").unwrap();
+        assert_eq!(session.settings.format(value), "3.3333333333333335");
+
+        assert!(matches!(
+            session.handle("precision -1"),
+            Err(CalcError::InvalidCommand(_))
+        ));
+    }
+
+    #[test]
+    fn test_handle_separators() {
+        let mut session = Session::default();
+        session.handle("sep on").unwrap();
+        assert_eq!(session.settings.format(1e6), "1,000,000");
+        session.handle("sep off").unwrap();
+        assert_eq!(session.settings.format(1e6), "1000000");
+        assert!(matches!(
+            session.handle("sep maybe"),
+            Err(CalcError::InvalidCommand(_))
+        ));
+    }
+
+    #[test]
+    fn test_handle_round_mode() {
+        let mut session = Session::default();
+        assert_eq!(
+            session.handle("round_mode truncate"),
+            Ok(Response::Info("Rounding mode set to truncate".to_string()))
+        );
+        assert_eq!(session.settings.round_mode, RoundMode::Truncate);
+        assert!(matches!(
+            session.handle("round_mode half"),
+            Err(CalcError::InvalidCommand(_))
+        ));
+    }
+
+    #[test]
+    fn test_handle_scientific() {
+        let mut session = Session::default();
+        session.handle("sci on").unwrap();
+        assert_eq!(session.settings.format(1500.0), "1.5e3");
+        session.handle("sci off").unwrap();
+        assert_eq!(session.settings.format(1500.0), "1500");
+        assert!(matches!(
+            session.handle("sci 1"),
+            Err(CalcError::InvalidCommand(_))
+        ));
+    }
+
+    #[test]
+    fn test_handle_engineering() {
+        let mut session = Session::default();
+        assert_eq!(
+            session.handle("eng on"),
+            Ok(Response::Info("Engineering notation on".to_string()))
+        );
+        assert_eq!(session.settings.format(12300.0), "12.3e3");
+        session.handle("eng off").unwrap();
+        assert_eq!(session.settings.format(12300.0), "12300");
+        assert!(matches!(
+            session.handle("eng 1"),
+            Err(CalcError::InvalidCommand(_))
+        ));
+    }
+
+    #[test]
+    fn test_handle_base() {
+        let mut session = Session::default();
+        session.handle("base hex").unwrap();
+        assert_eq!(session.settings.base, OutputBase::Hexadecimal);
+        session.handle("base bin").unwrap();
+        assert_eq!(session.settings.base, OutputBase::Binary);
+        session.handle("base dec").unwrap();
+        assert_eq!(session.settings.base, OutputBase::Decimal);
+        assert!(matches!(
+            session.handle("base oct"),
+            Err(CalcError::InvalidCommand(_))
+        ));
+    }
+
+    #[test]
+    fn test_handle_locale() {
+        let mut session = Session::default();
+        assert!(session.handle("5,5 + 1,5").is_err());
+        session.handle("locale eu").unwrap();
+        assert_eq!(
+            session.handle("5,5 + 1,5"),
+            Ok(Response::Value(Calculation::new("5,5 + 1,5", 7.0)))
+        );
+        session.handle("locale us").unwrap();
+        assert_eq!(session.context.locale, Locale::Us);
+        assert!(matches!(
+            session.handle("locale fr"),
+            Err(CalcError::InvalidCommand(_))
+        ));
+    }
+
+    #[test]
+    fn test_handle_convert() {
+        let mut session = Session::default();
+        assert_eq!(
+            session.handle("convert 2 * 1.5 km m"),
+            Ok(Response::Value(Calculation::new(
+                "convert 2 * 1.5 km m",
+                3000.0
+            )))
+        );
+        assert_eq!(session.context.ans, 3000.0);
+        assert_eq!(session.history.entries(), ["convert 2 * 1.5 km m = 3000"]);
+        assert_eq!(
+            session.handle("!1"),
+            Ok(Response::Value(Calculation::new(
+                "convert 2 * 1.5 km m",
+                3000.0
+            )))
+        );
+        assert!(matches!(
+            session.handle("convert 1 km kg"),
+            Err(CalcError::InvalidCommand(_))
+        ));
+        assert!(matches!(
+            session.handle("convert km mi"),
+            Err(CalcError::InvalidCommand(_))
+        ));
+    }
+
+    #[test]
+    fn test_undo_assignments() {
+        let mut session = Session::default();
+        session.handle("x = 5").unwrap();
+        session.handle("x = 10").unwrap();
+        assert_eq!(
+            session.handle("undo"),
+            Ok(Response::Output("Undone: x = 5, ans = 5".to_string()))
+        );
+        assert_eq!(session.context.variables.get("x"), Some(&5.0));
+        assert_eq!(
+            session.handle("undo"),
+            Ok(Response::Output("Undone: x removed, ans = 0".to_string()))
+        );
+        assert_eq!(session.context.variables.get("x"), None);
+        assert_eq!(
+            session.handle("undo"),
+            Ok(Response::Output("Nothing to undo".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_undo_ans() {
+        let mut session = Session::default();
+        session.handle("2 + 2").unwrap();
+        session.handle("ans * 10").unwrap();
+        session.handle("mode deg").unwrap();
+        assert!(session.handle("1 / 0").is_err());
+        assert_eq!(
+            session.handle("undo"),
+            Ok(Response::Output("Undone: ans = 4".to_string()))
+        );
+        assert_eq!(session.context.ans, 4.0);
+        assert_eq!(session.context.angle_mode, AngleMode::Degrees);
+
+        session.handle("!2").unwrap();
+        assert_eq!(session.context.ans, 40.0);
+        session.handle("undo").unwrap();
+        assert_eq!(session.context.ans, 4.0);
+    }
+
+    #[test]
+    fn test_undo_stack_is_bounded() {
+        let mut session = Session::default();
+        for i in 1..=MAX_UNDO + 5 {
+            session.handle(&i.to_string()).unwrap();
+        }
+        assert_eq!(session.undo_stack.len(), MAX_UNDO);
+    }
+
+    #[test]
+    fn test_handle_time() {
+        let mut session = Session::default();
+        let Ok(Response::Output(output)) = session.handle("time 2 + 3 * 4") else {
+            panic!("expected output");
+        };
+        let (result, time) = output.split_once('\n').unwrap();
+        assert_eq!(result, "2 + 3 * 4 = 14");
+        assert!(
+            time.starts_with("Time: ") && time.ends_with('s'),
+            "{}",
+            time
+        );
+        assert_eq!(session.context.ans, 14.0);
+        assert_eq!(session.history.entries(), ["2 + 3 * 4 = 14"]);
+        assert_eq!(session.handle("time 1 / 0"), Err(CalcError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_handle_repeat() {
+        let mut session = Session::default();
+        let Ok(Response::Output(output)) = session.handle("repeat 3 2 + 2") else {
+            panic!("expected output");
+        };
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[..3], ["2 + 2 = 4", "2 + 2 = 4", "2 + 2 = 4"]);
+        assert!(lines[3].starts_with("Time: ") && lines[3].ends_with(" for 3 evaluations"));
+        assert_eq!(lines.len(), 4);
+
+        let Ok(Response::Output(output)) = session.handle("repeat 2 ans + 1") else {
+            panic!("expected output");
+        };
+        assert!(output.starts_with("ans + 1 = 5\nans + 1 = 6\n"));
+    }
+
+    #[test]
+    fn test_handle_repeat_invalid_count() {
+        let mut session = Session::default();
+        for input in [
+            "repeat 0 1",
+            "repeat -2 1",
+            "repeat 1.5 1",
+            "repeat 1000001 1",
+            "repeat x 1",
+            "repeat 3",
+        ] {
+            assert!(
+                matches!(session.handle(input), Err(CalcError::InvalidCommand(_))),
+                "{}",
+                input
+            );
+        }
+        assert_eq!(
+            session.handle("repeat 2 1 / 0"),
+            Err(CalcError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn test_handle_list_commands() {
+        let mut session = Session::default();
+        assert_eq!(
+            session.handle("sum 1 2 3 4"),
+            Ok(Response::Value(Calculation::new("sum 1 2 3 4", 10.0)))
+        );
+        assert_eq!(
+            session.handle("mean 1 2 3 4"),
+            Ok(Response::Value(Calculation::new("mean 1 2 3 4", 2.5)))
+        );
+        assert_eq!(session.context.ans, 2.5);
+        assert_eq!(session.history.entries()[1], "mean 1 2 3 4 = 2.5");
+        assert_eq!(
+            session.handle("median 3 1 2 4"),
+            Ok(Response::Value(Calculation::new("median 3 1 2 4", 2.5)))
+        );
+        assert_eq!(
+            session.handle("stddev 2 4 4 4 5 5 7 9"),
+            Ok(Response::Value(Calculation::new(
+                "stddev 2 4 4 4 5 5 7 9",
+                2.0
+            )))
+        );
+        assert_eq!(
+            session.handle("sum  -1   0x10"),
+            Ok(Response::Value(Calculation::new("sum  -1   0x10", 15.0)))
+        );
+    }
+
+    #[test]
+    fn test_handle_list_command_errors() {
+        let mut session = Session::default();
+        assert!(matches!(
+            session.handle("sum"),
+            Err(CalcError::InvalidCommand(_))
+        ));
+        assert!(matches!(
+            session.handle("stddev "),
+            Err(CalcError::InvalidCommand(_))
+        ));
+        assert_eq!(
+            session.handle("mean 1 two 3"),
+            Err(CalcError::ParseNumber {
+                token: "two".to_string(),
+                position: Some(7)
+            })
+        );
+        assert!(matches!(
+            session.handle("sum 1 + 2"),
+            Err(CalcError::ParseNumber { .. })
+        ));
+    }
+
+    #[test]
+    fn test_tape_running_total() {
+        let mut session = Session::default();
+        assert_eq!(
+            session.handle("mode tape"),
+            Ok(Response::Info("Input mode set to tape".to_string()))
+        );
+        let totals: Vec<f64> = ["+5", "+3", "* 2", "-1", "10", "// 4"]
+            .iter()
+            .map(|entry| match session.handle(entry) {
+                Ok(Response::Value(calculation)) => calculation.result,
+                other => panic!("unexpected response: {:?}", other),
+            })
+            .collect();
+        assert_eq!(totals, [5.0, 8.0, 16.0, 15.0, 25.0, 6.0]);
+        assert_eq!(
+            session.handle("total"),
+            Ok(Response::Value(Calculation::new("total", 6.0)))
+        );
+        assert_eq!(session.context.ans, 6.0);
+    }
+
+    #[test]
+    fn test_tape_reset_and_errors() {
+        let mut session = Session::default();
+        session.handle("mode tape").unwrap();
+        session.handle("+5").unwrap();
+        assert_eq!(session.handle("/ 0"), Err(CalcError::DivisionByZero));
+        assert_eq!(session.total, 5.0);
+
+        session.handle("reset").unwrap();
+        assert_eq!(
+            session.handle("+ 2 * 3"),
+            Ok(Response::Value(Calculation::new("+ 2 * 3", 6.0)))
+        );
+
+        session.handle("mode std").unwrap();
+        assert_eq!(
+            session.handle("2 + 3"),
+            Ok(Response::Value(Calculation::new("2 + 3", 5.0)))
+        );
+        assert!(session.handle("total").is_err());
+    }
+
+    #[test]
+    fn test_rpn_mode() {
+        let mut session = Session::default();
+        assert_eq!(
+            session.handle("mode rpn"),
+            Ok(Response::Info("Input mode set to RPN".to_string()))
+        );
+        assert_eq!(
+            session.handle("2 3 4 * +"),
+            Ok(Response::Value(Calculation::new("2 3 4 * +", 14.0)))
+        );
+        assert_eq!(
+            session.handle("ans 2 /"),
+            Ok(Response::Value(Calculation::new("ans 2 /", 7.0)))
+        );
+        assert_eq!(
+            session.handle("1 +"),
+            Err(CalcError::InsufficientOperands("+".to_string()))
+        );
+        assert_eq!(session.handle("1 2"), Err(CalcError::TooManyOperands(2)));
+        assert_eq!(session.history.entries(), ["2 3 4 * + = 14", "ans 2 / = 7"]);
+    }
+
+    #[test]
+    fn test_last_error() {
+        let mut session = Session::default();
+        assert_eq!(
+            session.handle("last_error"),
+            Ok(Response::Output("No errors yet".to_string()))
+        );
+        assert!(session.handle("5 / 0").is_err());
+        session.handle("1 + 1").unwrap();
+        assert_eq!(
+            session.handle("last_error"),
+            Ok(Response::Output("Error: Cannot divide by zero".to_string()))
+        );
+        assert!(session.handle("mode grad").is_err());
+        assert!(matches!(
+            session.last_error,
+            Some(CalcError::InvalidCommand(_))
+        ));
+
+        session.reset();
+        assert_eq!(session.last_error, None);
+    }
+
+    #[test]
+    fn test_memory_register() {
+        let mut session = Session::default();
+        assert_eq!(session.handle("MR"), Ok(Response::Output("0".to_string())));
+
+        session.handle("100 * 3").unwrap();
+        assert_eq!(
+            session.handle("M+"),
+            Ok(Response::Info("Memory: 300".to_string()))
+        );
+        session.handle("50").unwrap();
+        session.handle("m-").unwrap();
+        assert_eq!(session.context.memory, 250.0);
+        assert_eq!(
+            session.handle("MR"),
+            Ok(Response::Output("250".to_string()))
+        );
+        assert_eq!(
+            session.handle("mr / 2"),
+            Ok(Response::Value(Calculation::new("mr / 2", 125.0)))
+        );
+        session.handle("M+").unwrap();
+        assert_eq!(session.context.memory, 375.0);
+
+        session.handle("MC").unwrap();
+        assert_eq!(session.context.memory, 0.0);
+        assert!(session.handle("mr = 1").is_err());
+    }
+
+    #[test]
+    fn test_reset_clears_state() {
+        let mut session = Session::default();
+        session.handle("mode deg").unwrap();
+        session.handle("x = 5").unwrap();
+        session.handle("x * 2").unwrap();
+        session.handle("M+").unwrap();
+
+        session.reset();
+        assert_eq!(session.context.ans, 0.0);
+        assert_eq!(session.context.memory, 0.0);
+        assert!(session.context.variables.is_empty());
+        assert!(session.history.entries().is_empty());
+        assert_eq!(session.context.angle_mode, AngleMode::Degrees);
+        assert!(matches!(
+            session.handle("x"),
+            Err(CalcError::UnknownIdentifier(_))
+        ));
+    }
+
+    #[test]
+    fn test_x_as_multiplication() {
+        let mut session = Session::default();
+        assert_eq!(
+            session.handle("5 x 5"),
+            Ok(Response::Value(Calculation::new("5 x 5", 25.0)))
+        );
+        session.handle("x = 3").unwrap();
+        assert_eq!(
+            session.handle("2 x x"),
+            Ok(Response::Value(Calculation::new("2 x x", 6.0)))
+        );
+    }
+
+    #[test]
+    fn test_x_as_variable_without_aliases() {
+        let mut session = Session::default();
+        assert_eq!(
+            session.handle("aliases off"),
+            Ok(Response::Info("Operator aliases off".to_string()))
+        );
+        session.handle("x = 4").unwrap();
+        assert_eq!(
+            session.handle("x + 1"),
+            Ok(Response::Value(Calculation::new("x + 1", 5.0)))
+        );
+        assert!(session.handle("5 x 5").is_err());
+        assert!(session.handle("5 × 5").is_err());
+    }
+
+    #[test]
+    fn test_currency_command() {
+        let mut session = Session::default();
+        assert_eq!(
+            session.handle("currency on"),
+            Ok(Response::Info("Currency format on ($)".to_string()))
+        );
+        assert_eq!(session.settings.format(-1234.5), "-$1,234.50");
+
+        session.handle("currency €").unwrap();
+        assert_eq!(session.settings.format(0.0), "€0.00");
+
+        assert_eq!(
+            session.handle("currency off"),
+            Ok(Response::Info("Currency format off".to_string()))
+        );
+        assert_eq!(session.settings.format(1234.5), "1234.5");
+    }
+
+    #[test]
+    fn test_prompt_command() {
+        let mut session = Session::default();
+        assert_eq!(session.prompt, DEFAULT_PROMPT);
+
+        assert_eq!(
+            session.handle("prompt \"calc> \""),
+            Ok(Response::Info("Prompt set to \"calc> \"".to_string()))
+        );
+        assert_eq!(session.prompt, "calc> ");
+
+        session.handle("prompt >>").unwrap();
+        assert_eq!(session.prompt, ">>");
+
+        session.handle("prompt \"\"").unwrap();
+        assert_eq!(session.prompt, "");
+    }
+
+    #[test]
+    fn test_define_command() {
+        let mut session = Session::default();
+        assert_eq!(
+            session.handle("define double(x) = x * 2"),
+            Ok(Response::Info("Function 'double' defined".to_string()))
+        );
+        assert_eq!(
+            session.handle("double(21)"),
+            Ok(Response::Value(Calculation::new("double(21)", 42.0)))
+        );
+        assert_eq!(
+            session.handle("define double(x) = x + x"),
+            Err(CalcError::InvalidCommand(
+                "Function 'double' is already defined".to_string()
+            ))
+        );
+
+        session.reset();
+        assert!(session.context.functions.is_empty());
+    }
+
+    #[test]
+    fn test_write_prompt_uses_configured_prompt() {
+        let session = Session {
+            prompt: "calc> ".to_string(),
+            ..Session::default()
+        };
+        let mut out = Vec::new();
+        session.write_prompt(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "calc> ");
+
+        let session = Session {
+            prompt: String::new(),
+            ..Session::default()
+        };
+        let mut out = Vec::new();
+        session.write_prompt(&mut out).unwrap();
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_handle_empty_input() {
+        let mut session = Session::default();
+        assert_eq!(session.handle(""), Ok(Response::Empty));
+        assert_eq!(session.handle("  \t\n"), Ok(Response::Empty));
+        assert!(session.history.entries().is_empty());
+    }
+
+    #[test]
+    fn test_handle_help() {
+        let mut session = Session::default();
+        match session.handle("help") {
+            Ok(Response::Output(help)) => {
+                assert!(help.contains('+'));
+                assert!(help.contains("sqrt"));
+                assert!(help.contains("quit"));
+            }
+            other => panic!("unexpected response: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_handle_explain() {
+        let mut session = Session::default();
+        assert_eq!(
+            session.handle("explain +"),
+            Ok(Response::Output(
+                "Addition: adds the right number to the left".to_string()
+            ))
+        );
+        match session.handle("explain sqrt") {
+            Ok(Response::Output(explanation)) => {
+                assert!(explanation.starts_with("Square root:"))
+            }
+            other => panic!("unexpected response: {:?}", other),
+        }
+        assert_eq!(
+            session.handle("explain foo"),
+            Ok(Response::Output("No explanation for foo".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_is_quit_command_q() {
+        assert!(is_quit_command("q"));
+        assert!(is_quit_command("Q"));
+    }
+
+    #[test]
+    fn test_is_quit_command_quit() {
+        assert!(is_quit_command("quit"));
+        assert!(is_quit_command("QUIT"));
+    }
+
+    #[test]
+    fn test_is_quit_command_exit() {
+        assert!(is_quit_command("exit"));
+        assert!(is_quit_command("Exit"));
+    }
+
+    #[test]
+    fn test_is_quit_command_colon_q() {
+        assert!(is_quit_command(":q"));
+        assert!(is_quit_command(":Q"));
+    }
+
+    #[test]
+    fn test_is_quit_command_tolerates_whitespace() {
+        assert!(is_quit_command("  quit \n"));
+        assert!(is_quit_command("\texit"));
+    }
+
+    #[test]
+    fn test_is_quit_command_rejects_other_input() {
+        assert!(!is_quit_command("question"));
+        assert!(!is_quit_command("q uit"));
+        assert!(!is_quit_command(""));
+    }
+
+    #[test]
+    fn test_history_lists_calculations() {
+        let mut session = Session::default();
+        session.handle("1 + 1").unwrap();
+        session.handle("x = 3").unwrap();
+        session.handle("x * 2").unwrap();
+        assert!(session.handle("1 +").is_err());
+        assert_eq!(
+            session.handle("history"),
+            Ok(Response::Output(
+                "1: 1 + 1 = 2\n2: x = 3 → 3\n3: x * 2 = 6".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_tokens_command() {
+        let mut session = Session::default();
+        assert_eq!(
+            session.handle("tokens 2 + 3 * 4"),
+            Ok(Response::Output(
+                "[Number(2.0), Operator(\"+\"), Number(3.0), Operator(\"*\"), Number(4.0)]"
+                    .to_string()
+            ))
+        );
+        assert_eq!(
+            session.handle("tokens sqrt(x)"),
+            Ok(Response::Output(
+                "[Identifier(\"sqrt\"), LeftParen, Identifier(\"x\"), RightParen]".to_string()
+            ))
+        );
+        assert_eq!(
+            session.handle("tokens 5 x 5"),
+            session.handle("tokens 5 * 5")
+        );
+        assert_eq!(
+            session.handle("tokens 5 $ 3").unwrap_err().position(),
+            Some(9)
+        );
+        assert!(session.history.entries().is_empty());
+    }
+
+    #[test]
+    fn test_ast_command() {
+        let mut session = Session::default();
+        assert_eq!(
+            session.handle("ast 2 + 3 * 4"),
+            Ok(Response::Output("+\n  2\n  *\n    3\n    4".to_string()))
+        );
+        assert_eq!(
+            session.handle("ast 2 * 3 + 4"),
+            Ok(Response::Output("+\n  *\n    2\n    3\n  4".to_string()))
+        );
+        assert_eq!(
+            session.handle("ast 2 + (3"),
+            Err(CalcError::UnmatchedParenthesis)
+        );
+        assert_eq!(
+            session.handle("ast 2 + + 3").unwrap_err().position(),
+            Some(8)
+        );
+        session.handle("aliases off").unwrap();
+        assert!(matches!(
+            session.handle("ast 2 x 3"),
+            Err(CalcError::UnexpectedToken { .. })
+        ));
+        assert_eq!(
+            session.handle("ast 2x"),
+            Ok(Response::Output("*\n  2\n  x".to_string()))
+        );
+        assert!(session.history.entries().is_empty());
+    }
+
+    #[test]
+    fn test_search_history() {
+        let mut session = Session::default();
+        session.handle("sqrt(16)").unwrap();
+        session.handle("2 + 2").unwrap();
+        session.handle("sqrt(9) * 2").unwrap();
+        assert_eq!(
+            session.handle("search SQRT"),
+            Ok(Response::Output(
+                "1: sqrt(16) = 4\n3: sqrt(9) * 2 = 6".to_string()
+            ))
+        );
+        assert_eq!(
+            session.handle("search log"),
+            Ok(Response::Output("No results".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_history_when_empty() {
+        let mut session = Session::default();
+        assert_eq!(
+            session.handle("history"),
+            Ok(Response::Output("No history yet".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_recall_history_entry() {
+        let mut session = Session::default();
+        session.handle("x = 2").unwrap();
+        session.handle("x ^ 3").unwrap();
+        session.handle("x = 3").unwrap();
+        assert_eq!(
+            session.handle("!2"),
+            Ok(Response::Value(Calculation::new("x ^ 3", 27.0)))
+        );
+        assert_eq!(session.history.entries().len(), 4);
+    }
+
+    #[test]
+    fn test_recall_missing_entry() {
+        let mut session = Session::default();
+        session.handle("1 + 1").unwrap();
+        assert!(matches!(
+            session.handle("!5"),
+            Err(CalcError::InvalidCommand(_))
+        ));
+        assert!(matches!(
+            session.handle("!x"),
+            Err(CalcError::InvalidCommand(_))
+        ));
+    }
+}
@@ -0,0 +1,759 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{CalcError, Exact};
+
+/// Base in which integer results are displayed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputBase {
+    /// Decimal (the default)
+    #[default]
+    Decimal,
+    /// Hexadecimal with a `0x` prefix
+    Hexadecimal,
+    /// Binary with a `0b` prefix
+    Binary,
+}
+
+impl FromStr for OutputBase {
+    type Err = CalcError;
+
+    /// Parses the argument of the `base` command: `dec`, `hex` or `bin`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dec" => Ok(OutputBase::Decimal),
+            "hex" => Ok(OutputBase::Hexadecimal),
+            "bin" => Ok(OutputBase::Binary),
+            _ => Err(CalcError::InvalidCommand(format!(
+                "Unknown base '{}'. Use dec, hex or bin",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for OutputBase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputBase::Decimal => write!(f, "decimal"),
+            OutputBase::Hexadecimal => write!(f, "hexadecimal"),
+            OutputBase::Binary => write!(f, "binary"),
+        }
+    }
+}
+
+/// How results are rounded to the number of decimal places in
+/// [`Settings::precision`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RoundMode {
+    /// To the nearest value (the default)
+    #[default]
+    Nearest,
+    /// Toward positive infinity, like `ceil`
+    Up,
+    /// Toward negative infinity, like `floor`
+    Down,
+    /// Toward zero, dropping the extra digits
+    Truncate,
+}
+
+impl RoundMode {
+    /// Rounds `value` to `decimals` decimal places in this mode.
+    ///
+    /// Most decimal fractions have no exact `f64` representation, so the
+    /// stored value of `2.675` is slightly below it and rounds to `2.67` in
+    /// [`RoundMode::Nearest`]. The directed modes ignore errors of that size
+    /// where the value is meant to end at the given digit, so `2.3` rounded
+    /// down to two places stays `2.3` even though `2.3 * 100` is just below
+    /// `230`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rust_calculator_cli::RoundMode;
+    ///
+    /// assert_eq!(RoundMode::Up.round(2.341, 2), 2.35);
+    /// assert_eq!(RoundMode::Truncate.round(-2.349, 2), -2.34);
+    /// ```
+    pub fn round(self, value: f64, decimals: usize) -> f64 {
+        if self == RoundMode::Nearest {
+            // The same rounding as shown by `{:.N}`.
+            return format!("{:.*}", decimals, value).parse().unwrap_or(value);
+        }
+        let scale = 10f64.powi(decimals.min(i32::MAX as usize) as i32);
+        let scaled = value * scale;
+        if !scaled.is_finite() {
+            return value;
+        }
+        let nearest = scaled.round();
+        let rounded = if (scaled - nearest).abs() <= 1e-9 * nearest.abs().max(1.0) {
+            nearest
+        } else {
+            match self {
+                RoundMode::Up => scaled.ceil(),
+                RoundMode::Down => scaled.floor(),
+                _ => scaled.trunc(),
+            }
+        };
+        // Adding zero turns `-0` into `0`, so `-0.001` truncates to `0.00`.
+        rounded / scale + 0.0
+    }
+}
+
+impl FromStr for RoundMode {
+    type Err = CalcError;
+
+    /// Parses the argument of the `round_mode` command: `nearest`, `up`,
+    /// `down` or `truncate`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nearest" => Ok(RoundMode::Nearest),
+            "up" => Ok(RoundMode::Up),
+            "down" => Ok(RoundMode::Down),
+            "truncate" => Ok(RoundMode::Truncate),
+            _ => Err(CalcError::InvalidCommand(format!(
+                "Unknown rounding mode '{}'. Use nearest, up, down or truncate",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for RoundMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoundMode::Nearest => write!(f, "nearest"),
+            RoundMode::Up => write!(f, "up"),
+            RoundMode::Down => write!(f, "down"),
+            RoundMode::Truncate => write!(f, "truncate"),
+        }
+    }
+}
+
+/// Options that control how results are displayed.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::Settings;
+///
+/// let mut settings = Settings::default();
+/// assert_eq!(settings.format(10.0 / 3.0), "3.3333333333333335");
+///
+/// settings.precision = Some(2);
+/// assert_eq!(settings.format(10.0 / 3.0), "3.33");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Settings {
+    /// Number of decimal places shown, or `None` for full precision
+    pub precision: Option<usize>,
+    /// Group the integer part into thousands, e.g. `1,000,000`
+    pub separators: bool,
+    /// Base for integer results; other results are always shown in decimal
+    pub base: OutputBase,
+    /// Show decimal results in scientific notation, e.g. `1.5e3`
+    pub scientific: bool,
+    /// Show decimal results in engineering notation, e.g. `12.3e3`; takes
+    /// precedence over [`Settings::scientific`]
+    pub engineering: bool,
+    /// How results are rounded to the precision
+    pub round_mode: RoundMode,
+    /// Symbol shown in front of results formatted as amounts of money, such
+    /// as `$1,234.50`, or `None` to show plain numbers
+    pub currency: Option<String>,
+}
+
+impl Settings {
+    /// Formats a result according to these settings.
+    ///
+    /// In a non-decimal [`OutputBase`], integer results are shown in that base
+    /// (e.g. `255` as `0xff`, `-1` as `-0x1`); see
+    /// [`Settings::format_with_width`] for two's complement. Other results fall
+    /// back to decimal, which [`Settings::warning`] reports. Decimal results
+    /// are shown in scientific notation if [`Settings::scientific`] is set, in
+    /// which case the precision applies to the mantissa and no separators are
+    /// added. [`Settings::engineering`] shows the same digits with an exponent
+    /// that is a multiple of 3 (see [`format_engineering`]). With a precision,
+    /// decimal results are rounded in the [`Settings::round_mode`]; the
+    /// mantissa in scientific notation is always rounded to the nearest value.
+    ///
+    /// With a [`Settings::currency`] symbol, finite results are shown as an
+    /// amount of money instead (see [`format_currency`]), with the precision
+    /// or [`DEFAULT_CURRENCY_DECIMALS`] decimal places.
+    pub fn format(&self, value: f64) -> String {
+        self.format_with_width(value, None)
+    }
+
+    /// Formats a result like [`Settings::format`]. With an `int_width` as set
+    /// with `intwidth` (see [`Context::int_width`](crate::Context)), negative
+    /// integers that fit in that many bits are shown in two's complement in
+    /// hexadecimal and binary, e.g. `-1` as `0xff` at 8 bits.
+    pub fn format_with_width(&self, value: f64, int_width: Option<u32>) -> String {
+        if let Some(symbol) = self.currency.as_deref().filter(|_| value.is_finite()) {
+            let decimals = self.precision.unwrap_or(DEFAULT_CURRENCY_DECIMALS);
+            return format_currency(self.round_mode.round(value, decimals), symbol, decimals);
+        }
+
+        if let Some(formatted) =
+            as_integer(value).and_then(|integer| self.in_base(integer.into(), int_width))
+        {
+            return formatted;
+        }
+
+        if self.engineering {
+            return match self.precision {
+                Some(precision) => format_engineering(value, precision + 1),
+                None => to_engineering(&format!("{:e}", value)),
+            };
+        }
+
+        if self.scientific {
+            return match self.precision {
+                Some(precision) => format!("{:.*e}", precision, value),
+                None => format!("{:e}", value),
+            };
+        }
+
+        let formatted = match self.precision {
+            Some(precision) => format!("{:.*}", precision, self.round_mode.round(value, precision)),
+            None => value.to_string(),
+        };
+        if self.separators {
+            group_thousands(&formatted)
+        } else {
+            formatted
+        }
+    }
+
+    /// Formats an exact result from one of the exact number modes according
+    /// to these settings.
+    ///
+    /// Integers are shown as [`Settings::format`] shows whole numbers, in the
+    /// [`OutputBase`] and with separators or the decimal places of the
+    /// precision, but without being rounded to an `f64`. Only in scientific or
+    /// engineering notation and as an amount of money are they shown through
+    /// their `f64` approximation. Fractions and complex numbers are always
+    /// shown as they are, e.g. `1/3` or `2 + 3i`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rust_calculator_cli::{Exact, OutputBase, Settings};
+    ///
+    /// let settings = Settings {
+    ///     base: OutputBase::Hexadecimal,
+    ///     ..Settings::default()
+    /// };
+    /// assert_eq!(settings.format_exact(&Exact::Integer(255)), "0xff");
+    /// ```
+    pub fn format_exact(&self, value: &Exact) -> String {
+        self.format_exact_with_width(value, None)
+    }
+
+    /// Formats an exact result like [`Settings::format_exact`], showing
+    /// negative integers in two's complement at `int_width` bits as
+    /// [`Settings::format_with_width`] does.
+    pub fn format_exact_with_width(&self, value: &Exact, int_width: Option<u32>) -> String {
+        let Exact::Integer(integer) = *value else {
+            return value.to_string();
+        };
+        if let Some(formatted) = self
+            .in_base(integer, int_width)
+            .filter(|_| self.currency.is_none())
+        {
+            return formatted;
+        }
+        if self.scientific || self.engineering || self.currency.is_some() {
+            return self.format(integer as f64);
+        }
+        let formatted = match self.precision {
+            Some(precision) if precision > 0 => format!("{}.{}", integer, "0".repeat(precision)),
+            _ => integer.to_string(),
+        };
+        if self.separators {
+            group_thousands(&formatted)
+        } else {
+            formatted
+        }
+    }
+
+    /// Formats an integer in a non-decimal [`OutputBase`], or returns `None`
+    /// in [`OutputBase::Decimal`].
+    fn in_base(&self, integer: i128, int_width: Option<u32>) -> Option<String> {
+        let (sign, magnitude) = match int_width.and_then(|width| twos_complement(integer, width)) {
+            Some(bits) => ("", bits),
+            None if integer < 0 => ("-", integer.unsigned_abs()),
+            None => ("", integer.unsigned_abs()),
+        };
+        match self.base {
+            OutputBase::Decimal => None,
+            OutputBase::Hexadecimal => Some(format!("{}0x{:x}", sign, magnitude)),
+            OutputBase::Binary => Some(format!("{}0b{:b}", sign, magnitude)),
+        }
+    }
+
+    /// Explains why `value` isn't shown in the selected base, if it isn't.
+    pub fn warning(&self, value: f64) -> Option<String> {
+        if self.base != OutputBase::Decimal && as_integer(value).is_none() {
+            Some(format!(
+                "{} is not an integer, showing it in decimal",
+                value
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns the two's-complement bits of a negative `integer` at `width` bits,
+/// or `None` if it isn't negative or doesn't fit in that many bits.
+fn twos_complement(integer: i128, width: u32) -> Option<u128> {
+    let min = -(1i128 << (width - 1));
+    (integer < 0 && integer >= min).then(|| (integer + (1i128 << width)) as u128)
+}
+
+/// Returns `value` as an `i64` if it is a whole number in range.
+fn as_integer(value: f64) -> Option<i64> {
+    // `i64::MAX as f64` rounds up to 2^63, which is already out of range.
+    let in_range = value >= i64::MIN as f64 && value < i64::MAX as f64;
+    (value.fract() == 0.0 && in_range).then_some(value as i64)
+}
+
+/// Formats `value` with `decimals` decimal places and a `,` between each
+/// group of three digits in the integer part.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::format_with_separators;
+///
+/// assert_eq!(format_with_separators(1234567.891, 2), "1,234,567.89");
+/// assert_eq!(format_with_separators(-1000.0, 0), "-1,000");
+/// ```
+pub fn format_with_separators(value: f64, decimals: usize) -> String {
+    group_thousands(&format!("{:.*}", decimals, value))
+}
+
+/// Formats `value` in engineering notation with `sig_figs` significant
+/// figures: like scientific notation, but with an exponent that is a multiple
+/// of 3, so the mantissa is in `[1, 1000)`.
+///
+/// The last digit is rounded to the nearest value. At least one significant
+/// figure is shown, and more if the mantissa needs them, as in `10e3`.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::format_engineering;
+///
+/// assert_eq!(format_engineering(12300.0, 3), "12.3e3");
+/// assert_eq!(format_engineering(0.0012, 2), "1.2e-3");
+/// assert_eq!(format_engineering(-4.7e-8, 2), "-47e-9");
+/// ```
+pub fn format_engineering(value: f64, sig_figs: usize) -> String {
+    to_engineering(&format!("{:.*e}", sig_figs.max(1) - 1, value))
+}
+
+/// Moves the point of a number in scientific notation such as `1.23e4` so
+/// that the exponent is a multiple of 3, as in `12.3e3`. Infinity and NaN,
+/// which have no exponent, are returned unchanged.
+fn to_engineering(scientific: &str) -> String {
+    let Some((mantissa, exponent)) = scientific.split_once('e') else {
+        return scientific.to_string();
+    };
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(magnitude) => ("-", magnitude),
+        None => ("", mantissa),
+    };
+    let mut digits: String = mantissa.chars().filter(|&c| c != '.').collect();
+    let shift = exponent.rem_euclid(3);
+    while digits.len() <= shift as usize {
+        digits.push('0');
+    }
+    let (integer, fraction) = digits.split_at(shift as usize + 1);
+    let point = if fraction.is_empty() { "" } else { "." };
+    format!(
+        "{}{}{}{}e{}",
+        sign,
+        integer,
+        point,
+        fraction,
+        exponent - shift
+    )
+}
+
+/// Currency symbol used by `currency on`.
+pub const DEFAULT_CURRENCY_SYMBOL: &str = "$";
+
+/// Decimal places of amounts of money, unless a precision is set.
+pub const DEFAULT_CURRENCY_DECIMALS: usize = 2;
+
+/// Formats `value` as an amount of money: `symbol` followed by the amount with
+/// `decimals` decimal places and thousands separators. The sign of a negative
+/// amount comes before the symbol.
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::format_currency;
+///
+/// assert_eq!(format_currency(1234.5, "$", 2), "$1,234.50");
+/// assert_eq!(format_currency(-1234.5, "$", 2), "-$1,234.50");
+/// assert_eq!(format_currency(99.999, "€", 0), "€100");
+/// ```
+pub fn format_currency(value: f64, symbol: &str, decimals: usize) -> String {
+    let amount = format_with_separators(value.abs(), decimals);
+    // An amount that rounds to zero, such as -0.001, is shown without a sign.
+    let is_zero = amount.chars().all(|c| matches!(c, '0' | '.' | ','));
+    let sign = if value < 0.0 && !is_zero { "-" } else { "" };
+    format!("{}{}{}", sign, symbol, amount)
+}
+
+/// Inserts thousands separators into the integer part of a formatted number.
+fn group_thousands(formatted: &str) -> String {
+    let (sign, unsigned) = match formatted.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", formatted),
+    };
+    let (integer, fraction) = match unsigned.find('.') {
+        Some(point) => unsigned.split_at(point),
+        None => (unsigned, ""),
+    };
+
+    let mut grouped = String::with_capacity(integer.len() + integer.len() / 3);
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+
+    format!("{}{}{}", sign, grouped, fraction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_full_precision_by_default() {
+        let settings = Settings::default();
+        assert_eq!(settings.format(10.0), "10");
+        assert_eq!(settings.format(0.1 + 0.2), "0.30000000000000004");
+    }
+
+    #[test]
+    fn test_format_with_precision() {
+        let settings = Settings {
+            precision: Some(2),
+            ..Settings::default()
+        };
+        assert_eq!(settings.format(10.0 / 3.0), "3.33");
+        assert_eq!(settings.format(2.0 / 3.0), "0.67");
+        assert_eq!(settings.format(10.0), "10.00");
+    }
+
+    #[test]
+    fn test_format_with_zero_precision() {
+        let settings = Settings {
+            precision: Some(0),
+            ..Settings::default()
+        };
+        assert_eq!(settings.format(2.7), "3");
+    }
+
+    #[test]
+    fn test_round_mode_on_boundary() {
+        // 2.345 is stored as 2.34500000000000019984...
+        assert_eq!(RoundMode::Nearest.round(2.345, 2), 2.35);
+        assert_eq!(RoundMode::Up.round(2.345, 2), 2.35);
+        assert_eq!(RoundMode::Down.round(2.345, 2), 2.34);
+        assert_eq!(RoundMode::Truncate.round(2.345, 2), 2.34);
+
+        // 2.675 is stored as 2.67499999999999982236...
+        assert_eq!(RoundMode::Nearest.round(2.675, 2), 2.67);
+        assert_eq!(RoundMode::Up.round(2.675, 2), 2.68);
+
+        assert_eq!(RoundMode::Nearest.round(-2.345, 2), -2.35);
+        assert_eq!(RoundMode::Up.round(-2.345, 2), -2.34);
+        assert_eq!(RoundMode::Down.round(-2.345, 2), -2.35);
+        assert_eq!(RoundMode::Truncate.round(-2.345, 2), -2.34);
+    }
+
+    #[test]
+    fn test_round_mode_keeps_exact_digits() {
+        for mode in [RoundMode::Up, RoundMode::Down, RoundMode::Truncate] {
+            assert_eq!(mode.round(2.3, 2), 2.3);
+            assert_eq!(mode.round(-0.07, 2), -0.07);
+            assert_eq!(mode.round(5.0, 0), 5.0);
+        }
+        assert_eq!(RoundMode::Truncate.round(-0.001, 2).to_string(), "0");
+    }
+
+    #[test]
+    fn test_round_mode_from_str() {
+        assert_eq!("truncate".parse(), Ok(RoundMode::Truncate));
+        assert_eq!("up".parse(), Ok(RoundMode::Up));
+        assert!("half".parse::<RoundMode>().is_err());
+        assert_eq!(Settings::default().round_mode, RoundMode::Nearest);
+    }
+
+    #[test]
+    fn test_format_with_round_mode() {
+        let mut settings = Settings {
+            precision: Some(2),
+            round_mode: RoundMode::Up,
+            ..Settings::default()
+        };
+        assert_eq!(settings.format(10.0 / 3.0), "3.34");
+        settings.round_mode = RoundMode::Down;
+        assert_eq!(settings.format(2.0 / 3.0), "0.66");
+        assert_eq!(settings.format(-2.0 / 3.0), "-0.67");
+        settings.round_mode = RoundMode::Truncate;
+        assert_eq!(settings.format(-2.0 / 3.0), "-0.66");
+        assert_eq!(settings.format(1.0), "1.00");
+    }
+
+    #[test]
+    fn test_format_with_separators_positive() {
+        assert_eq!(format_with_separators(1000000.0, 0), "1,000,000");
+        assert_eq!(format_with_separators(999.0, 0), "999");
+        assert_eq!(format_with_separators(12345.0, 0), "12,345");
+    }
+
+    #[test]
+    fn test_format_with_separators_negative() {
+        assert_eq!(format_with_separators(-1234567.0, 0), "-1,234,567");
+        assert_eq!(format_with_separators(-100.0, 0), "-100");
+    }
+
+    #[test]
+    fn test_format_with_separators_fractional() {
+        assert_eq!(format_with_separators(1234.5678, 4), "1,234.5678");
+        assert_eq!(format_with_separators(0.12345, 5), "0.12345");
+    }
+
+    #[test]
+    fn test_format_separators_setting() {
+        let mut settings = Settings {
+            separators: true,
+            ..Settings::default()
+        };
+        assert_eq!(settings.format(1e12), "1,000,000,000,000");
+        assert_eq!(settings.format(-1234.125), "-1,234.125");
+
+        settings.precision = Some(1);
+        assert_eq!(settings.format(1234.25), "1,234.2");
+    }
+
+    #[test]
+    fn test_format_scientific() {
+        let mut settings = Settings {
+            scientific: true,
+            separators: true,
+            ..Settings::default()
+        };
+        assert_eq!(settings.format(1234.5), "1.2345e3");
+        assert_eq!(settings.format(0.00025), "2.5e-4");
+        assert_eq!(settings.format(-1e20), "-1e20");
+
+        settings.precision = Some(2);
+        assert_eq!(settings.format(1234.5), "1.23e3");
+    }
+
+    #[test]
+    fn test_scientific_round_trip() {
+        let settings = Settings {
+            scientific: true,
+            ..Settings::default()
+        };
+        for value in [1001.0, 2.5e-4, 6.02214076e23, -0.1] {
+            let formatted = settings.format(value);
+            assert_eq!(formatted.parse::<f64>(), Ok(value));
+        }
+    }
+
+    #[test]
+    fn test_format_engineering() {
+        assert_eq!(format_engineering(12300.0, 3), "12.3e3");
+        assert_eq!(format_engineering(0.0012, 2), "1.2e-3");
+        assert_eq!(format_engineering(0.012, 2), "12e-3");
+        assert_eq!(format_engineering(123456.0, 2), "120e3");
+        assert_eq!(format_engineering(10000.0, 1), "10e3");
+        assert_eq!(format_engineering(999.96, 3), "1.00e3");
+        assert_eq!(format_engineering(-1.5, 0), "-2e0");
+        assert_eq!(format_engineering(0.0, 2), "0.0e0");
+        assert_eq!(format_engineering(f64::INFINITY, 3), "inf");
+    }
+
+    #[test]
+    fn test_format_engineering_setting() {
+        let mut settings = Settings {
+            engineering: true,
+            scientific: true,
+            ..Settings::default()
+        };
+        assert_eq!(settings.format(12300.0), "12.3e3");
+        assert_eq!(settings.format(0.0012), "1.2e-3");
+        assert_eq!(settings.format(-2.5e7), "-25e6");
+        assert_eq!(settings.format(6.02214076e23), "602.214076e21");
+
+        settings.precision = Some(2);
+        assert_eq!(settings.format(1234.5), "1.23e3");
+        assert_eq!(settings.format(12345.0), "12.3e3");
+    }
+
+    #[test]
+    fn test_output_base_from_str() {
+        assert_eq!("hex".parse(), Ok(OutputBase::Hexadecimal));
+        assert_eq!("bin".parse(), Ok(OutputBase::Binary));
+        assert_eq!("dec".parse(), Ok(OutputBase::Decimal));
+        assert!("oct".parse::<OutputBase>().is_err());
+    }
+
+    #[test]
+    fn test_format_hexadecimal() {
+        let settings = Settings {
+            base: OutputBase::Hexadecimal,
+            ..Settings::default()
+        };
+        assert_eq!(settings.format(255.0), "0xff");
+        assert_eq!(settings.format(0.0), "0x0");
+        assert_eq!(settings.format(-1.0), "-0x1");
+    }
+
+    #[test]
+    fn test_format_binary() {
+        let settings = Settings {
+            base: OutputBase::Binary,
+            ..Settings::default()
+        };
+        assert_eq!(settings.format(10.0), "0b1010");
+        assert_eq!(settings.format(-2.0), "-0b10");
+    }
+
+    #[test]
+    fn test_format_twos_complement() {
+        let mut settings = Settings {
+            base: OutputBase::Hexadecimal,
+            ..Settings::default()
+        };
+        assert_eq!(settings.format_with_width(-1.0, Some(8)), "0xff");
+        assert_eq!(settings.format_with_width(-128.0, Some(8)), "0x80");
+        assert_eq!(settings.format_with_width(-129.0, Some(8)), "-0x81");
+        assert_eq!(settings.format_with_width(255.0, Some(8)), "0xff");
+        assert_eq!(settings.format_with_width(-1.0, None), "-0x1");
+
+        assert_eq!(settings.format_with_width(-1.0, Some(16)), "0xffff");
+        settings.base = OutputBase::Binary;
+        assert_eq!(
+            settings.format_with_width(-2.0, Some(16)),
+            "0b1111111111111110"
+        );
+        assert_eq!(
+            settings.format_with_width(-1.0, Some(64)),
+            format!("0b{}", "1".repeat(64))
+        );
+
+        settings.base = OutputBase::Decimal;
+        assert_eq!(settings.format_with_width(-1.0, Some(8)), "-1");
+    }
+
+    #[test]
+    fn test_format_exact() {
+        let large = Exact::Integer(9999999999800000000001);
+        let mut settings = Settings::default();
+        assert_eq!(settings.format_exact(&large), "9999999999800000000001");
+
+        settings.separators = true;
+        assert_eq!(
+            settings.format_exact(&large),
+            "9,999,999,999,800,000,000,001"
+        );
+        settings.precision = Some(2);
+        assert_eq!(settings.format_exact(&Exact::Integer(1234)), "1,234.00");
+
+        settings = Settings {
+            base: OutputBase::Hexadecimal,
+            ..Settings::default()
+        };
+        assert_eq!(settings.format_exact(&Exact::Integer(255)), "0xff");
+        assert_eq!(settings.format_exact(&large), "0x21e19e0c98c21523001");
+        assert_eq!(settings.format_exact(&Exact::Integer(-1)), "-0x1");
+        assert_eq!(
+            settings.format_exact_with_width(&Exact::Integer(-1), Some(8)),
+            "0xff"
+        );
+
+        settings = Settings {
+            scientific: true,
+            ..Settings::default()
+        };
+        assert_eq!(settings.format_exact(&Exact::Integer(1500)), "1.5e3");
+        settings.currency = Some("$".to_string());
+        assert_eq!(settings.format_exact(&Exact::Integer(1500)), "$1,500.00");
+    }
+
+    #[test]
+    fn test_format_exact_fraction_ignores_settings() {
+        let settings = Settings {
+            base: OutputBase::Hexadecimal,
+            precision: Some(2),
+            ..Settings::default()
+        };
+        let third = Exact::Fraction(crate::Fraction::new(1, 3).unwrap());
+        assert_eq!(settings.format_exact(&third), "1/3");
+    }
+
+    #[test]
+    fn test_format_decimal_base() {
+        let settings = Settings::default();
+        assert_eq!(settings.format(255.0), "255");
+        assert_eq!(settings.warning(2.5), None);
+    }
+
+    #[test]
+    fn test_format_non_integer_falls_back_to_decimal() {
+        let settings = Settings {
+            base: OutputBase::Hexadecimal,
+            ..Settings::default()
+        };
+        assert_eq!(settings.format(2.5), "2.5");
+        assert!(settings.warning(2.5).is_some());
+        assert_eq!(settings.warning(16.0), None);
+        assert_eq!(settings.format(1e300), 1e300.to_string());
+        assert!(settings.warning(f64::INFINITY).is_some());
+    }
+
+    #[test]
+    fn test_format_currency_positive() {
+        assert_eq!(format_currency(1234.5, "$", 2), "$1,234.50");
+        assert_eq!(format_currency(1234567.891, "$", 2), "$1,234,567.89");
+        assert_eq!(format_currency(0.5, "€", 2), "€0.50");
+        assert_eq!(format_currency(12.0, "£", 0), "£12");
+    }
+
+    #[test]
+    fn test_format_currency_negative() {
+        assert_eq!(format_currency(-1234.5, "$", 2), "-$1,234.50");
+        assert_eq!(format_currency(-0.25, "$", 2), "-$0.25");
+    }
+
+    #[test]
+    fn test_format_currency_zero() {
+        assert_eq!(format_currency(0.0, "$", 2), "$0.00");
+        assert_eq!(format_currency(-0.0, "$", 2), "$0.00");
+        assert_eq!(format_currency(-0.001, "$", 2), "$0.00");
+    }
+
+    #[test]
+    fn test_format_with_currency() {
+        let mut settings = Settings {
+            currency: Some(DEFAULT_CURRENCY_SYMBOL.to_string()),
+            ..Settings::default()
+        };
+        assert_eq!(settings.format(1234.5), "$1,234.50");
+        assert_eq!(settings.format(-10.0 / 3.0), "-$3.33");
+        assert_eq!(settings.format(f64::INFINITY), "inf");
+
+        settings.precision = Some(0);
+        assert_eq!(settings.format(1234.6), "$1,235");
+        settings.round_mode = RoundMode::Down;
+        assert_eq!(settings.format(1234.6), "$1,234");
+    }
+}
@@ -0,0 +1,128 @@
+use crate::CalcError;
+
+/// The list commands, such as `sum 1 2 3`, that aggregate the numbers after
+/// them. A new command needs an entry here and in [`aggregate`].
+pub const LIST_COMMANDS: [&str; 4] = ["sum", "mean", "median", "stddev"];
+
+/// Aggregates `values` with one of the [`LIST_COMMANDS`].
+///
+/// `median` averages the two middle values of an even number of values, and
+/// `stddev` is the population standard deviation, so a single value has a
+/// standard deviation of `0`.
+///
+/// # Arguments
+/// * `command` - The name of the list command, such as `"mean"`
+/// * `values` - The numbers to aggregate
+///
+/// # Errors
+/// * [`CalcError::InvalidCommand`] if `values` is empty or `command` is not
+///   a list command
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::aggregate;
+///
+/// assert_eq!(aggregate("sum", &[1.0, 2.0, 3.0, 4.0]), Ok(10.0));
+/// assert_eq!(aggregate("mean", &[1.0, 2.0, 3.0, 4.0]), Ok(2.5));
+/// ```
+pub fn aggregate(command: &str, values: &[f64]) -> Result<f64, CalcError> {
+    if values.is_empty() {
+        return Err(CalcError::InvalidCommand(format!(
+            "Usage: {} NUMBERS..., e.g. {} 1 2 3",
+            command, command
+        )));
+    }
+    match command {
+        "sum" => Ok(values.iter().sum()),
+        "mean" => Ok(mean(values)),
+        "median" => Ok(median(values)),
+        "stddev" => Ok(standard_deviation(values)),
+        _ => Err(CalcError::InvalidCommand(format!(
+            "Unknown list command '{}'",
+            command
+        ))),
+    }
+}
+
+/// The arithmetic mean of a non-empty list.
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// The middle value of a non-empty list once it is sorted.
+fn median(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let middle = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[middle - 1] + sorted[middle]) / 2.0
+    } else {
+        sorted[middle]
+    }
+}
+
+/// The population standard deviation of a non-empty list.
+fn standard_deviation(values: &[f64]) -> f64 {
+    let mean = mean(values);
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / values.len() as f64;
+    variance.sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx_eq;
+
+    #[test]
+    fn test_sum() {
+        assert_eq!(aggregate("sum", &[1.0, 2.0, 3.0, 4.0]), Ok(10.0));
+        assert_eq!(aggregate("sum", &[-1.5]), Ok(-1.5));
+    }
+
+    #[test]
+    fn test_mean() {
+        assert_eq!(aggregate("mean", &[1.0, 2.0, 3.0, 4.0]), Ok(2.5));
+        assert_eq!(aggregate("mean", &[7.0]), Ok(7.0));
+    }
+
+    #[test]
+    fn test_median() {
+        assert_eq!(aggregate("median", &[1.0, 2.0, 3.0, 4.0]), Ok(2.5));
+        assert_eq!(aggregate("median", &[9.0, 1.0, 5.0]), Ok(5.0));
+        assert_eq!(aggregate("median", &[4.0]), Ok(4.0));
+    }
+
+    #[test]
+    fn test_standard_deviation() {
+        let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        assert_eq!(aggregate("stddev", &values), Ok(2.0));
+        assert!(approx_eq(
+            aggregate("stddev", &[1.0, 2.0, 3.0, 4.0]).unwrap(),
+            1.25f64.sqrt(),
+            1e-10
+        ));
+        assert_eq!(aggregate("stddev", &[3.5]), Ok(0.0));
+    }
+
+    #[test]
+    fn test_empty_list() {
+        for command in LIST_COMMANDS {
+            assert!(matches!(
+                aggregate(command, &[]),
+                Err(CalcError::InvalidCommand(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_unknown_command() {
+        assert!(matches!(
+            aggregate("mode", &[1.0]),
+            Err(CalcError::InvalidCommand(_))
+        ));
+    }
+}
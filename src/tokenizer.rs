@@ -0,0 +1,556 @@
+//! Splits expressions into [`Token`]s.
+//!
+//! The tokenizer is shared by the parser and the exact evaluation modes, and
+//! is available to library users who want to inspect an expression.
+
+use crate::{is_function, large_integer, parse_number, AngleMode, CalcError, Locale};
+
+/// A single lexical unit of an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    /// A number literal, already converted, e.g. `2.5` or `0xff`
+    Number(f64),
+    /// An integer literal beyond 2^53, such as `9007199254740993`, which an
+    /// `f64` can't hold exactly. It is kept exact for integer mode.
+    Integer(i128),
+    /// An operator symbol such as `+`, `//` or `<=`, or the keyword `xor`
+    Operator(&'static str),
+    /// A name such as `pi`, `sqrt`, `ans` or a variable
+    Identifier(String),
+    /// `(`
+    LeftParen,
+    /// `)`
+    RightParen,
+    /// `,` between function arguments
+    Comma,
+}
+
+/// Operators recognised by the tokenizer. Longer symbols come first so that
+/// `//` is not read as two `/`. Exclusive or is the keyword `xor`.
+const OPERATORS: [&str; 18] = [
+    "//", "<<", ">>", "<=", ">=", "==", "!=", "+", "-", "*", "/", "%", "^", "!", "&", "|", "<", ">",
+];
+
+/// Symbols read as another operator, such as `×` for `*`.
+pub(crate) const OPERATOR_ALIASES: [(char, &str); 2] = [('×', "*"), ('÷', "/")];
+
+/// Splits an expression into tokens, reading numbers with a decimal point.
+///
+/// This is [`tokenize_with`] for [`Locale::Us`].
+///
+/// # Errors
+/// * [`CalcError::ParseNumber`] for a malformed number such as `1.2.3` or `0xZ`,
+///   or an angle such as `10d75m` with too many minutes or seconds
+/// * [`CalcError::NotANumber`] for a number that reads as NaN
+/// * [`CalcError::UnexpectedToken`] for a character that can't start a
+///   token, such as `$`
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::tokenizer::{tokenize, Token};
+///
+/// assert_eq!(
+///     tokenize("2 * pi"),
+///     Ok(vec![
+///         Token::Number(2.0),
+///         Token::Operator("*"),
+///         Token::Identifier("pi".to_string()),
+///     ])
+/// );
+/// ```
+pub fn tokenize(input: &str) -> Result<Vec<Token>, CalcError> {
+    tokenize_with(input, Locale::Us)
+}
+
+/// Splits an expression into tokens, reading numbers as usual in `locale`.
+///
+/// Whitespace only separates tokens. Every `-` becomes an operator token;
+/// whether it means negation or subtraction is decided by the parser.
+/// Numbers may be written in decimal or as `0x` hexadecimal and `0b` binary
+/// integers, and decimal numbers may have an exponent as in `2.5e-4`. With
+/// [`Locale::Eu`] a comma between two digits is read as the decimal point.
+///
+/// An angle in degrees, minutes and seconds such as `30d15m20s` becomes one
+/// number in decimal degrees. Either the minutes or the seconds may be left
+/// out, so `30d30m` is `30.5`, but each must be below 60. A bare `30d` is not
+/// an angle but the product `30 * d`.
+///
+/// `×` and `÷` are read as `*` and `/`, and so is a name `x` or `X` where an
+/// operator is expected, as in `5 x 5`; elsewhere `x` stays a name, so it can
+/// still be used as a variable.
+///
+/// # Errors
+/// The same as [`tokenize`].
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::tokenizer::{tokenize_with, Token};
+/// use rust_calculator_cli::Locale;
+///
+/// assert_eq!(tokenize_with("5,5", Locale::Eu), Ok(vec![Token::Number(5.5)]));
+/// ```
+pub fn tokenize_with(input: &str, locale: Locale) -> Result<Vec<Token>, CalcError> {
+    Ok(
+        tokenize_with_positions(input, locale, true, AngleMode::Degrees)?
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect(),
+    )
+}
+
+/// Splits an expression into tokens like [`tokenize_with`], pairing each
+/// token with its byte offset in `input`. Without `aliases`, `x`, `X`, `×`
+/// and `÷` are not read as operators. An angle such as `30d15m` becomes a
+/// number in `angle_mode`, so it means the same in `mode rad` and `mode deg`.
+pub(crate) fn tokenize_with_positions(
+    input: &str,
+    locale: Locale,
+    aliases: bool,
+    angle_mode: AngleMode,
+) -> Result<Vec<(Token, usize)>, CalcError> {
+    let mut tokens = Vec::new();
+    let mut rest = input.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        let position = input.len() - rest.len();
+        let token;
+        if c.is_ascii_digit() || c == '.' {
+            let has_radix_prefix = ["0x", "0X", "0b", "0B"]
+                .iter()
+                .any(|prefix| rest.starts_with(prefix));
+            let angle_end = dms_length(rest).filter(|_| !has_radix_prefix);
+            let end = if has_radix_prefix {
+                rest[2..]
+                    .find(|d: char| !(d.is_ascii_alphanumeric() || d == '_'))
+                    .map_or(rest.len(), |i| i + 2)
+            } else {
+                angle_end.unwrap_or_else(|| decimal_length(rest, locale))
+            };
+            let number = match angle_end {
+                Some(_) => parse_dms(&rest[..end]).map(|degrees| angle_mode.from_degrees(degrees)),
+                None => parse_number(&rest[..end].replace(',', ".")),
+            }
+            .map_err(|e| e.at(position))?;
+            token = match large_integer(&rest[..end]).filter(|_| angle_end.is_none()) {
+                Some(integer) => Token::Integer(integer),
+                None => Token::Number(number),
+            };
+            rest = &rest[end..];
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let end = rest
+                .find(|d: char| !(d.is_ascii_alphanumeric() || d == '_'))
+                .unwrap_or(rest.len());
+            token = match &rest[..end] {
+                "xor" => Token::Operator("xor"),
+                "x" | "X" if aliases && is_operator_position(&tokens, input, position) => {
+                    Token::Operator("*")
+                }
+                name => Token::Identifier(name.to_string()),
+            };
+            rest = &rest[end..];
+        } else if c == '(' {
+            token = Token::LeftParen;
+            rest = &rest[1..];
+        } else if c == ')' {
+            token = Token::RightParen;
+            rest = &rest[1..];
+        } else if c == ',' {
+            token = Token::Comma;
+            rest = &rest[1..];
+        } else if let Some(&(_, operator)) = OPERATOR_ALIASES
+            .iter()
+            .find(|(alias, _)| aliases && c == *alias)
+        {
+            token = Token::Operator(operator);
+            rest = &rest[c.len_utf8()..];
+        } else if let Some(operator) = OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+            token = Token::Operator(operator);
+            rest = &rest[operator.len()..];
+        } else {
+            return Err(CalcError::UnexpectedToken {
+                token: format!("character '{}'", c),
+                position: Some(position),
+            });
+        }
+
+        tokens.push((token, position));
+        rest = rest.trim_start();
+    }
+
+    Ok(tokens)
+}
+
+/// Returns `true` if a name at `position` stands where an operator is
+/// expected: after a space that follows a number, a `)` or a name other than
+/// a function. An `x` there is read as multiplication, as in `5 x 5`, while
+/// `x = 5`, `2 * x`, `2x` and `sqrt x` keep `x` as a variable.
+fn is_operator_position(tokens: &[(Token, usize)], input: &str, position: usize) -> bool {
+    let after_space = input[..position].ends_with(char::is_whitespace);
+    let after_operand = match tokens.last() {
+        Some((Token::Number(_) | Token::Integer(_) | Token::RightParen, _)) => true,
+        Some((Token::Identifier(name), _)) => !is_function(name),
+        _ => false,
+    };
+    after_space && after_operand
+}
+
+/// Returns the length of the decimal number at the start of `input`.
+fn decimal_length(input: &str, locale: Locale) -> usize {
+    let bytes = input.as_bytes();
+    let mut end = 0;
+    while end < bytes.len() {
+        let is_decimal_comma = locale == Locale::Eu
+            && bytes[end] == b','
+            && end > 0
+            && bytes[end - 1].is_ascii_digit()
+            && bytes.get(end + 1).is_some_and(u8::is_ascii_digit);
+        let is_digit_separator = bytes[end] == b'_';
+        if !(bytes[end].is_ascii_digit()
+            || bytes[end] == b'.'
+            || is_decimal_comma
+            || is_digit_separator)
+        {
+            break;
+        }
+        end += 1;
+    }
+
+    // An exponent such as `e3` or `E-4` belongs to the number. Without digits
+    // the `e` is left for the name that follows, so `2e` is `2 * e`.
+    let sign = usize::from(matches!(bytes.get(end + 1), Some(b'+' | b'-')));
+    let has_exponent = end > 0
+        && matches!(bytes.get(end), Some(b'e' | b'E'))
+        && bytes.get(end + 1 + sign).is_some_and(u8::is_ascii_digit);
+    if has_exponent {
+        end += 1 + sign;
+        while bytes
+            .get(end)
+            .is_some_and(|&byte| byte.is_ascii_digit() || byte == b'_')
+        {
+            end += 1;
+        }
+    }
+    end
+}
+
+/// The length of an angle such as `30d15m20s` at the start of `input`, or
+/// `None` if it doesn't start with one.
+///
+/// Each part is a number followed by its unit `d`, `m` or `s`. Either the
+/// minutes or the seconds may be left out, but not both, so that `3d` stays
+/// the product `3 * d`. A letter, digit or `_` right after the angle means it
+/// is something else, such as the product `2 * d2`.
+fn dms_length(input: &str) -> Option<usize> {
+    let bytes = input.as_bytes();
+    let digits_end = |start: usize| {
+        start
+            + bytes[start..]
+                .iter()
+                .take_while(|&&byte| byte.is_ascii_digit() || byte == b'.' || byte == b'_')
+                .count()
+    };
+
+    let mut end = digits_end(0);
+    if end == 0 || bytes.get(end) != Some(&b'd') {
+        return None;
+    }
+    end += 1;
+    let degrees_end = end;
+    for unit in [b'm', b's'] {
+        let number_end = digits_end(end);
+        if number_end > end && bytes.get(number_end) == Some(&unit) {
+            end = number_end + 1;
+        }
+    }
+    if end == degrees_end {
+        return None;
+    }
+    let followed_by_name = bytes
+        .get(end)
+        .is_some_and(|&byte| byte.is_ascii_alphanumeric() || byte == b'_');
+    (!followed_by_name).then_some(end)
+}
+
+/// Converts an angle found by [`dms_length`] to decimal degrees.
+fn parse_dms(literal: &str) -> Result<f64, CalcError> {
+    let error = || CalcError::ParseNumber {
+        token: literal.to_string(),
+        position: None,
+    };
+    let mut degrees = 0.0;
+    let mut rest = literal;
+    for (unit, per_degree) in [('d', 1.0), ('m', 60.0), ('s', 3600.0)] {
+        if let Some((number, after)) = rest.split_once(unit) {
+            let value = parse_number(number).map_err(|_| error())?;
+            if per_degree > 1.0 && value >= 60.0 {
+                return Err(error());
+            }
+            degrees += value / per_degree;
+            rest = after;
+        }
+    }
+    Ok(degrees)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_degrees_minutes_seconds() {
+        assert_eq!(tokenize("30d30m"), Ok(vec![Token::Number(30.5)]));
+        assert_eq!(
+            tokenize("1d30s"),
+            Ok(vec![Token::Number(1.0 + 30.0 / 3600.0)])
+        );
+        assert_eq!(
+            tokenize("30d15m20s + 1"),
+            Ok(vec![
+                Token::Number(30.0 + 15.0 / 60.0 + 20.0 / 3600.0),
+                Token::Operator("+"),
+                Token::Number(1.0),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_tokenize_rejects_invalid_degrees_minutes_seconds() {
+        assert_eq!(
+            tokenize("1 + 10d75m"),
+            Err(CalcError::ParseNumber {
+                token: "10d75m".to_string(),
+                position: Some(4)
+            })
+        );
+        assert!(tokenize("10d5m60s").is_err());
+        // Not an angle, so the number and the name stay separate.
+        assert_eq!(
+            tokenize("3d"),
+            Ok(vec![Token::Number(3.0), Token::Identifier("d".to_string())])
+        );
+        assert_eq!(
+            tokenize("2dx"),
+            Ok(vec![
+                Token::Number(2.0),
+                Token::Identifier("dx".to_string())
+            ])
+        );
+    }
+
+    #[test]
+    fn test_tokenize_numbers() {
+        assert_eq!(
+            tokenize("42 2.5 .5 0xff 0b101"),
+            Ok(vec![
+                Token::Number(42.0),
+                Token::Number(2.5),
+                Token::Number(0.5),
+                Token::Number(255.0),
+                Token::Number(5.0),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_tokenize_large_integers_exactly() {
+        assert_eq!(
+            tokenize("9007199254740993 + 9007199254740992"),
+            Ok(vec![
+                Token::Integer(9007199254740993),
+                Token::Operator("+"),
+                Token::Number(9007199254740992.0),
+            ])
+        );
+        assert_eq!(
+            tokenize("0xffff_ffff_ffff_ffff"),
+            Ok(vec![Token::Integer(u64::MAX.into())])
+        );
+        assert_eq!(tokenize("1e20"), Ok(vec![Token::Number(1e20)]));
+    }
+
+    #[test]
+    fn test_tokenize_scientific_notation() {
+        assert_eq!(
+            tokenize_with("1e3", Locale::Us),
+            Ok(vec![Token::Number(1000.0)])
+        );
+        assert_eq!(
+            tokenize_with("2.5e-4", Locale::Us),
+            Ok(vec![Token::Number(2.5e-4)])
+        );
+        assert_eq!(
+            tokenize_with("1E6", Locale::Us),
+            Ok(vec![Token::Number(1e6)])
+        );
+        assert_eq!(
+            tokenize_with("1e+2", Locale::Us),
+            Ok(vec![Token::Number(100.0)])
+        );
+    }
+
+    #[test]
+    fn test_tokenize_operators() {
+        assert_eq!(
+            tokenize("1+2//3<=4 xor 5<<6!"),
+            Ok(vec![
+                Token::Number(1.0),
+                Token::Operator("+"),
+                Token::Number(2.0),
+                Token::Operator("//"),
+                Token::Number(3.0),
+                Token::Operator("<="),
+                Token::Number(4.0),
+                Token::Operator("xor"),
+                Token::Number(5.0),
+                Token::Operator("<<"),
+                Token::Number(6.0),
+                Token::Operator("!"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_tokenize_identifiers_and_punctuation() {
+        assert_eq!(
+            tokenize("log(x_1, e)"),
+            Ok(vec![
+                Token::Identifier("log".to_string()),
+                Token::LeftParen,
+                Token::Identifier("x_1".to_string()),
+                Token::Comma,
+                Token::Identifier("e".to_string()),
+                Token::RightParen,
+            ])
+        );
+    }
+
+    #[test]
+    fn test_tokenize_operator_aliases() {
+        let product = Ok(vec![
+            Token::Number(5.0),
+            Token::Operator("*"),
+            Token::Number(5.0),
+        ]);
+        assert_eq!(tokenize("5 x 5"), product);
+        assert_eq!(tokenize("5 X 5"), product);
+        assert_eq!(tokenize("5 × 5"), product);
+        assert_eq!(tokenize("5×5"), product);
+        assert_eq!(
+            tokenize("10 ÷ 4"),
+            Ok(vec![
+                Token::Number(10.0),
+                Token::Operator("/"),
+                Token::Number(4.0),
+            ])
+        );
+        assert_eq!(
+            tokenize("(1) x y"),
+            Ok(vec![
+                Token::LeftParen,
+                Token::Number(1.0),
+                Token::RightParen,
+                Token::Operator("*"),
+                Token::Identifier("y".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_tokenize_x_as_name() {
+        let name = || Token::Identifier("x".to_string());
+        assert_eq!(tokenize("x"), Ok(vec![name()]));
+        assert_eq!(
+            tokenize("2 * x"),
+            Ok(vec![Token::Number(2.0), Token::Operator("*"), name()])
+        );
+        assert_eq!(tokenize("2x"), Ok(vec![Token::Number(2.0), name()]));
+        assert_eq!(
+            tokenize("sqrt x"),
+            Ok(vec![Token::Identifier("sqrt".to_string()), name()])
+        );
+    }
+
+    #[test]
+    fn test_tokenize_angle_in_radians() {
+        let tokens: Vec<Token> =
+            tokenize_with_positions("30d30m", Locale::Us, true, AngleMode::Radians)
+                .unwrap()
+                .into_iter()
+                .map(|(token, _)| token)
+                .collect();
+        assert_eq!(tokens, [Token::Number(30.5_f64.to_radians())]);
+    }
+
+    #[test]
+    fn test_tokenize_without_aliases() {
+        let tokens: Vec<Token> =
+            tokenize_with_positions("5 x 5", Locale::Us, false, AngleMode::Degrees)
+                .unwrap()
+                .into_iter()
+                .map(|(token, _)| token)
+                .collect();
+        assert_eq!(
+            tokens,
+            [
+                Token::Number(5.0),
+                Token::Identifier("x".to_string()),
+                Token::Number(5.0),
+            ]
+        );
+        assert!(matches!(
+            tokenize_with_positions("5 × 5", Locale::Us, false, AngleMode::Degrees),
+            Err(CalcError::UnexpectedToken { .. })
+        ));
+    }
+
+    #[test]
+    fn test_tokenize_decimal_comma() {
+        assert_eq!(
+            tokenize_with("5,5", Locale::Eu),
+            Ok(vec![Token::Number(5.5)])
+        );
+        assert_eq!(
+            tokenize("5,5"),
+            Ok(vec![Token::Number(5.0), Token::Comma, Token::Number(5.0)])
+        );
+    }
+
+    #[test]
+    fn test_tokenize_positions() {
+        let positions: Vec<usize> =
+            tokenize_with_positions(" 12 +  x", Locale::Us, true, AngleMode::Degrees)
+                .unwrap()
+                .into_iter()
+                .map(|(_, position)| position)
+                .collect();
+        assert_eq!(positions, [1, 4, 7]);
+    }
+
+    #[test]
+    fn test_tokenize_illegal_character() {
+        assert_eq!(
+            tokenize("2 $ 3"),
+            Err(CalcError::UnexpectedToken {
+                token: "character '$'".to_string(),
+                position: Some(2),
+            })
+        );
+        assert!(matches!(
+            tokenize("1.2.3 + 2"),
+            Err(CalcError::ParseNumber { .. })
+        ));
+    }
+
+    #[test]
+    fn test_tokenize_e_without_exponent() {
+        assert_eq!(
+            tokenize("2e + 1"),
+            Ok(vec![
+                Token::Number(2.0),
+                Token::Identifier("e".to_string()),
+                Token::Operator("+"),
+                Token::Number(1.0),
+            ])
+        );
+    }
+}
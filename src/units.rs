@@ -0,0 +1,210 @@
+use std::fmt;
+
+use crate::CalcError;
+
+/// The kind of quantity a unit measures. Only units of the same dimension
+/// can be converted into each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    Length,
+    Weight,
+    Temperature,
+}
+
+impl fmt::Display for Dimension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Dimension::Length => write!(f, "length"),
+            Dimension::Weight => write!(f, "weight"),
+            Dimension::Temperature => write!(f, "temperature"),
+        }
+    }
+}
+
+/// A unit known to `convert`.
+///
+/// A value `v` in this unit is `(v + offset) * factor / divisor` in the base
+/// unit of its dimension: metres, kilograms or degrees Celsius. The offset is
+/// only needed for temperature scales whose zero differs from that of
+/// Celsius. The divisor keeps ratios such as `5 / 9` for Fahrenheit exact, so
+/// that `0 c` converts to exactly `32 f`.
+struct Unit {
+    name: &'static str,
+    dimension: Dimension,
+    factor: f64,
+    divisor: f64,
+    offset: f64,
+}
+
+/// All supported units. A new unit only needs an entry here.
+const UNITS: [Unit; 9] = [
+    Unit {
+        name: "m",
+        dimension: Dimension::Length,
+        factor: 1.0,
+        divisor: 1.0,
+        offset: 0.0,
+    },
+    Unit {
+        name: "km",
+        dimension: Dimension::Length,
+        factor: 1000.0,
+        divisor: 1.0,
+        offset: 0.0,
+    },
+    Unit {
+        name: "ft",
+        dimension: Dimension::Length,
+        factor: 0.3048,
+        divisor: 1.0,
+        offset: 0.0,
+    },
+    Unit {
+        name: "mi",
+        dimension: Dimension::Length,
+        factor: 1609.344,
+        divisor: 1.0,
+        offset: 0.0,
+    },
+    Unit {
+        name: "kg",
+        dimension: Dimension::Weight,
+        factor: 1.0,
+        divisor: 1.0,
+        offset: 0.0,
+    },
+    Unit {
+        name: "lb",
+        dimension: Dimension::Weight,
+        factor: 0.45359237,
+        divisor: 1.0,
+        offset: 0.0,
+    },
+    Unit {
+        name: "k",
+        dimension: Dimension::Temperature,
+        factor: 1.0,
+        divisor: 1.0,
+        offset: -273.15,
+    },
+    Unit {
+        name: "c",
+        dimension: Dimension::Temperature,
+        factor: 1.0,
+        divisor: 1.0,
+        offset: 0.0,
+    },
+    Unit {
+        name: "f",
+        dimension: Dimension::Temperature,
+        factor: 5.0,
+        divisor: 9.0,
+        offset: -32.0,
+    },
+];
+
+/// Looks up a unit by name, ignoring case.
+fn unit(name: &str) -> Result<&'static Unit, CalcError> {
+    UNITS
+        .iter()
+        .find(|unit| unit.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| {
+            let names: Vec<&str> = UNITS.iter().map(|unit| unit.name).collect();
+            CalcError::InvalidCommand(format!("Unknown unit '{}'. Use {}", name, names.join(", ")))
+        })
+}
+
+/// Converts `value` from the unit `from` to the unit `to`.
+///
+/// Supported units are `m`, `km`, `ft` and `mi` for length, `kg` and `lb`
+/// for weight, and `c`, `f` and `k` for temperature. Unit names are case
+/// insensitive.
+///
+/// # Arguments
+/// * `value` - The amount to convert
+/// * `from` - The unit of `value`
+/// * `to` - The unit to convert to
+///
+/// # Returns
+/// The amount in the unit `to`.
+///
+/// # Errors
+/// * [`CalcError::InvalidCommand`] if a unit is unknown or the units measure
+///   different things, e.g. `km` and `kg`
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::convert;
+///
+/// assert_eq!(convert(1.0, "km", "m"), Ok(1000.0));
+/// assert!(convert(1.0, "km", "kg").is_err());
+/// ```
+pub fn convert(value: f64, from: &str, to: &str) -> Result<f64, CalcError> {
+    let (from_unit, to_unit) = (unit(from)?, unit(to)?);
+    if from_unit.dimension != to_unit.dimension {
+        return Err(CalcError::InvalidCommand(format!(
+            "Cannot convert {} ({}) to {} ({})",
+            from, from_unit.dimension, to, to_unit.dimension
+        )));
+    }
+    let base = (value + from_unit.offset) * from_unit.factor / from_unit.divisor;
+    Ok(base * to_unit.divisor / to_unit.factor - to_unit.offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx_eq;
+
+    const EPSILON: f64 = 1e-9;
+
+    #[test]
+    fn test_convert_by_factor() {
+        assert!(approx_eq(
+            convert(100.0, "km", "mi").unwrap(),
+            62.13711922373339,
+            EPSILON
+        ));
+        assert!(approx_eq(
+            convert(1.0, "mi", "ft").unwrap(),
+            5280.0,
+            EPSILON
+        ));
+        assert!(approx_eq(
+            convert(1.0, "kg", "lb").unwrap(),
+            2.204622621848776,
+            EPSILON
+        ));
+    }
+
+    #[test]
+    fn test_convert_temperature() {
+        assert_eq!(convert(100.0, "c", "f"), Ok(212.0));
+        assert_eq!(convert(0.0, "c", "f"), Ok(32.0));
+        assert_eq!(convert(32.0, "F", "C"), Ok(0.0));
+        assert_eq!(convert(-40.0, "f", "c"), Ok(-40.0));
+        assert_eq!(convert(-40.0, "c", "f"), Ok(-40.0));
+        assert_eq!(convert(0.0, "k", "c"), Ok(-273.15));
+        assert_eq!(convert(0.0, "c", "k"), Ok(273.15));
+        assert_eq!(convert(32.0, "f", "k"), Ok(273.15));
+        assert_eq!(convert(37.0, "c", "f"), Ok(98.6));
+    }
+
+    #[test]
+    fn test_convert_unknown_unit() {
+        assert!(matches!(
+            convert(1.0, "km", "parsec"),
+            Err(CalcError::InvalidCommand(message)) if message.starts_with("Unknown unit 'parsec'")
+        ));
+    }
+
+    #[test]
+    fn test_convert_mismatched_dimensions() {
+        assert_eq!(
+            convert(1.0, "km", "kg"),
+            Err(CalcError::InvalidCommand(
+                "Cannot convert km (length) to kg (weight)".to_string()
+            ))
+        );
+    }
+}
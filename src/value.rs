@@ -0,0 +1,41 @@
+use std::fmt;
+
+/// The result of an expression that may be a comparison.
+///
+/// Arithmetic produces a [`Value::Number`]; an expression whose top-level
+/// operator is a comparison such as `5 > 3` produces a [`Value::Bool`].
+///
+/// # Examples
+/// ```
+/// use rust_calculator_cli::Value;
+///
+/// assert_eq!(Value::Bool(true).to_string(), "true");
+/// assert_eq!(Value::Number(2.5).to_string(), "2.5");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    /// A number, from any expression without a comparison
+    Number(f64),
+    /// The outcome of a comparison
+    Bool(bool),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(number) => write!(f, "{}", number),
+            Value::Bool(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Value::Number(10.0).to_string(), "10");
+        assert_eq!(Value::Bool(false).to_string(), "false");
+    }
+}
@@ -0,0 +1,854 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+
+/// Runs the calculator with `HOME` set to an empty directory, so a
+/// `~/.calculatorrc` of the developer running the tests is not read.
+/// Tests of the configuration file set `HOME` again.
+fn calculator() -> Command {
+    let home = std::env::temp_dir().join(format!("calc_empty_home_{}", std::process::id()));
+    std::fs::create_dir_all(&home).unwrap();
+    let mut command = Command::new(env!("CARGO_BIN_EXE_rust-calculator-cli"));
+    command.env("HOME", home);
+    command
+}
+
+#[test]
+fn test_single_expression_argument() {
+    let output = calculator().arg("5 + 5").output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "10\n");
+}
+
+#[test]
+fn test_expression_split_across_arguments() {
+    let output = calculator()
+        .args(["2", "+", "3", "*", "4"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "14\n");
+}
+
+#[test]
+fn test_expression_from_env() {
+    let output = calculator()
+        .arg("--from-env")
+        .env("CALC_EXPR", "5 + 5")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "10\n");
+
+    let output = calculator()
+        .args(["--from-env", "--json"])
+        .env("CALC_EXPR", "2 ^ 10")
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "{\"expression\":\"2 ^ 10\",\"result\":1024.0}\n"
+    );
+}
+
+#[test]
+fn test_expression_from_unset_env() {
+    let output = calculator()
+        .arg("--from-env")
+        .env_remove("CALC_EXPR")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Error: Option '--from-env' needs the CALC_EXPR environment variable to be set\n"
+    );
+
+    let output = calculator()
+        .args(["--from-env", "1 + 1"])
+        .env("CALC_EXPR", "5 + 5")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn test_max_input_len() {
+    let output = calculator()
+        .args(["--max-input-len", "5", "1 + 2"])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+
+    let output = calculator()
+        .args(["--max-input-len", "5", "1 + 20"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Error: Input too long: the limit is 5 characters\n"
+    );
+
+    let output = calculator()
+        .args(["--max-input-len", "0", "1"])
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid length '0'"));
+}
+
+#[test]
+fn test_deeply_nested_expression() {
+    let nested = format!("{}1{}", "(".repeat(1000), ")".repeat(1000));
+    let output = calculator().arg(&nested).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Error: Expression too deeply nested: the limit is 256 levels\n"
+    );
+
+    let output = calculator()
+        .args(["--max-depth", "2", "((1))"])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n");
+    let output = calculator()
+        .args(["--max-depth", "2", "(((1)))"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn test_invalid_expression_argument() {
+    let output = calculator().arg("5 +").output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Error:"));
+}
+
+#[test]
+fn test_division_by_zero_argument_exit_code() {
+    let output = calculator().arg("5 / 0").output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Error: Cannot divide by zero\n"
+    );
+
+    let output = calculator().arg("5 / 2").output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn test_verbose_flag() {
+    let output = calculator()
+        .args(["--verbose", "2 + 3 * 4 - 1"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "3 * 4 = 12\n2 + 12 = 14\n14 - 1 = 13\n13\n"
+    );
+}
+
+#[test]
+fn test_allow_inf_flag() {
+    let output = calculator()
+        .args(["--allow-inf", "inf - 1"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "inf\n");
+
+    let output = calculator().arg("inf - 1").output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    let output = calculator()
+        .args(["--allow-inf", "inf - inf"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Error: inf - inf is undefined\n"
+    );
+}
+
+#[test]
+fn test_comparison_argument() {
+    let output = calculator().arg("5 > 3").output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "true\n");
+
+    let output = calculator().args(["--json", "1 == 2"]).output().unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "{\"expression\":\"1 == 2\",\"result\":false}\n"
+    );
+}
+
+#[test]
+fn test_json_result() {
+    let output = calculator().args(["--json", "5 + 5"]).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "{\"expression\":\"5 + 5\",\"result\":10.0}\n"
+    );
+}
+
+#[test]
+fn test_json_error() {
+    let output = calculator().args(["5 / 0", "--json"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "{\"expression\":\"5 / 0\",\"error\":\"Cannot divide by zero\"}\n"
+    );
+}
+
+#[test]
+fn test_format_flag() {
+    let output = calculator()
+        .args(["--format", "plain", "5 + 5"])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "10\n");
+
+    let output = calculator()
+        .args(["--format", "json", "5 + 5"])
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "{\"expression\":\"5 + 5\",\"result\":10.0}\n"
+    );
+
+    let output = calculator()
+        .args(["--format", "csv", "max(1, 2) * 5"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "\"max(1, 2) * 5\",10\n"
+    );
+
+    let output = calculator()
+        .args(["--format", "xml", "5 + 5"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown format 'xml'"));
+}
+
+#[test]
+fn test_file_batch_formats() {
+    let path = std::env::temp_dir().join(format!("calc_formats_{}.txt", std::process::id()));
+    std::fs::write(&path, "1 + 1\nx = 4\n5 / 0\n").unwrap();
+
+    let csv = calculator()
+        .args(["--format", "csv", "--file"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    let json = calculator()
+        .args(["--format", "json", "--file"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(csv.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&csv.stdout),
+        "expression,result\n1 + 1,2\nx,4\n"
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&csv.stderr),
+        "Error: 5 / 0: Cannot divide by zero\nEvaluated 3 expressions, 1 error\n"
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&json.stdout),
+        "{\"expression\":\"1 + 1\",\"result\":2.0}\n\
+         {\"expression\":\"x\",\"result\":4.0}\n\
+         {\"expression\":\"5 / 0\",\"error\":\"Cannot divide by zero\"}\n"
+    );
+}
+
+#[test]
+fn test_precision_flag() {
+    let output = calculator()
+        .args(["--precision", "3", "10 / 3"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3.333\n");
+}
+
+#[test]
+fn test_precision_flag_with_json() {
+    let output = calculator()
+        .args(["--json", "--precision", "2", "2 / 3"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "{\"expression\":\"2 / 3\",\"result\":0.67}\n"
+    );
+}
+
+#[test]
+fn test_precision_flag_out_of_range() {
+    for precision in ["18", "-1", "many"] {
+        let output = calculator()
+            .args(["--precision", precision, "10 / 3"])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid precision"));
+    }
+}
+
+#[test]
+fn test_precision_flag_needs_value() {
+    let output = calculator().arg("--precision").output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("needs a number"));
+}
+
+#[test]
+fn test_check_valid_expression() {
+    let output = calculator().args(["--check", "5 / 0"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_check_invalid_expression() {
+    let output = calculator().args(["--check", "5 + "]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Error: Unexpected end of input\n"
+    );
+}
+
+#[test]
+fn test_check_uses_options() {
+    let output = calculator()
+        .args(["--max-depth", "3", "--check", "((((1))))"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Error: Expression too deeply nested: the limit is 3 levels\n"
+    );
+
+    let output = calculator()
+        .args(["--allow-inf", "--check", "inf + 1"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let output = calculator().args(["--check", "inf + 1"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn test_unknown_option() {
+    let output = calculator().args(["--jsn", "5 + 5"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown option '--jsn'"));
+}
+
+#[test]
+fn test_config_file() {
+    let home = std::env::temp_dir().join(format!("calc_home_{}", std::process::id()));
+    std::fs::create_dir_all(&home).unwrap();
+    std::fs::write(
+        home.join(".calculatorrc"),
+        "# defaults\nprecision = 2\nangle_mode = deg\nfont = big\n",
+    )
+    .unwrap();
+
+    let output = calculator()
+        .arg("10 / 3 + sin(90)")
+        .env("HOME", &home)
+        .output()
+        .unwrap();
+    let flagged = calculator()
+        .args(["--precision", "4", "10 / 3"])
+        .env("HOME", &home)
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(&home).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "4.33\n");
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains(".calculatorrc: line 4: Unknown setting 'font'"));
+    assert_eq!(String::from_utf8_lossy(&flagged.stdout), "3.3333\n");
+}
+
+#[test]
+fn test_eval_statements() {
+    let output = calculator()
+        .args(["--eval", "x = 5; x * 2; ans + x;"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "10\n15\n");
+
+    let output = calculator()
+        .args(["--eval", "x = 5; x / 0; x * 2"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Error: x / 0: Cannot divide by zero\n"
+    );
+}
+
+#[test]
+fn test_file_batch_mode() {
+    let path = std::env::temp_dir().join(format!("calc_batch_{}.txt", std::process::id()));
+    std::fs::write(&path, "# a comment\n1 + 1\n\nx = 4\n5 / 0\nx * 2\n").unwrap();
+
+    let output = calculator().arg("--file").arg(&path).output().unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "1 + 1 = 2\nx = 4\nx * 2 = 8\nEvaluated 4 expressions, 1 error\n"
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Error: 5 / 0: Cannot divide by zero\n"
+    );
+}
+
+#[test]
+fn test_quiet_file_batch_mode() {
+    let path = std::env::temp_dir().join(format!("calc_quiet_{}.txt", std::process::id()));
+    std::fs::write(&path, "1 + 1\nx = 4\n5 / 0\nx * 2\n").unwrap();
+
+    let normal = calculator().arg("--file").arg(&path).output().unwrap();
+    let quiet = calculator()
+        .args(["--quiet", "--file"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        String::from_utf8_lossy(&normal.stdout),
+        "1 + 1 = 2\nx = 4\nx * 2 = 8\nEvaluated 4 expressions, 1 error\n"
+    );
+    assert_eq!(quiet.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&quiet.stdout), "2\n4\n8\n");
+    assert_eq!(
+        String::from_utf8_lossy(&quiet.stderr),
+        "Error: 5 / 0: Cannot divide by zero\nEvaluated 4 expressions, 1 error\n"
+    );
+}
+
+#[test]
+fn test_quiet_single_expression() {
+    let normal = calculator()
+        .args(["--verbose", "2 + 3 * 4"])
+        .output()
+        .unwrap();
+    let quiet = calculator()
+        .args(["--verbose", "--quiet", "2 + 3 * 4"])
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&normal.stdout),
+        "3 * 4 = 12\n2 + 12 = 14\n14\n"
+    );
+    assert_eq!(String::from_utf8_lossy(&quiet.stdout), "14\n");
+
+    let output = calculator().args(["--quiet", "5 / 0"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Error: Cannot divide by zero\n"
+    );
+}
+
+#[test]
+fn test_file_batch_mode_missing_file() {
+    let output = calculator()
+        .args(["--file", "/nonexistent/calcs.txt"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Error: Could not read"));
+}
+
+#[test]
+fn test_serve_answers_each_line() {
+    let mut child = calculator()
+        .arg("--serve")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+
+    // Each answer must arrive before the next line is sent.
+    let mut answers = Vec::new();
+    for line in ["5 + 5", "5 / 0", "x = ans * 2", "x > 15", "1 +", "x / 4"] {
+        writeln!(stdin, "{}", line).unwrap();
+        let mut answer = String::new();
+        stdout.read_line(&mut answer).unwrap();
+        answers.push(answer);
+    }
+    drop(stdin);
+
+    assert_eq!(
+        answers,
+        [
+            "10\n",
+            "ERR: Cannot divide by zero\n",
+            "20\n",
+            "true\n",
+            "ERR: Unexpected end of input\n",
+            "5\n"
+        ]
+    );
+    assert!(child.wait().unwrap().success());
+}
+
+/// Runs the calculator with `input` piped to stdin and returns its stdout.
+fn run_piped(input: &str) -> String {
+    let mut child = calculator()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn test_piped_lines_print_only_results() {
+    assert_eq!(run_piped("5 + 5\n2 + 3 * 4\n(1 + 1) ^ 3\n"), "10\n14\n8\n");
+}
+
+#[test]
+fn test_piped_input_without_trailing_newline() {
+    assert_eq!(run_piped("1 + 1\n2 + 2"), "2\n4\n");
+}
+
+#[test]
+fn test_piped_input_stops_at_quit() {
+    assert_eq!(run_piped("1 + 1\nq\n2 + 2\n"), "2\n");
+    assert_eq!(run_piped("1 + 1\n  EXIT \n2 + 2\n"), "2\n");
+}
+
+#[test]
+fn test_piped_mode_switch_to_degrees() {
+    assert_eq!(
+        run_piped("cos(0)\nmode deg\nsin(90)\nmode rad\nsin(0)\n"),
+        "1\n1\n0\n"
+    );
+}
+
+#[test]
+fn test_piped_ans_chains_results() {
+    assert_eq!(run_piped("ans + 1\n5 + 5\nans * 2\n"), "1\n10\n20\n");
+}
+
+#[test]
+fn test_piped_leading_operator_uses_ans() {
+    assert_eq!(run_piped("10\n+ 5\n* 2\n- 5\n-5\n"), "10\n15\n30\n25\n-5\n");
+}
+
+#[test]
+fn test_piped_variable_assignment() {
+    assert_eq!(run_piped("x = 5\nx * 3\n"), "x = 5\n15\n");
+}
+
+#[test]
+fn test_piped_history_and_recall() {
+    assert_eq!(
+        run_piped("1 + 1\n2 * 3\nhistory\n!1\n"),
+        "2\n6\n1: 1 + 1 = 2\n2: 2 * 3 = 6\n2\n"
+    );
+}
+
+#[test]
+fn test_piped_precision() {
+    assert_eq!(
+        run_piped("10 / 3\nprecision 2\n10 / 3\nprecision full\n1 / 4\n"),
+        "3.3333333333333335\n3.33\n0.25\n"
+    );
+}
+
+#[test]
+fn test_piped_output_base() {
+    assert_eq!(
+        run_piped("base hex\n255\n5 / 2\nbase bin\n10\nbase dec\n10\n"),
+        "0xff\n2.5\n0b1010\n10\n"
+    );
+}
+
+#[test]
+fn test_piped_clear_all_resets_state() {
+    assert_eq!(
+        run_piped("5 + 5\nclear\nans\nclear all\nans\nhistory\n"),
+        "10\n10\n0\n1: ans = 0\n"
+    );
+}
+
+#[test]
+fn test_piped_help() {
+    let output = run_piped("help\n");
+    assert!(output.contains('+'));
+    assert!(output.contains("sqrt"));
+    assert!(output.contains("quit"));
+}
+
+#[test]
+fn test_piped_output_is_not_colored() {
+    let mut child = calculator()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"5 + 5\n5 / 0\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "10\n");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Error: Cannot divide by zero\n"
+    );
+}
+
+#[test]
+fn test_piped_precision_loss_warning() {
+    let mut child = calculator()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"9007199254740993 - 1\n1000000 - 1\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "9007199254740991\n999999\n"
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Warning: 9007199254740993 can't be represented exactly and is rounded to 9007199254740992\n"
+    );
+}
+
+#[test]
+fn test_no_color_flag_is_accepted() {
+    let mut child = calculator()
+        .arg("--no-color")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"2 * 3\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "6\n");
+}
+
+#[test]
+fn test_piped_tape_mode() {
+    assert_eq!(
+        run_piped("mode tape\n+5\n+3\ntotal\nreset\n-2\n"),
+        "5\n8\n8\n-2\n"
+    );
+}
+
+#[test]
+fn test_piped_rpn_mode() {
+    assert_eq!(run_piped("mode rpn\n5 5 +\n2 3 4 * +\n"), "10\n14\n");
+}
+
+#[test]
+fn test_piped_last_error() {
+    assert_eq!(
+        run_piped("last_error\n5 / 0\n1 + 1\nlast_error\n"),
+        "No errors yet\n2\nError: Cannot divide by zero\n"
+    );
+}
+
+#[test]
+fn test_piped_memory_register() {
+    assert_eq!(
+        run_piped("MR\n10 * 2\nM+\n5\nM-\nMR\nmr + 1\nMC\nMR\n"),
+        "0\n20\n5\n15\n16\n0\n"
+    );
+}
+
+#[test]
+fn test_piped_line_continuation() {
+    assert_eq!(run_piped("(1 + 2) * \\\n4\n"), "12\n");
+    assert_eq!(run_piped("1 + \\\n2 + \\  \n3\nans\n"), "6\n6\n");
+}
+
+#[test]
+fn test_piped_continuation_at_end_of_input() {
+    let mut child = calculator()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"1 + \\\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Error: Input ended after a line continued with '\\'\n"
+    );
+}
+
+#[test]
+fn test_piped_blank_lines_are_ignored() {
+    assert_eq!(run_piped("1 + 1\n\n   \n2 + 2\n"), "2\n4\n");
+}
+
+#[test]
+fn test_piped_chained_operations() {
+    assert_eq!(run_piped("5 + 3 - 2\n1 + 2 + 3 + 4\n"), "6\n10\n");
+}
+
+#[test]
+fn test_piped_integer_mode() {
+    assert_eq!(
+        run_piped("mode int\n99999999999 * 99999999999\n7 // 2\nsqrt(16)\n"),
+        "9999999999800000000001\n3\n4\n"
+    );
+}
+
+#[test]
+fn test_integer_mode_uses_display_settings() {
+    assert_eq!(
+        run_piped("mode int\nbase hex\n255\nbase dec\nsep on\n99999999999 * 99999999999\n"),
+        "0xff\n9,999,999,999,800,000,000,001\n"
+    );
+}
+
+#[test]
+fn test_integer_mode_warns_about_fallback() {
+    let mut child = calculator()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"mode int\n9007199254740993 + 1\nsqrt(16)\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "9007199254740994\n4\n"
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Warning: not exact in integer mode, evaluated with floating point\n"
+    );
+}
+
+#[test]
+fn test_seed_makes_rand_reproducible() {
+    let run = || {
+        calculator()
+            .args(["--seed", "42", "--eval", "rand(); rand(); rand(10, 20)"])
+            .output()
+            .expect("failed to run calculator")
+    };
+    let (first, second) = (run(), run());
+    assert!(first.status.success());
+    assert_eq!(first.stdout, second.stdout);
+
+    let stdout = String::from_utf8_lossy(&first.stdout);
+    let numbers: Vec<f64> = stdout.lines().map(|line| line.parse().unwrap()).collect();
+    assert_eq!(numbers.len(), 3);
+    assert!((0.0..1.0).contains(&numbers[0]));
+    assert!((0.0..1.0).contains(&numbers[1]));
+    assert!((10.0..20.0).contains(&numbers[2]));
+}
+
+#[test]
+fn test_invalid_seed() {
+    let output = calculator()
+        .args(["--seed", "abc", "rand()"])
+        .output()
+        .expect("failed to run calculator");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid seed 'abc'"));
+}
+
+#[test]
+fn test_interactive_continues_after_expression() {
+    let mut child = calculator()
+        .args(["--interactive", "2 + 3"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"ans * 2\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "5\n10\n");
+}
+
+#[test]
+fn test_short_interactive_flag_without_expression() {
+    let mut child = calculator()
+        .arg("-i")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"1 + 1\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "2\n");
+}